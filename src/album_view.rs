@@ -0,0 +1,515 @@
+// album_view.rs
+use adw::prelude::*;
+use gtk::subclass::prelude::*;
+use gettextrs::{gettext, ngettext};
+use gtk::glib::subclass::Signal;
+use gtk::{gdk, gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use crate::photo_manager::{Album, PhotoManager};
+use crate::storage::StorageError;
+use crate::texture_loader::TextureLoader;
+use crate::NotiaWindow;
+
+/// What is dragged to reorder an album. A type of its own keeps text and
+/// files dropped from elsewhere from being taken for album photos.
+#[derive(Debug, Clone, glib::Boxed)]
+#[boxed_type(name = "NotiaAlbumPhoto")]
+struct AlbumPhoto {
+    album: i64,
+    photo: String,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/album_view.ui")]
+    pub struct AlbumView {
+        #[template_child]
+        pub cover_picture: TemplateChild<gtk::Picture>,
+        #[template_child]
+        pub name_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub description_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub count_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub edit_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub grid_view: TemplateChild<gtk::GridView>,
+
+        pub album_id: Cell<Option<i64>>,
+        /// Photo shown by `cover_picture`.
+        pub cover: RefCell<Option<String>>,
+        /// Photos of the shown album, in the order of `model`.
+        pub photos: RefCell<Vec<String>>,
+        /// The photos as `gio::File`s, shown by `grid_view`.
+        pub model: gio::ListStore,
+        pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
+        pub texture_loader: RefCell<Option<Rc<TextureLoader>>>,
+    }
+
+    impl Default for AlbumView {
+        fn default() -> Self {
+            Self {
+                cover_picture: TemplateChild::default(),
+                name_label: TemplateChild::default(),
+                description_label: TemplateChild::default(),
+                count_label: TemplateChild::default(),
+                edit_button: TemplateChild::default(),
+                stack: TemplateChild::default(),
+                grid_view: TemplateChild::default(),
+                album_id: Cell::new(None),
+                cover: RefCell::new(None),
+                photos: RefCell::new(Vec::new()),
+                model: gio::ListStore::new::<gio::File>(),
+                photo_manager: RefCell::new(None),
+                texture_loader: RefCell::new(None),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AlbumView {
+        const NAME: &'static str = "AlbumView";
+        type Type = super::AlbumView;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for AlbumView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.setup_grid();
+            obj.setup_callbacks();
+            obj.setup_actions();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![Signal::builder("photo-activated")
+                    .param_types([String::static_type()])
+                    .build()]
+            })
+        }
+    }
+
+    impl WidgetImpl for AlbumView {}
+    impl BoxImpl for AlbumView {}
+}
+
+glib::wrapper! {
+    pub struct AlbumView(ObjectSubclass<imp::AlbumView>) @extends gtk::Widget, gtk::Box;
+}
+
+impl AlbumView {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    pub fn set_photo_manager(&self, manager: Rc<RefCell<PhotoManager>>) {
+        *self.imp().photo_manager.borrow_mut() = Some(manager);
+    }
+
+    pub fn set_texture_loader(&self, loader: Rc<TextureLoader>) {
+        *self.imp().texture_loader.borrow_mut() = Some(loader);
+    }
+
+    /// Called with the path of a photo the user clicked.
+    pub fn connect_photo_activated<F: Fn(&Self, &str) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "photo-activated",
+            false,
+            glib::closure_local!(move |view: &AlbumView, photo: String| callback(view, &photo)),
+        )
+    }
+
+    pub fn show_album(&self, id: Option<i64>) {
+        self.imp().album_id.set(id);
+        self.refresh();
+    }
+
+    /// Shows the album again after it changed.
+    pub fn refresh(&self) {
+        let imp = self.imp();
+        let album = self.album().unwrap_or_else(|| Album {
+            id: 0,
+            name: String::new(),
+            description: String::new(),
+            cover: None,
+            photos: Vec::new(),
+        });
+
+        imp.name_label.set_text(&album.name);
+        imp.description_label.set_text(&album.description);
+        imp.description_label.set_visible(!album.description.is_empty());
+        let count = album.photos.len();
+        imp.count_label.set_text(
+            &ngettext("{} photo", "{} photos", count as u32).replace("{}", &count.to_string()),
+        );
+        match album.cover_photo() {
+            Some(cover) => {
+                imp.cover_picture.set_visible(true);
+                imp.cover.replace(Some(cover.to_string()));
+                let wanted = cover.to_string();
+                self.show_thumbnail(&imp.cover_picture, cover, glib::clone!(
+                    #[weak(rename_to = view)]
+                    self,
+                    #[upgrade_or]
+                    false,
+                    move || view.imp().cover.borrow().as_deref() == Some(wanted.as_str())
+                ));
+            }
+            None => {
+                imp.cover.replace(None);
+                imp.cover_picture.set_visible(false);
+            }
+        }
+
+        // Yalnızca değişen aralığı yenile, diğer kutucuklar yerinde kalsın
+        let mut photos = imp.photos.borrow_mut();
+        let same_start = photos.iter().zip(&album.photos).take_while(|(old, new)| old == new).count();
+        let same_end = photos[same_start..]
+            .iter()
+            .rev()
+            .zip(album.photos[same_start..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let removed = photos.len() - same_start - same_end;
+        let added: Vec<gio::File> = album.photos[same_start..album.photos.len() - same_end]
+            .iter()
+            .map(gio::File::for_path)
+            .collect();
+        *photos = album.photos;
+        drop(photos);
+        if removed > 0 || !added.is_empty() {
+            imp.model.splice(same_start as u32, removed as u32, &added);
+        }
+        imp.stack.set_visible_child_name(if count == 0 { "empty" } else { "photos" });
+    }
+
+    fn album(&self) -> Option<Album> {
+        let imp = self.imp();
+        let id = imp.album_id.get()?;
+        let manager = imp.photo_manager.borrow().clone()?;
+        let album = manager.borrow().album(id).cloned();
+        album
+    }
+
+    fn setup_grid(&self) {
+        let imp = self.imp();
+        let factory = gtk::SignalListItemFactory::new();
+
+        factory.connect_setup(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                item.set_child(Some(&view.create_tile(item)));
+            }
+        ));
+
+        factory.connect_bind(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                view.bind_tile(item);
+            }
+        ));
+
+        // Görünümden çıkan kutucukların bekleyen çözümlemelerini iptal et
+        factory.connect_unbind(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let loader = view.imp().texture_loader.borrow().clone();
+                if let (Some(loader), Some(photo)) = (loader, item_photo(item)) {
+                    loader.cancel(&photo);
+                }
+            }
+        ));
+
+        imp.grid_view.set_factory(Some(&factory));
+        imp.grid_view.set_model(Some(&gtk::NoSelection::new(Some(imp.model.clone()))));
+    }
+
+    fn setup_callbacks(&self) {
+        let imp = self.imp();
+        imp.grid_view.connect_activate(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, position| {
+                let photo = view.imp().photos.borrow().get(position as usize).cloned();
+                if let Some(photo) = photo {
+                    view.emit_by_name::<()>("photo-activated", &[&photo]);
+                }
+            }
+        ));
+        imp.edit_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_| {
+                view.edit_album();
+            }
+        ));
+    }
+
+    /// Actions of the photo menus, taking the photo path as target.
+    fn setup_actions(&self) {
+        let group = gio::SimpleActionGroup::new();
+        let handlers: [(&str, fn(&Self, &str)); 2] = [
+            ("set-cover", Self::set_cover),
+            ("remove", Self::remove_photo),
+        ];
+        for (name, handler) in handlers {
+            let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = view)]
+                self,
+                move |_, parameter| {
+                    if let Some(photo) = parameter.and_then(|parameter| parameter.str()) {
+                        handler(&view, photo);
+                    }
+                }
+            ));
+            group.add_action(&action);
+        }
+        self.insert_action_group("album-view", Some(&group));
+    }
+
+    /// A thumbnail with a photo menu that can be dragged onto another
+    /// thumbnail to change the album order.
+    fn create_tile(&self, item: &gtk::ListItem) -> gtk::Overlay {
+        let picture = gtk::Picture::builder()
+            .content_fit(gtk::ContentFit::Cover)
+            .width_request(160)
+            .height_request(120)
+            .build();
+        let tile = gtk::Overlay::builder().child(&picture).build();
+
+        let menu_button = gtk::MenuButton::builder()
+            .icon_name("view-more-symbolic")
+            .tooltip_text(gettext("Photo Menu"))
+            .halign(gtk::Align::End)
+            .valign(gtk::Align::Start)
+            .margin_top(6)
+            .margin_end(6)
+            .build();
+        menu_button.add_css_class("osd");
+        menu_button.add_css_class("circular");
+        tile.add_overlay(&menu_button);
+
+        // Sürükle bırak ile sıralama
+        let drag_source = gtk::DragSource::new();
+        drag_source.set_actions(gdk::DragAction::MOVE);
+        drag_source.connect_prepare(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            #[weak]
+            item,
+            #[upgrade_or]
+            None,
+            move |_, _, _| {
+                let dragged = AlbumPhoto {
+                    album: view.imp().album_id.get()?,
+                    photo: item_photo(&item)?,
+                };
+                Some(gdk::ContentProvider::for_value(&dragged.to_value()))
+            }
+        ));
+        drag_source.connect_drag_begin(glib::clone!(
+            #[weak]
+            picture,
+            move |source, _| {
+                source.set_icon(Some(&gtk::WidgetPaintable::new(Some(&picture))), 0, 0);
+            }
+        ));
+        tile.add_controller(drag_source);
+
+        let drop_target = gtk::DropTarget::new(AlbumPhoto::static_type(), gdk::DragAction::MOVE);
+        drop_target.connect_drop(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            #[weak]
+            item,
+            #[upgrade_or]
+            false,
+            move |_, value, _, _| {
+                let (Ok(dragged), Some(target)) = (value.get::<AlbumPhoto>(), item_photo(&item)) else {
+                    return false;
+                };
+                view.move_photo(&dragged, &target)
+            }
+        ));
+        tile.add_controller(drop_target);
+        tile
+    }
+
+    fn bind_tile(&self, item: &gtk::ListItem) {
+        let (Some(tile), Some(photo)) = (item.child().and_downcast::<gtk::Overlay>(), item_photo(item)) else {
+            return;
+        };
+        let name = Path::new(&photo).file_name().map(|name| name.to_string_lossy().to_string());
+        tile.set_tooltip_text(name.as_deref());
+
+        let target = photo.to_variant();
+        let menu = gio::Menu::new();
+        for (label, action) in [
+            (gettext("Use as _Cover"), "album-view.set-cover"),
+            (gettext("_Remove from Album"), "album-view.remove"),
+        ] {
+            let menu_item = gio::MenuItem::new(Some(label.as_str()), None);
+            menu_item.set_action_and_target_value(Some(action), Some(&target));
+            menu.append_item(&menu_item);
+        }
+        if let Some(menu_button) = tile.last_child().and_downcast::<gtk::MenuButton>() {
+            menu_button.set_menu_model(Some(&menu));
+        }
+
+        let Some(picture) = tile.child().and_downcast::<gtk::Picture>() else {
+            return;
+        };
+        // The tile may show another photo by the time the thumbnail is ready
+        let file = item.item();
+        let item = item.downgrade();
+        self.show_thumbnail(&picture, &photo, move || {
+            item.upgrade().and_then(|item| item.item()) == file
+        });
+    }
+
+    fn show_thumbnail<F>(&self, picture: &gtk::Picture, photo: &str, still_wanted: F)
+    where
+        F: Fn() -> bool + 'static,
+    {
+        let imp = self.imp();
+        let Some(loader) = imp.texture_loader.borrow().clone() else {
+            return;
+        };
+        let orientation = imp
+            .photo_manager
+            .borrow()
+            .as_ref()
+            .map(|manager| manager.borrow().orientation(photo))
+            .unwrap_or_default();
+        loader.show_thumbnail(picture, photo, orientation, still_wanted);
+    }
+
+    /// Moves `dragged` to the place of the photo it was dropped on, if both
+    /// belong to the album shown.
+    fn move_photo(&self, dragged: &AlbumPhoto, target: &str) -> bool {
+        let imp = self.imp();
+        if imp.album_id.get() != Some(dragged.album) || dragged.photo == target {
+            return false;
+        }
+        let photos = imp.photos.borrow();
+        let index = photos.iter().position(|photo| photo == target);
+        let (Some(index), true) = (index, photos.contains(&dragged.photo)) else {
+            return false;
+        };
+        drop(photos);
+        self.update_album(|manager| manager.move_in_album(dragged.album, &dragged.photo, index));
+        true
+    }
+
+    fn set_cover(&self, photo: &str) {
+        if let Some(id) = self.imp().album_id.get() {
+            self.update_album(|manager| manager.set_album_cover(id, Some(photo)));
+        }
+    }
+
+    fn remove_photo(&self, photo: &str) {
+        if let Some(id) = self.imp().album_id.get() {
+            self.update_album(|manager| manager.remove_from_album(id, &[photo.to_string()]));
+        }
+    }
+
+    fn edit_album(&self) {
+        let Some(album) = self.album() else {
+            return;
+        };
+        let name_entry = gtk::Entry::builder()
+            .text(album.name.as_str())
+            .placeholder_text(gettext("Name"))
+            .activates_default(true)
+            .build();
+        let description_entry = gtk::Entry::builder()
+            .text(album.description.as_str())
+            .placeholder_text(gettext("Description"))
+            .activates_default(true)
+            .build();
+        let fields = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        fields.append(&name_entry);
+        fields.append(&description_entry);
+
+        let alert = adw::AlertDialog::new(Some(&gettext("Edit Album")), None);
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("save", &gettext("_Save"))]);
+        alert.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+        alert.set_default_response(Some("save"));
+        alert.set_close_response("cancel");
+        alert.set_extra_child(Some(&fields));
+
+        name_entry.connect_changed(glib::clone!(
+            #[weak]
+            alert,
+            move |entry| {
+                alert.set_response_enabled("save", !entry.text().trim().is_empty());
+            }
+        ));
+        let id = album.id;
+        alert.connect_response(Some("save"), glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            #[weak]
+            name_entry,
+            #[weak]
+            description_entry,
+            move |_, _| {
+                let name = name_entry.text();
+                let description = description_entry.text();
+                view.update_album(|manager| manager.edit_album(id, name.trim(), description.trim()));
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    /// Runs a change on the photo manager and lets the window show the
+    /// result everywhere albums appear.
+    fn update_album<F>(&self, change: F)
+    where
+        F: FnOnce(&mut PhotoManager) -> Result<(), StorageError>,
+    {
+        let Some(manager) = self.imp().photo_manager.borrow().clone() else {
+            return;
+        };
+        let result = change(&mut manager.borrow_mut());
+        let window = self.root().and_downcast::<NotiaWindow>();
+        match (result, window) {
+            (Ok(()), Some(window)) => window.albums_changed(),
+            (Ok(()), None) => self.refresh(),
+            (Err(err), Some(window)) => window.show_storage_error(&err),
+            (Err(err), None) => NotiaWindow::report_storage_error(self, &err),
+        }
+    }
+}
+
+/// Path of the photo a grid item shows.
+fn item_photo(item: &gtk::ListItem) -> Option<String> {
+    let path = item.item().and_downcast::<gio::File>()?.path()?;
+    Some(path.to_string_lossy().to_string())
+}
@@ -4,6 +4,7 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gtk::{gio, glib};
 use crate::config::VERSION;
+use crate::orientation::Orientation;
 use crate::NotiaWindow;
 
 mod imp {
@@ -25,6 +26,10 @@ mod imp {
             obj.setup_gactions();
             obj.set_accels_for_action("app.quit", &["<primary>q"]);
             obj.set_accels_for_action("app.refresh", &["<primary>r"]);
+            obj.set_accels_for_action("app.preferences", &["<primary>comma"]);
+            obj.set_accels_for_action("win.search", &["<primary>f"]);
+            obj.set_accels_for_action("app.rotate_left", &["<primary>bracketleft"]);
+            obj.set_accels_for_action("app.rotate_right", &["<primary>bracketright"]);
         }
     }
 
@@ -87,7 +92,100 @@ impl NotiaApplication {
             })
             .build();
 
-        self.add_action_entries([quit_action, about_action, refresh_action, clear_notes_action]);
+        let restore_backup_action = gio::ActionEntry::builder("restore_backup")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.show_restore_dialog();
+                    }
+                }
+            })
+            .build();
+
+        let orphaned_notes_action = gio::ActionEntry::builder("orphaned_notes")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.show_orphaned_notes();
+                    }
+                }
+            })
+            .build();
+
+        let manage_tags_action = gio::ActionEntry::builder("manage_tags")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.show_tag_manager();
+                    }
+                }
+            })
+            .build();
+
+        let preferences_action = gio::ActionEntry::builder("preferences")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.show_preferences();
+                    }
+                }
+            })
+            .build();
+
+        let rotate_left_action = gio::ActionEntry::builder("rotate_left")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.transform_current_photo(Orientation::ROTATE_COUNTERCLOCKWISE);
+                    }
+                }
+            })
+            .build();
+
+        let rotate_right_action = gio::ActionEntry::builder("rotate_right")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.transform_current_photo(Orientation::ROTATE_CLOCKWISE);
+                    }
+                }
+            })
+            .build();
+
+        let flip_horizontal_action = gio::ActionEntry::builder("flip_horizontal")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.transform_current_photo(Orientation::FLIP_HORIZONTAL);
+                    }
+                }
+            })
+            .build();
+
+        let flip_vertical_action = gio::ActionEntry::builder("flip_vertical")
+            .activate(move |app: &Self, _, _| {
+                if let Some(window) = app.active_window() {
+                    if let Some(notia_window) = window.downcast_ref::<NotiaWindow>() {
+                        notia_window.transform_current_photo(Orientation::FLIP_VERTICAL);
+                    }
+                }
+            })
+            .build();
+
+        self.add_action_entries([
+            quit_action,
+            about_action,
+            refresh_action,
+            clear_notes_action,
+            restore_backup_action,
+            orphaned_notes_action,
+            manage_tags_action,
+            preferences_action,
+            rotate_left_action,
+            rotate_right_action,
+            flip_horizontal_action,
+            flip_vertical_action,
+        ]);
     }
 
     fn show_about(&self) {
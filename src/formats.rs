@@ -0,0 +1,399 @@
+// formats.rs
+use gettextrs::gettext;
+use gtk::prelude::*;
+use gtk::{gdk, gdk_pixbuf, gio, glib};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use crate::metadata;
+use crate::orientation::Orientation;
+
+/// How an image format is turned into something GTK can draw.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    /// Decoded by GTK itself.
+    Builtin,
+    /// Needs a gdk-pixbuf loader, which may not be installed.
+    PixbufLoader,
+    /// Camera RAW files; the JPEG preview embedded by the camera is shown.
+    EmbeddedPreview,
+}
+
+#[derive(Debug)]
+pub struct ImageFormat {
+    pub name: &'static str,
+    pub mime_types: &'static [&'static str],
+    pub extensions: &'static [&'static str],
+    pub renderer: Renderer,
+}
+
+/// Every image format Notia recognizes.
+pub static FORMATS: &[ImageFormat] = &[
+    ImageFormat { name: "JPEG", mime_types: &["image/jpeg"], extensions: &["jpg", "jpeg", "jpe", "jfif"], renderer: Renderer::Builtin },
+    ImageFormat { name: "PNG", mime_types: &["image/png"], extensions: &["png"], renderer: Renderer::Builtin },
+    ImageFormat { name: "TIFF", mime_types: &["image/tiff"], extensions: &["tif", "tiff"], renderer: Renderer::Builtin },
+    ImageFormat { name: "GIF", mime_types: &["image/gif"], extensions: &["gif"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "BMP", mime_types: &["image/bmp", "image/x-bmp"], extensions: &["bmp"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "WebP", mime_types: &["image/webp"], extensions: &["webp"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "SVG", mime_types: &["image/svg+xml"], extensions: &["svg"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "HEIC", mime_types: &["image/heif", "image/heic"], extensions: &["heic", "heif"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "AVIF", mime_types: &["image/avif"], extensions: &["avif"], renderer: Renderer::PixbufLoader },
+    ImageFormat { name: "Canon CR2", mime_types: &["image/x-canon-cr2"], extensions: &["cr2"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Nikon NEF", mime_types: &["image/x-nikon-nef", "image/x-nikon-nrw"], extensions: &["nef", "nrw"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Sony ARW", mime_types: &["image/x-sony-arw"], extensions: &["arw"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "DNG", mime_types: &["image/x-adobe-dng"], extensions: &["dng"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Olympus ORF", mime_types: &["image/x-olympus-orf"], extensions: &["orf"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Panasonic RW2", mime_types: &["image/x-panasonic-rw2", "image/x-panasonic-raw2"], extensions: &["rw2"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Pentax PEF", mime_types: &["image/x-pentax-pef"], extensions: &["pef"], renderer: Renderer::EmbeddedPreview },
+    ImageFormat { name: "Fujifilm RAF", mime_types: &["image/x-fuji-raf"], extensions: &["raf"], renderer: Renderer::EmbeddedPreview },
+];
+
+/// How many bytes are read when the file name alone does not tell the type.
+const SNIFF_LENGTH: usize = 4096;
+
+pub fn format_for_mime_type(mime_type: &str) -> Option<&'static ImageFormat> {
+    FORMATS.iter().find(|format| format.mime_types.contains(&mime_type))
+}
+
+pub fn format_for_extension(path: &Path) -> Option<&'static ImageFormat> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    FORMATS.iter().find(|format| format.extensions.contains(&extension.as_str()))
+}
+
+/// Detects the image format of a file from its content type, reading the
+/// start of the file when the name is not conclusive.
+pub fn detect(path: &Path) -> Option<&'static ImageFormat> {
+    let (content_type, uncertain) = gio::content_type_guess(Some(path), &[]);
+    if let Some(format) = format_for_content_type(&content_type) {
+        return Some(format);
+    }
+    if uncertain {
+        let mut head = Vec::with_capacity(SNIFF_LENGTH);
+        if let Ok(file) = File::open(path) {
+            let _ = file.take(SNIFF_LENGTH as u64).read_to_end(&mut head);
+        }
+        let (content_type, _) = gio::content_type_guess(Some(path), &head);
+        if let Some(format) = format_for_content_type(&content_type) {
+            return Some(format);
+        }
+    }
+    // shared-mime-info may not know every RAW variant
+    format_for_extension(path)
+}
+
+fn format_for_content_type(content_type: &str) -> Option<&'static ImageFormat> {
+    let mime_type = gio::content_type_get_mime_type(content_type)?;
+    format_for_mime_type(&mime_type)
+}
+
+static PIXBUF_MIME_TYPES: Lazy<HashSet<String>> = Lazy::new(|| {
+    gdk_pixbuf::Pixbuf::formats()
+        .iter()
+        .flat_map(|format| format.mime_types())
+        .map(|mime_type| mime_type.to_string())
+        .collect()
+});
+
+/// Whether a loader for the format is available on this system. RAW files
+/// additionally need an embedded preview, see [`raw_preview`].
+pub fn can_render(format: &ImageFormat) -> bool {
+    match format.renderer {
+        Renderer::Builtin | Renderer::EmbeddedPreview => true,
+        Renderer::PixbufLoader => format
+            .mime_types
+            .iter()
+            .any(|mime_type| PIXBUF_MIME_TYPES.contains(*mime_type)),
+    }
+}
+
+/// Decodes the whole image at `path` on a worker thread, turned by
+/// `orientation`. `None` if the format cannot be displayed.
+pub async fn load_texture(path: &Path, orientation: Orientation) -> Option<gdk::Texture> {
+    let path = path.to_path_buf();
+    gio::spawn_blocking(move || {
+        let pixbuf = load_upright(&path)?;
+        Some(gdk::Texture::for_pixbuf(&orientation.apply(&pixbuf)))
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// Decodes the whole image at `path`, turned the way its EXIF orientation
+/// asks. `None` if the format cannot be displayed.
+pub fn load_upright(path: &Path) -> Option<gdk_pixbuf::Pixbuf> {
+    let format = detect(path)?;
+    if format.renderer == Renderer::EmbeddedPreview {
+        // Gömülü önizlemede yön etiketi genelde yoktur, RAW dosyasınınki geçerlidir
+        let preview = raw_preview(path)?;
+        let stream = gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(preview));
+        let pixbuf = gdk_pixbuf::Pixbuf::from_stream(&stream, gio::Cancellable::NONE).ok()?;
+        return Some(metadata::read_orientation(path).apply(&pixbuf));
+    }
+    if !can_render(format) {
+        return None;
+    }
+    let pixbuf = gdk_pixbuf::Pixbuf::from_file(path).ok()?;
+    Some(pixbuf.apply_embedded_orientation().unwrap_or(pixbuf))
+}
+
+/// Shows an icon in place of an image that cannot be displayed.
+pub fn show_placeholder(picture: &gtk::Picture) {
+    picture.set_paintable(Some(&icon(picture, "image-missing-symbolic")));
+    picture.set_tooltip_text(Some(&gettext("This image format cannot be displayed")));
+}
+
+/// Shows an icon while the image is being loaded.
+pub fn show_loading(picture: &gtk::Picture) {
+    picture.set_paintable(Some(&icon(picture, "image-x-generic-symbolic")));
+    picture.set_tooltip_text(None);
+}
+
+fn icon(picture: &gtk::Picture, name: &str) -> gtk::IconPaintable {
+    gtk::IconTheme::for_display(&picture.display()).lookup_icon(
+        name,
+        &[],
+        64,
+        picture.scale_factor(),
+        gtk::TextDirection::None,
+        gtk::IconLookupFlags::empty(),
+    )
+}
+
+/// Largest IFD chain walked while looking for a RAW preview.
+const MAX_IFDS: usize = 32;
+
+/// Extracts the largest displayable JPEG preview embedded in a camera RAW
+/// file. TIFF-based formats (CR2, NEF, ARW, DNG, ORF, RW2, PEF) and RAF
+/// are supported.
+pub fn raw_preview(path: &Path) -> Option<Vec<u8>> {
+    let (mut file, (offset, length)) = locate_raw_preview(path)?;
+    let mut preview = vec![0u8; length as usize];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut preview).ok()?;
+    Some(preview)
+}
+
+/// Opens a RAW file and finds the offset and length of its preview.
+fn locate_raw_preview(path: &Path) -> Option<(File, (u64, u64))> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 16];
+    file.read_exact(&mut header).ok()?;
+
+    let candidates = if &header == b"FUJIFILMCCD-RAW " {
+        raf_preview_location(&mut file)?
+    } else {
+        tiff_preview_locations(&mut file, &header)?
+    };
+    let location = largest_jpeg(&mut file, candidates)?;
+    Some((file, location))
+}
+
+fn raf_preview_location(file: &mut File) -> Option<Vec<(u64, u64)>> {
+    let mut location = [0u8; 8];
+    file.seek(SeekFrom::Start(84)).ok()?;
+    file.read_exact(&mut location).ok()?;
+    let offset = u32::from_be_bytes(location[0..4].try_into().ok()?);
+    let length = u32::from_be_bytes(location[4..8].try_into().ok()?);
+    Some(vec![(offset as u64, length as u64)])
+}
+
+/// Collects the offset and length of every JPEG stored in the TIFF IFDs,
+/// either as a thumbnail (tags 0x0201/0x0202) or as a single JPEG strip.
+fn tiff_preview_locations(file: &mut File, header: &[u8; 16]) -> Option<Vec<(u64, u64)>> {
+    let big_endian = match &header[0..2] {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+    let read_u16 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    };
+
+    // 42 for TIFF, ORF and RW2 use their own magic numbers
+    if !matches!(read_u16(&header[2..4]), 42 | 0x4f52 | 0x5352 | 0x55) {
+        return None;
+    }
+
+    let mut candidates = Vec::new();
+    let mut pending = vec![read_u32(&header[4..8]) as u64];
+    let mut visited = HashSet::new();
+    while let Some(offset) = pending.pop() {
+        if offset == 0 || visited.len() >= MAX_IFDS || !visited.insert(offset) {
+            continue;
+        }
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut count = [0u8; 2];
+        if file.read_exact(&mut count).is_err() {
+            continue;
+        }
+        let mut entries = vec![0u8; read_u16(&count) as usize * 12 + 4];
+        if file.read_exact(&mut entries).is_err() {
+            continue;
+        }
+
+        let mut jpeg = (None, None);
+        let mut strip = (None, None);
+        let mut compression = 0;
+        for entry in entries.chunks_exact(12) {
+            let tag = read_u16(&entry[0..2]);
+            let field_type = read_u16(&entry[2..4]);
+            let count = read_u32(&entry[4..8]);
+            // SHORT değerler alanın ilk iki baytında durur
+            let value = if field_type == 3 { read_u16(&entry[8..10]) as u32 } else { read_u32(&entry[8..12]) };
+            match tag {
+                0x0103 => compression = value,
+                0x0111 if count == 1 => strip.0 = Some(value),
+                0x0117 if count == 1 => strip.1 = Some(value),
+                0x0201 => jpeg.0 = Some(value),
+                0x0202 => jpeg.1 = Some(value),
+                0x014a if count == 1 => pending.push(value as u64),
+                0x014a => {
+                    let mut sub_ifds = vec![0u8; count.min(MAX_IFDS as u32) as usize * 4];
+                    let position = file.stream_position().ok()?;
+                    if file.seek(SeekFrom::Start(value as u64)).is_ok() && file.read_exact(&mut sub_ifds).is_ok() {
+                        pending.extend(sub_ifds.chunks_exact(4).map(|sub_ifd| read_u32(sub_ifd) as u64));
+                    }
+                    file.seek(SeekFrom::Start(position)).ok()?;
+                }
+                _ => {}
+            }
+        }
+        if let (Some(offset), Some(length)) = jpeg {
+            candidates.push((offset as u64, length as u64));
+        }
+        if let (Some(offset), Some(length), 6 | 7) = (strip.0, strip.1, compression) {
+            candidates.push((offset as u64, length as u64));
+        }
+        pending.push(read_u32(&entries[entries.len() - 4..]) as u64);
+    }
+    Some(candidates)
+}
+
+/// Picks the largest candidate that is a JPEG ordinary decoders can show.
+/// Lossless JPEG, which RAW files use for the sensor data, is skipped.
+fn largest_jpeg(file: &mut File, mut candidates: Vec<(u64, u64)>) -> Option<(u64, u64)> {
+    const HEAD_LENGTH: u64 = 64 * 1024;
+    const MAX_PREVIEW_LENGTH: u64 = 64 * 1024 * 1024;
+
+    candidates.sort_by_key(|(_, length)| std::cmp::Reverse(*length));
+    for (offset, length) in candidates {
+        if length == 0 || length > MAX_PREVIEW_LENGTH {
+            continue;
+        }
+        let mut head = vec![0u8; length.min(HEAD_LENGTH) as usize];
+        if file.seek(SeekFrom::Start(offset)).is_err() || file.read_exact(&mut head).is_err() {
+            continue;
+        }
+        let end = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        if is_displayable_jpeg(&head) && offset + length <= end {
+            return Some((offset, length));
+        }
+    }
+    None
+}
+
+/// Whether `data` starts a baseline or progressive JPEG.
+fn is_displayable_jpeg(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return false;
+    }
+    let mut position = 2;
+    while position + 4 <= data.len() {
+        if data[position] != 0xff {
+            return false;
+        }
+        let marker = data[position + 1];
+        match marker {
+            0xc0..=0xc2 => return true,
+            0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => return false,
+            _ => {}
+        }
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        position += 2 + length;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_found_by_extension_and_mime_type() {
+        assert_eq!(format_for_extension(Path::new("/a/B.JPG")).map(|f| f.name), Some("JPEG"));
+        assert_eq!(format_for_extension(Path::new("/a/b.HEIC")).map(|f| f.name), Some("HEIC"));
+        assert_eq!(format_for_extension(Path::new("/a/b.dng")).map(|f| f.renderer), Some(Renderer::EmbeddedPreview));
+        assert!(format_for_extension(Path::new("/a/b.txt")).is_none());
+        assert!(format_for_extension(Path::new("/a/jpg")).is_none());
+        assert_eq!(format_for_mime_type("image/avif").map(|f| f.name), Some("AVIF"));
+        assert!(format_for_mime_type("text/plain").is_none());
+    }
+
+    #[test]
+    fn every_format_has_a_mime_type_and_extension() {
+        for format in FORMATS {
+            assert!(!format.mime_types.is_empty(), "{}", format.name);
+            assert!(!format.extensions.is_empty(), "{}", format.name);
+        }
+    }
+
+    #[test]
+    fn only_baseline_and_progressive_jpegs_are_displayable() {
+        let app0 = [0xff, 0xe0, 0x00, 0x04, 0x00, 0x00];
+        let jpeg = |sof: u8| [&[0xff, 0xd8][..], &app0, &[0xff, sof, 0x00, 0x02]].concat();
+        assert!(is_displayable_jpeg(&jpeg(0xc0)));
+        assert!(is_displayable_jpeg(&jpeg(0xc2)));
+        assert!(!is_displayable_jpeg(&jpeg(0xc3)));
+        assert!(!is_displayable_jpeg(b"not a jpeg"));
+    }
+
+    #[test]
+    fn raw_preview_reads_the_largest_embedded_jpeg() {
+        let small = [0xff, 0xd8, 0xff, 0xc0, 0x00, 0x02, 0xff, 0xd9];
+        let large = [0xff, 0xd8, 0xff, 0xc2, 0x00, 0x04, 0x01, 0x02, 0xff, 0xd9];
+        let lossless = [0xff, 0xd8, 0xff, 0xc3, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0xff, 0xd9];
+
+        // IFD0 with a thumbnail, a sub-IFD with a larger preview and a
+        // lossless JPEG strip that must be skipped
+        let ifd0 = 8u32;
+        let sub_ifd = ifd0 + 2 + 3 * 12 + 4;
+        let data = sub_ifd + 2 + 5 * 12 + 4;
+        let (small_at, large_at) = (data, data + small.len() as u32);
+        let lossless_at = large_at + large.len() as u32;
+
+        let entry = |tag: u16, field_type: u16, value: u32| {
+            [&tag.to_le_bytes()[..], &field_type.to_le_bytes(), &1u32.to_le_bytes(), &value.to_le_bytes()].concat()
+        };
+        let mut raw = b"II*\0".to_vec();
+        raw.extend(ifd0.to_le_bytes());
+        raw.extend(3u16.to_le_bytes());
+        raw.extend(entry(0x014a, 4, sub_ifd));
+        raw.extend(entry(0x0201, 4, small_at));
+        raw.extend(entry(0x0202, 4, small.len() as u32));
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(5u16.to_le_bytes());
+        raw.extend(entry(0x0103, 3, 7));
+        raw.extend(entry(0x0111, 4, lossless_at));
+        raw.extend(entry(0x0117, 4, lossless.len() as u32));
+        raw.extend(entry(0x0201, 4, large_at));
+        raw.extend(entry(0x0202, 4, large.len() as u32));
+        raw.extend(0u32.to_le_bytes());
+        raw.extend(small);
+        raw.extend(large);
+        raw.extend(lossless);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.dng");
+        std::fs::write(&path, &raw).unwrap();
+        let preview = raw_preview(&path);
+        assert_eq!(preview.as_deref(), Some(&large[..]));
+    }
+}
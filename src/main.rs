@@ -1,9 +1,26 @@
 // main.rs
+mod album_view;
 mod application;
 mod config;
 mod window;
 mod sidebar;
+mod formats;
+mod metadata;
+mod navigation;
+mod orientation;
 mod photo_manager;
+mod storage;
+mod texture_loader;
+mod thumbnails;
+mod orphaned_notes;
+mod preferences;
+mod query;
+mod search;
+mod sorting;
+mod tag_manager;
+mod tags;
+mod timeline;
+mod timeline_view;
 use self::application::NotiaApplication;
 use self::window::NotiaWindow;
 use self::sidebar::Sidebar;
@@ -0,0 +1,215 @@
+// metadata.rs
+use chrono::{DateTime, Local, NaiveDateTime};
+use exif::{Exif, In, Tag, Value};
+use gtk::gdk_pixbuf;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use crate::formats;
+use crate::orientation::Orientation;
+
+/// What is known about a photo file besides its note.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhotoMetadata {
+    pub taken_at: Option<NaiveDateTime>,
+    /// Last modification of the file, in local time.
+    pub modified: Option<NaiveDateTime>,
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    /// Exposure time in seconds.
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u32>,
+    /// Focal length in millimetres.
+    pub focal_length: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// How the camera says the image should be turned for display.
+    pub orientation: Orientation,
+    pub file_size: u64,
+    pub format: Option<&'static str>,
+}
+
+impl PhotoMetadata {
+    /// Exposure settings in the usual "1/250 s · f/2.8 · ISO 100 · 50 mm"
+    /// form, leaving out what the camera did not record.
+    pub fn exposure_summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(time) = self.exposure_time.filter(|time| *time > 0.0) {
+            if time < 1.0 {
+                parts.push(format!("1/{} s", (1.0 / time).round()));
+            } else {
+                parts.push(format!("{} s", trim_decimal(time)));
+            }
+        }
+        if let Some(f_number) = self.f_number {
+            parts.push(format!("f/{}", trim_decimal(f_number)));
+        }
+        if let Some(iso) = self.iso {
+            parts.push(format!("ISO {}", iso));
+        }
+        if let Some(focal_length) = self.focal_length {
+            parts.push(format!("{} mm", trim_decimal(focal_length)));
+        }
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
+
+    /// Size of the photo as it is displayed, i.e. after the orientation
+    /// has been applied.
+    pub fn dimensions(&self) -> Option<String> {
+        let (width, height) = (self.width?, self.height?);
+        if self.orientation.swaps_dimensions() {
+            Some(format!("{} × {}", height, width))
+        } else {
+            Some(format!("{} × {}", width, height))
+        }
+    }
+}
+
+/// Formats with at most one decimal, dropping a trailing ".0".
+fn trim_decimal(value: f64) -> String {
+    let rounded = (value * 10.0).round() / 10.0;
+    if rounded.fract() == 0.0 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{:.1}", rounded)
+    }
+}
+
+/// "Canon" + "Canon EOS R6" reads as "Canon EOS R6", not "Canon Canon EOS R6".
+fn camera_name(make: Option<String>, model: Option<String>) -> Option<String> {
+    match (make, model) {
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    }
+}
+
+fn parse_exif_date(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// Reads the metadata of a photo. Missing or unreadable EXIF data leaves
+/// the corresponding fields empty.
+pub fn read_metadata(path: &Path) -> PhotoMetadata {
+    let file_metadata = fs::metadata(path).ok();
+    let mut metadata = PhotoMetadata {
+        modified: file_metadata
+            .as_ref()
+            .and_then(|file_metadata| file_metadata.modified().ok())
+            .map(|modified| DateTime::<Local>::from(modified).naive_local()),
+        file_size: file_metadata.map(|file_metadata| file_metadata.len()).unwrap_or(0),
+        format: formats::detect(path).map(|format| format.name),
+        ..PhotoMetadata::default()
+    };
+
+    if let Some(exif) = read_exif(path) {
+        let ascii = |tag| ascii_field(&exif, tag);
+        metadata.taken_at = [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+            .into_iter()
+            .find_map(|tag| parse_exif_date(&ascii(tag)?));
+        metadata.camera = camera_name(ascii(Tag::Make), ascii(Tag::Model));
+        metadata.lens = ascii(Tag::LensModel);
+        metadata.exposure_time = rational_field(&exif, Tag::ExposureTime);
+        metadata.f_number = rational_field(&exif, Tag::FNumber);
+        metadata.focal_length = rational_field(&exif, Tag::FocalLength);
+        metadata.iso = uint_field(&exif, Tag::PhotographicSensitivity);
+        metadata.width = uint_field(&exif, Tag::PixelXDimension);
+        metadata.height = uint_field(&exif, Tag::PixelYDimension);
+        metadata.orientation = exif_orientation(&exif);
+    }
+
+    if metadata.width.is_none() || metadata.height.is_none() {
+        if let Some((_, width, height)) = gdk_pixbuf::Pixbuf::file_info(path) {
+            metadata.width = Some(width as u32);
+            metadata.height = Some(height as u32);
+        }
+    }
+    metadata
+}
+
+/// Reads only the EXIF orientation, e.g. of a RAW file whose embedded
+/// preview does not carry one.
+pub fn read_orientation(path: &Path) -> Orientation {
+    read_exif(path).map(|exif| exif_orientation(&exif)).unwrap_or_default()
+}
+
+fn exif_orientation(exif: &Exif) -> Orientation {
+    uint_field(exif, Tag::Orientation)
+        .and_then(|value| u16::try_from(value).ok())
+        .map(Orientation::from_exif)
+        .unwrap_or_default()
+}
+
+fn read_exif(path: &Path) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()
+}
+
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        _ => None,
+    }
+}
+
+fn uint_field(exif: &Exif, tag: Tag) -> Option<u32> {
+    exif.get_field(tag, In::PRIMARY)?.value.get_uint(0)
+}
+
+fn rational_field(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values.first().filter(|value| value.denom != 0).map(|value| value.to_f64()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposure_summary_skips_missing_values() {
+        let metadata = PhotoMetadata {
+            exposure_time: Some(0.004),
+            f_number: Some(2.8),
+            iso: Some(100),
+            focal_length: Some(50.0),
+            ..PhotoMetadata::default()
+        };
+        assert_eq!(metadata.exposure_summary().as_deref(), Some("1/250 s · f/2.8 · ISO 100 · 50 mm"));
+
+        let metadata = PhotoMetadata { exposure_time: Some(2.0), ..PhotoMetadata::default() };
+        assert_eq!(metadata.exposure_summary().as_deref(), Some("2 s"));
+        assert_eq!(PhotoMetadata::default().exposure_summary(), None);
+    }
+
+    #[test]
+    fn dimensions_follow_the_orientation() {
+        let metadata = PhotoMetadata { width: Some(4000), height: Some(3000), ..PhotoMetadata::default() };
+        assert_eq!(metadata.dimensions().as_deref(), Some("4000 × 3000"));
+        let metadata = PhotoMetadata { orientation: Orientation::from_exif(6), ..metadata };
+        assert_eq!(metadata.dimensions().as_deref(), Some("3000 × 4000"));
+    }
+
+    #[test]
+    fn camera_name_does_not_repeat_the_make() {
+        let name = |make: &str, model: &str| camera_name(Some(make.to_string()), Some(model.to_string()));
+        assert_eq!(name("Canon", "Canon EOS R6").as_deref(), Some("Canon EOS R6"));
+        assert_eq!(name("NIKON CORPORATION", "NIKON Z 6").as_deref(), Some("NIKON CORPORATION NIKON Z 6"));
+        assert_eq!(name("FUJIFILM", "X-T4").as_deref(), Some("FUJIFILM X-T4"));
+        assert_eq!(camera_name(None, Some("Pixel 8".to_string())).as_deref(), Some("Pixel 8"));
+    }
+
+    #[test]
+    fn exif_dates_are_parsed() {
+        let date = parse_exif_date("2024:07:14 18:03:59").unwrap();
+        assert_eq!(date.format("%Y-%m-%d %H:%M:%S").to_string(), "2024-07-14 18:03:59");
+        assert!(parse_exif_date("0000:00:00 00:00:00").is_none());
+    }
+}
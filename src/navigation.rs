@@ -0,0 +1,680 @@
+// navigation.rs
+use adw::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::glib::subclass::Signal;
+use gtk::{gio, glib, pango, CompositeTemplate};
+use gettextrs::gettext;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use crate::photo_manager::{PhotoManager, SmartAlbum};
+use crate::storage::StorageError;
+use crate::tags::{self, TagTree};
+use crate::NotiaWindow;
+
+/// A row of the album and tag lists.
+#[derive(Debug, Clone, PartialEq)]
+enum Entry {
+    SmartAlbum(i64),
+    Album(i64),
+    Tag(String),
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/navigation.ui")]
+    pub struct NavigationSidebar {
+        #[template_child]
+        pub library_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub all_photos_row: TemplateChild<gtk::ListBoxRow>,
+        #[template_child]
+        pub smart_album_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub new_album_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub album_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub tag_list: TemplateChild<gtk::ListBox>,
+
+        /// Ids of the albums in `smart_album_list`, by row.
+        pub smart_album_ids: RefCell<Vec<i64>>,
+        /// Ids of the albums in `album_list`, by row.
+        pub album_ids: RefCell<Vec<i64>>,
+        /// Rows of `tag_list`; expanding a tag inserts the tags below it.
+        pub tag_tree: RefCell<Option<gtk::TreeListModel>>,
+        /// Tags and photo counts shown in `tag_list`.
+        pub tags: Rc<RefCell<TagTree>>,
+        /// Photo count labels of the tag rows built so far, by tag.
+        pub tag_counts: Rc<RefCell<HashMap<String, gtk::Label>>>,
+        pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NavigationSidebar {
+        const NAME: &'static str = "NavigationSidebar";
+        type Type = super::NavigationSidebar;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for NavigationSidebar {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.setup_selection();
+            obj.setup_actions();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| vec![Signal::builder("selection-changed").build()])
+        }
+    }
+
+    impl WidgetImpl for NavigationSidebar {}
+    impl BoxImpl for NavigationSidebar {}
+}
+
+glib::wrapper! {
+    pub struct NavigationSidebar(ObjectSubclass<imp::NavigationSidebar>) @extends gtk::Widget, gtk::Box;
+}
+
+impl NavigationSidebar {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    pub fn set_photo_manager(&self, manager: Rc<RefCell<PhotoManager>>) {
+        *self.imp().photo_manager.borrow_mut() = Some(manager);
+        self.refresh();
+    }
+
+    /// Called when another album, or all photos, were selected.
+    pub fn connect_selection_changed<F: Fn(&Self) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "selection-changed",
+            false,
+            glib::closure_local!(move |navigation: &NavigationSidebar| callback(navigation)),
+        )
+    }
+
+    /// The selected smart album; `None` while all photos or an album are
+    /// shown.
+    pub fn selected_smart_album(&self) -> Option<SmartAlbum> {
+        let Some(Entry::SmartAlbum(id)) = self.selected_entry() else {
+            return None;
+        };
+        let manager = self.imp().photo_manager.borrow().clone()?;
+        let album = manager.borrow().smart_album(id).cloned();
+        album
+    }
+
+    /// Id of the selected album.
+    pub fn selected_album(&self) -> Option<i64> {
+        match self.selected_entry() {
+            Some(Entry::Album(id)) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The selected tag, whose photos are shown together with those of
+    /// the tags below it.
+    pub fn selected_tag(&self) -> Option<String> {
+        match self.selected_entry() {
+            Some(Entry::Tag(tag)) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds the album lists from the photo manager, e.g. after a backup
+    /// was restored.
+    pub fn refresh(&self) {
+        self.reload(None);
+    }
+
+    /// Rebuilds the tag tree after tags were added or removed, keeping
+    /// expanded tags open.
+    pub fn refresh_tags(&self) {
+        let imp = self.imp();
+        let Some(manager) = imp.photo_manager.borrow().clone() else {
+            return;
+        };
+        // Etiketler aynıysa yalnızca sayılar değişmiştir, ağacı yeniden kurma
+        let tree = manager.borrow().tag_tree();
+        if imp.tag_tree.borrow().is_some() && tree.same_tags(&imp.tags.borrow()) {
+            imp.tags.replace(tree);
+            self.update_tag_counts();
+            return;
+        }
+        self.reload_tags(self.selected_entry().as_ref());
+        self.select_fallback();
+    }
+
+    fn update_tag_counts(&self) {
+        let imp = self.imp();
+        let tree = imp.tags.borrow();
+        for (tag, count) in imp.tag_counts.borrow().iter() {
+            count.set_text(&tree.count(tag).to_string());
+        }
+    }
+
+    /// Asks for a name and creates an album holding `photos`. An album
+    /// created without photos is selected so it can be filled.
+    pub fn create_album(&self, photos: Vec<String>) {
+        self.ask_name(&gettext("New Album"), &gettext("C_reate"), "", glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |name: &str| {
+                let Some(manager) = navigation.imp().photo_manager.borrow().clone() else {
+                    return;
+                };
+                let result = {
+                    let mut manager = manager.borrow_mut();
+                    manager
+                        .create_album(name, "")
+                        .and_then(|id| manager.add_to_album(id, &photos).map(|_| id))
+                };
+                match result {
+                    Ok(id) => {
+                        navigation.notify_albums_changed();
+                        if photos.is_empty() {
+                            navigation.reload(Some(Entry::Album(id)));
+                        }
+                    }
+                    Err(err) => navigation.report_storage_error(&err),
+                }
+            }
+        ));
+    }
+
+    /// Asks for a name and saves `query` as a new smart album, which is
+    /// then selected.
+    pub fn save_search<F: Fn() + 'static>(&self, query: &str, on_saved: F) {
+        let query = query.to_string();
+        self.ask_name(&gettext("Save Search"), &gettext("_Save"), "", glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |name: &str| {
+                let Some(manager) = navigation.imp().photo_manager.borrow().clone() else {
+                    return;
+                };
+                let result = manager.borrow_mut().add_smart_album(name, &query);
+                match result {
+                    Ok(id) => {
+                        navigation.reload(Some(Entry::SmartAlbum(id)));
+                        on_saved();
+                    }
+                    Err(err) => navigation.report_storage_error(&err),
+                }
+            }
+        ));
+    }
+
+    fn lists(&self) -> [gtk::ListBox; 4] {
+        let imp = self.imp();
+        [imp.library_list.get(), imp.smart_album_list.get(), imp.album_list.get(), imp.tag_list.get()]
+    }
+
+    fn setup_selection(&self) {
+        let imp = self.imp();
+        // Listelerden yalnızca birinde seçim olur
+        for list in self.lists() {
+            list.connect_row_selected(glib::clone!(
+                #[weak(rename_to = navigation)]
+                self,
+                move |list, row| {
+                    if row.is_none() {
+                        return;
+                    }
+                    for other in navigation.lists() {
+                        if other != *list {
+                            other.unselect_all();
+                        }
+                    }
+                    navigation.emit_by_name::<()>("selection-changed", &[]);
+                }
+            ));
+        }
+        imp.library_list.select_row(Some(&*imp.all_photos_row));
+        imp.new_album_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |_| {
+                navigation.create_album(Vec::new());
+            }
+        ));
+    }
+
+    fn selected_entry(&self) -> Option<Entry> {
+        let imp = self.imp();
+        if let Some(row) = imp.smart_album_list.selected_row() {
+            let id = *imp.smart_album_ids.borrow().get(row.index() as usize)?;
+            return Some(Entry::SmartAlbum(id));
+        }
+        if let Some(row) = imp.album_list.selected_row() {
+            let id = *imp.album_ids.borrow().get(row.index() as usize)?;
+            return Some(Entry::Album(id));
+        }
+        let row = imp.tag_list.selected_row()?;
+        let tag = imp.tag_tree.borrow().as_ref()?.row(row.index() as u32)?.item()?;
+        Some(Entry::Tag(tag.downcast::<gtk::StringObject>().ok()?.string().into()))
+    }
+
+    /// Actions of the album menus, taking the album id as target.
+    fn setup_actions(&self) {
+        self.add_actions("smart-album", &[
+            ("rename", Self::rename_smart_album),
+            ("duplicate", Self::duplicate_smart_album),
+            ("move-up", Self::move_smart_album_up),
+            ("move-down", Self::move_smart_album_down),
+            ("delete", Self::delete_smart_album),
+        ]);
+        self.add_actions("album", &[
+            ("move-up", Self::move_album_up),
+            ("move-down", Self::move_album_down),
+            ("delete", Self::delete_album),
+        ]);
+    }
+
+    fn add_actions(&self, prefix: &str, handlers: &[(&str, fn(&Self, i64))]) {
+        let group = gio::SimpleActionGroup::new();
+        for &(name, handler) in handlers {
+            let action = gio::SimpleAction::new(name, Some(glib::VariantTy::INT64));
+            action.connect_activate(glib::clone!(
+                #[weak(rename_to = navigation)]
+                self,
+                move |_, parameter| {
+                    if let Some(id) = parameter.and_then(|parameter| parameter.get::<i64>()) {
+                        handler(&navigation, id);
+                    }
+                }
+            ));
+            group.add_action(&action);
+        }
+        self.insert_action_group(prefix, Some(&group));
+    }
+
+    /// Rebuilds the album rows, selecting `select` or keeping the current
+    /// selection. All photos are selected if the selected album is gone.
+    fn reload(&self, select: Option<Entry>) {
+        let imp = self.imp();
+        let Some(manager) = imp.photo_manager.borrow().clone() else {
+            return;
+        };
+        let selected = select.or_else(|| self.selected_entry());
+        // Etiket ağacı yeniden kurulurken seçim kaybolmasın
+        self.reload_tags(selected.as_ref());
+        let (smart_albums, albums) = {
+            let manager = manager.borrow();
+            (manager.smart_albums().to_vec(), manager.albums().to_vec())
+        };
+
+        imp.smart_album_list.remove_all();
+        *imp.smart_album_ids.borrow_mut() = smart_albums.iter().map(|album| album.id).collect();
+        for album in &smart_albums {
+            let row = Self::create_row("folder-saved-search-symbolic", &album.name, &Self::smart_album_menu(album.id));
+            row.set_tooltip_text(Some(&album.query));
+            imp.smart_album_list.append(&row);
+            if selected == Some(Entry::SmartAlbum(album.id)) {
+                imp.smart_album_list.select_row(Some(&row));
+            }
+        }
+
+        imp.album_list.remove_all();
+        *imp.album_ids.borrow_mut() = albums.iter().map(|album| album.id).collect();
+        for album in &albums {
+            let row = Self::create_row("folder-pictures-symbolic", &album.name, &Self::album_menu(album.id));
+            if !album.description.is_empty() {
+                row.set_tooltip_text(Some(&album.description));
+            }
+            imp.album_list.append(&row);
+            if selected == Some(Entry::Album(album.id)) {
+                imp.album_list.select_row(Some(&row));
+            }
+        }
+
+        self.select_fallback();
+    }
+
+    /// Selects all photos when the selected row is gone.
+    fn select_fallback(&self) {
+        let imp = self.imp();
+        if self.lists().iter().all(|list| list.selected_row().is_none()) {
+            imp.library_list.select_row(Some(&*imp.all_photos_row));
+        }
+    }
+
+    /// Fills the tag list with the top-level tags, opening the tags that
+    /// were expanded before and selecting `selected` if it is a tag.
+    fn reload_tags(&self, selected: Option<&Entry>) {
+        let imp = self.imp();
+        let Some(manager) = imp.photo_manager.borrow().clone() else {
+            return;
+        };
+        imp.tags.replace(manager.borrow().tag_tree());
+        let tree = imp.tags.clone();
+        let expanded = self.expanded_tags();
+
+        let tag_list = |tags: &[String]| gtk::StringList::new(&tags.iter().map(String::as_str).collect::<Vec<_>>());
+        let root = tag_list(tree.borrow().children(None));
+        let model = gtk::TreeListModel::new(root, false, false, glib::clone!(
+            #[strong]
+            tree,
+            move |item| {
+                let tag = item.downcast_ref::<gtk::StringObject>()?.string();
+                let tree = tree.borrow();
+                let children = tree.children(Some(tag.as_str()));
+                (!children.is_empty()).then(|| tag_list(children).upcast())
+            }
+        ));
+        imp.tag_counts.borrow_mut().clear();
+        let counts = imp.tag_counts.clone();
+        imp.tag_list.bind_model(Some(&model), move |item| {
+            let row = item.downcast_ref::<gtk::TreeListRow>().unwrap();
+            Self::create_tag_row(row, &tree.borrow(), &mut counts.borrow_mut()).upcast()
+        });
+        imp.tag_tree.replace(Some(model.clone()));
+
+        // Açılan her etiket altındaki etiketleri hemen arkasına ekler
+        let mut position = 0;
+        while let Some(row) = model.row(position) {
+            if let Some(tag) = row.item().and_downcast::<gtk::StringObject>().map(|tag| tag.string()) {
+                if expanded.iter().any(|expanded| expanded == tag.as_str()) {
+                    row.set_expanded(true);
+                }
+                if selected == Some(&Entry::Tag(tag.to_string())) {
+                    imp.tag_list.select_row(imp.tag_list.row_at_index(position as i32).as_ref());
+                }
+            }
+            position += 1;
+        }
+    }
+
+    fn expanded_tags(&self) -> Vec<String> {
+        let Some(model) = self.imp().tag_tree.borrow().clone() else {
+            return Vec::new();
+        };
+        (0..model.n_items())
+            .filter_map(|position| model.row(position))
+            .filter(|row| row.is_expanded())
+            .filter_map(|row| row.item().and_downcast::<gtk::StringObject>())
+            .map(|tag| tag.string().to_string())
+            .collect()
+    }
+
+    /// Builds the row of a tag and keeps its count label in `counts`, so
+    /// that the count can change without rebuilding the row.
+    fn create_tag_row(row: &gtk::TreeListRow, tree: &TagTree, counts: &mut HashMap<String, gtk::Label>) -> gtk::ListBoxRow {
+        let tag = row
+            .item()
+            .and_downcast::<gtk::StringObject>()
+            .map(|tag| tag.string().to_string())
+            .unwrap_or_default();
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let label = gtk::Label::builder()
+            .label(tags::leaf_name(&tag))
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        content.append(&label);
+        let count = gtk::Label::new(Some(&tree.count(&tag).to_string()));
+        count.add_css_class("dim-label");
+        count.add_css_class("caption");
+        content.append(&count);
+        counts.insert(tag.clone(), count);
+
+        let expander = gtk::TreeExpander::builder()
+            .list_row(row)
+            .child(&content)
+            .build();
+        gtk::ListBoxRow::builder()
+            .child(&expander)
+            .tooltip_text(tag.as_str())
+            .build()
+    }
+
+    fn create_row(icon: &str, name: &str, menu: &gio::Menu) -> gtk::ListBoxRow {
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        content.append(&gtk::Image::from_icon_name(icon));
+        let label = gtk::Label::builder()
+            .label(name)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(pango::EllipsizeMode::End)
+            .build();
+        content.append(&label);
+
+        let menu_button = gtk::MenuButton::builder()
+            .icon_name("view-more-symbolic")
+            .tooltip_text(gettext("Album Menu"))
+            .menu_model(menu)
+            .valign(gtk::Align::Center)
+            .build();
+        menu_button.add_css_class("flat");
+        content.append(&menu_button);
+
+        gtk::ListBoxRow::builder().child(&content).build()
+    }
+
+    /// A menu of `sections`, whose actions all take the album id.
+    fn menu(id: i64, sections: &[&[(String, &str)]]) -> gio::Menu {
+        let target = id.to_variant();
+        let section = |items: &[(String, &str)]| {
+            let section = gio::Menu::new();
+            for (label, action) in items {
+                let item = gio::MenuItem::new(Some(label.as_str()), None);
+                item.set_action_and_target_value(Some(*action), Some(&target));
+                section.append_item(&item);
+            }
+            section
+        };
+        let menu = gio::Menu::new();
+        for items in sections {
+            menu.append_section(None, &section(items));
+        }
+        menu
+    }
+
+    fn smart_album_menu(id: i64) -> gio::Menu {
+        Self::menu(id, &[
+            &[
+                (gettext("_Rename…"), "smart-album.rename"),
+                (gettext("D_uplicate"), "smart-album.duplicate"),
+            ],
+            &[
+                (gettext("Move _Up"), "smart-album.move-up"),
+                (gettext("Move _Down"), "smart-album.move-down"),
+            ],
+            &[(gettext("_Delete"), "smart-album.delete")],
+        ])
+    }
+
+    fn album_menu(id: i64) -> gio::Menu {
+        Self::menu(id, &[
+            &[
+                (gettext("Move _Up"), "album.move-up"),
+                (gettext("Move _Down"), "album.move-down"),
+            ],
+            &[(gettext("_Delete"), "album.delete")],
+        ])
+    }
+
+    /// Runs a change on the photo manager and shows the result.
+    fn update_albums<F>(&self, change: F)
+    where
+        F: FnOnce(&mut PhotoManager) -> Result<(), StorageError>,
+    {
+        let Some(manager) = self.imp().photo_manager.borrow().clone() else {
+            return;
+        };
+        let result = change(&mut manager.borrow_mut());
+        match result {
+            Ok(()) => self.notify_albums_changed(),
+            Err(err) => self.report_storage_error(&err),
+        }
+    }
+
+    /// Lets the window update everything that shows albums, this sidebar
+    /// included.
+    fn notify_albums_changed(&self) {
+        match self.root().and_downcast::<NotiaWindow>() {
+            Some(window) => window.albums_changed(),
+            None => self.reload(None),
+        }
+    }
+
+    fn smart_album(&self, id: i64) -> Option<(usize, SmartAlbum)> {
+        let manager = self.imp().photo_manager.borrow().clone()?;
+        let manager = manager.borrow();
+        manager
+            .smart_albums()
+            .iter()
+            .position(|album| album.id == id)
+            .map(|index| (index, manager.smart_albums()[index].clone()))
+    }
+
+    fn rename_smart_album(&self, id: i64) {
+        let Some((_, album)) = self.smart_album(id) else {
+            return;
+        };
+        self.ask_name(&gettext("Rename Smart Album"), &gettext("_Rename"), &album.name, glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |name: &str| {
+                navigation.update_albums(|manager| manager.rename_smart_album(id, name));
+            }
+        ));
+    }
+
+    fn duplicate_smart_album(&self, id: i64) {
+        let Some((_, album)) = self.smart_album(id) else {
+            return;
+        };
+        let name = gettext("{} (Copy)").replace("{}", &album.name);
+        self.update_albums(|manager| manager.duplicate_smart_album(id, &name).map(|_| ()));
+    }
+
+    fn move_smart_album_up(&self, id: i64) {
+        if let Some((index, _)) = self.smart_album(id).filter(|(index, _)| *index > 0) {
+            self.update_albums(|manager| manager.move_smart_album(id, index - 1));
+        }
+    }
+
+    fn move_smart_album_down(&self, id: i64) {
+        if let Some((index, _)) = self.smart_album(id) {
+            self.update_albums(|manager| manager.move_smart_album(id, index + 1));
+        }
+    }
+
+    fn delete_smart_album(&self, id: i64) {
+        let Some((_, album)) = self.smart_album(id) else {
+            return;
+        };
+        let alert = adw::AlertDialog::new(
+            Some(&gettext("Delete “{}”?").replace("{}", &album.name)),
+            Some(&gettext("Only the saved search is deleted; the photos and their notes stay.")),
+        );
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("delete", &gettext("_Delete"))]);
+        alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+        alert.connect_response(Some("delete"), glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |_, _| {
+                navigation.update_albums(|manager| manager.delete_smart_album(id));
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn album_position(&self, id: i64) -> Option<usize> {
+        let manager = self.imp().photo_manager.borrow().clone()?;
+        let position = manager.borrow().albums().iter().position(|album| album.id == id);
+        position
+    }
+
+    fn move_album_up(&self, id: i64) {
+        if let Some(index) = self.album_position(id).filter(|index| *index > 0) {
+            self.update_albums(|manager| manager.move_album(id, index - 1));
+        }
+    }
+
+    fn move_album_down(&self, id: i64) {
+        if let Some(index) = self.album_position(id) {
+            self.update_albums(|manager| manager.move_album(id, index + 1));
+        }
+    }
+
+    fn delete_album(&self, id: i64) {
+        let Some(manager) = self.imp().photo_manager.borrow().clone() else {
+            return;
+        };
+        let Some(name) = manager.borrow().album(id).map(|album| album.name.clone()) else {
+            return;
+        };
+        let alert = adw::AlertDialog::new(
+            Some(&gettext("Delete “{}”?").replace("{}", &name)),
+            Some(&gettext("Only the album is deleted; the photos and their notes stay.")),
+        );
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("delete", &gettext("_Delete"))]);
+        alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+        alert.connect_response(Some("delete"), glib::clone!(
+            #[weak(rename_to = navigation)]
+            self,
+            move |_, _| {
+                navigation.update_albums(|manager| manager.delete_album(id));
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    /// Asks for an album name and calls `on_accept` with it, trimmed.
+    fn ask_name<F: Fn(&str) + 'static>(&self, heading: &str, accept_label: &str, initial: &str, on_accept: F) {
+        let entry = gtk::Entry::builder()
+            .text(initial)
+            .activates_default(true)
+            .build();
+        let alert = adw::AlertDialog::new(Some(heading), None);
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("accept", accept_label)]);
+        alert.set_response_appearance("accept", adw::ResponseAppearance::Suggested);
+        alert.set_default_response(Some("accept"));
+        alert.set_close_response("cancel");
+        alert.set_response_enabled("accept", !initial.trim().is_empty());
+        alert.set_extra_child(Some(&entry));
+
+        entry.connect_changed(glib::clone!(
+            #[weak]
+            alert,
+            move |entry| {
+                alert.set_response_enabled("accept", !entry.text().trim().is_empty());
+            }
+        ));
+        alert.connect_response(Some("accept"), glib::clone!(
+            #[weak]
+            entry,
+            move |_, _| {
+                on_accept(entry.text().trim());
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn report_storage_error(&self, err: &StorageError) {
+        NotiaWindow::report_storage_error(self, err);
+    }
+}
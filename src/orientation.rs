@@ -0,0 +1,133 @@
+// orientation.rs
+use gtk::gdk_pixbuf::{self, PixbufRotation};
+use serde::{Deserialize, Serialize};
+
+/// One of the eight EXIF orientations: an optional horizontal mirror
+/// followed by a clockwise rotation. Also used for the rotate/flip
+/// override a user puts on top of the orientation stored in the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct Orientation {
+    mirrored: bool,
+    /// Clockwise rotation in quarter turns, 0..4.
+    quarter_turns: u8,
+}
+
+impl Orientation {
+    pub const NORMAL: Orientation = Orientation { mirrored: false, quarter_turns: 0 };
+    pub const ROTATE_CLOCKWISE: Orientation = Orientation { mirrored: false, quarter_turns: 1 };
+    pub const ROTATE_COUNTERCLOCKWISE: Orientation = Orientation { mirrored: false, quarter_turns: 3 };
+    pub const FLIP_HORIZONTAL: Orientation = Orientation { mirrored: true, quarter_turns: 0 };
+    pub const FLIP_VERTICAL: Orientation = Orientation { mirrored: true, quarter_turns: 2 };
+
+    /// Maps the value of the EXIF Orientation tag; anything outside 1..=8
+    /// is treated as upright.
+    pub fn from_exif(value: u16) -> Orientation {
+        Orientation::try_from(value).unwrap_or_default()
+    }
+
+    pub fn to_exif(self) -> u16 {
+        match (self.mirrored, self.quarter_turns) {
+            (false, 0) => 1,
+            (true, 0) => 2,
+            (false, 2) => 3,
+            (true, 2) => 4,
+            (true, 3) => 5,
+            (false, 1) => 6,
+            (true, 1) => 7,
+            _ => 8,
+        }
+    }
+
+    pub fn is_normal(self) -> bool {
+        self == Orientation::NORMAL
+    }
+
+    /// Whether width and height trade places.
+    pub fn swaps_dimensions(self) -> bool {
+        self.quarter_turns % 2 == 1
+    }
+
+    /// The orientation that results from applying `other` after `self`.
+    pub fn then(self, other: Orientation) -> Orientation {
+        // Aynalama, kendisinden önceki dönüşün yönünü tersine çevirir
+        let turns = if other.mirrored { 4 - self.quarter_turns } else { self.quarter_turns };
+        Orientation {
+            mirrored: self.mirrored != other.mirrored,
+            quarter_turns: (turns + other.quarter_turns) % 4,
+        }
+    }
+
+    /// Returns a transformed copy of `pixbuf`, or the pixbuf itself when
+    /// there is nothing to do.
+    pub fn apply(self, pixbuf: &gdk_pixbuf::Pixbuf) -> gdk_pixbuf::Pixbuf {
+        let mirrored = if self.mirrored { pixbuf.flip(true) } else { None };
+        let mirrored = mirrored.as_ref().unwrap_or(pixbuf);
+        let rotation = match self.quarter_turns {
+            1 => PixbufRotation::Clockwise,
+            2 => PixbufRotation::Upsidedown,
+            3 => PixbufRotation::Counterclockwise,
+            _ => return mirrored.clone(),
+        };
+        mirrored.rotate_simple(rotation).unwrap_or_else(|| mirrored.clone())
+    }
+}
+
+impl TryFrom<u16> for Orientation {
+    type Error = String;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        let (mirrored, quarter_turns) = match value {
+            1 => (false, 0),
+            2 => (true, 0),
+            3 => (false, 2),
+            4 => (true, 2),
+            5 => (true, 3),
+            6 => (false, 1),
+            7 => (true, 1),
+            8 => (false, 3),
+            _ => return Err(format!("invalid orientation {}", value)),
+        };
+        Ok(Orientation { mirrored, quarter_turns })
+    }
+}
+
+impl From<Orientation> for u16 {
+    fn from(orientation: Orientation) -> u16 {
+        orientation.to_exif()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exif_values_round_trip() {
+        for value in 1..=8 {
+            assert_eq!(Orientation::from_exif(value).to_exif(), value);
+        }
+        assert_eq!(Orientation::from_exif(0), Orientation::NORMAL);
+        assert_eq!(Orientation::from_exif(9), Orientation::NORMAL);
+        assert_eq!(Orientation::from_exif(6), Orientation::ROTATE_CLOCKWISE);
+        assert!(Orientation::from_exif(8).swaps_dimensions());
+    }
+
+    #[test]
+    fn transforms_compose_like_the_exif_table() {
+        let cw = Orientation::ROTATE_CLOCKWISE;
+        let ccw = Orientation::ROTATE_COUNTERCLOCKWISE;
+        let flip = Orientation::FLIP_HORIZONTAL;
+
+        assert_eq!(cw.then(cw).to_exif(), 3);
+        assert_eq!(cw.then(ccw), Orientation::NORMAL);
+        assert_eq!(cw.then(cw).then(cw).then(cw), Orientation::NORMAL);
+        assert_eq!(flip.then(flip), Orientation::NORMAL);
+        // EXIF 5 is "mirror horizontal and rotate 270 CW", 7 is "... 90 CW"
+        assert_eq!(flip.then(ccw).to_exif(), 5);
+        assert_eq!(flip.then(cw).to_exif(), 7);
+        // Flipping a rotated photo turns the rotation the other way
+        assert_eq!(cw.then(flip), flip.then(ccw));
+        assert_eq!(Orientation::FLIP_VERTICAL, flip.then(cw).then(cw));
+    }
+}
@@ -0,0 +1,349 @@
+// orphaned_notes.rs
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gtk::{gio, glib, CompositeTemplate};
+use gettextrs::{gettext, ngettext};
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+use crate::photo_manager::PhotoManager;
+use crate::storage::StorageError;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/orphaned_notes.ui")]
+    pub struct OrphanedNotesDialog {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub orphan_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub action_bar: TemplateChild<gtk::ActionBar>,
+        #[template_child]
+        pub select_all_check: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub delete_button: TemplateChild<gtk::Button>,
+
+        pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
+        /// Listed note paths together with the check button selecting each.
+        pub rows: RefCell<Vec<(String, gtk::CheckButton)>>,
+        /// Photos that notes were re-linked to while the dialog was open.
+        pub relinked: RefCell<Vec<String>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OrphanedNotesDialog {
+        const NAME: &'static str = "OrphanedNotesDialog";
+        type Type = super::OrphanedNotesDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for OrphanedNotesDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_callbacks();
+        }
+    }
+
+    impl WidgetImpl for OrphanedNotesDialog {}
+    impl AdwDialogImpl for OrphanedNotesDialog {}
+}
+
+glib::wrapper! {
+    pub struct OrphanedNotesDialog(ObjectSubclass<imp::OrphanedNotesDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+impl OrphanedNotesDialog {
+    pub fn new(manager: Rc<RefCell<PhotoManager>>) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        *dialog.imp().photo_manager.borrow_mut() = Some(manager);
+        dialog.reload();
+        dialog
+    }
+
+    /// Photos that notes were re-linked to, so that the gallery can show
+    /// their notes and marks.
+    pub fn relinked_photos(&self) -> Vec<String> {
+        self.imp().relinked.borrow().clone()
+    }
+
+    fn setup_callbacks(&self) {
+        let imp = self.imp();
+
+        imp.select_all_check.connect_toggled(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |check| {
+                let active = check.is_active();
+                for (_, row_check) in dialog.imp().rows.borrow().iter() {
+                    row_check.set_active(active);
+                }
+            }
+        ));
+
+        imp.export_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.export_selected();
+            }
+        ));
+
+        imp.delete_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.confirm_delete_selected();
+            }
+        ));
+    }
+
+    fn manager(&self) -> Rc<RefCell<PhotoManager>> {
+        self.imp()
+            .photo_manager
+            .borrow()
+            .clone()
+            .expect("OrphanedNotesDialog needs a photo manager")
+    }
+
+    /// Rebuilds the list from the notes whose photos are missing.
+    fn reload(&self) {
+        let imp = self.imp();
+        imp.orphan_list.remove_all();
+        imp.rows.borrow_mut().clear();
+
+        let orphans: Vec<(String, String)> = self
+            .manager()
+            .borrow()
+            .orphaned_notes()
+            .iter()
+            .map(|note| (note.path.clone(), note.note.clone()))
+            .collect();
+        let empty = orphans.is_empty();
+        for (path, note) in orphans {
+            self.append_row(path, &note);
+        }
+
+        imp.stack.set_visible_child_name(if empty { "empty" } else { "list" });
+        imp.action_bar.set_revealed(!empty);
+        imp.select_all_check.set_active(false);
+        self.update_selection();
+    }
+
+    fn append_row(&self, path: String, note: &str) {
+        let imp = self.imp();
+        let name = Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.clone());
+
+        let row = adw::ActionRow::builder()
+            .title(name)
+            .subtitle(path.as_str())
+            .use_markup(false)
+            .build();
+        if !note.is_empty() {
+            row.set_tooltip_text(Some(note));
+        }
+
+        let check = gtk::CheckButton::new();
+        check.set_valign(gtk::Align::Center);
+        check.connect_toggled(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.update_selection();
+            }
+        ));
+        row.add_prefix(&check);
+        row.set_activatable_widget(Some(&check));
+
+        let relink_button = gtk::Button::from_icon_name("find-location-symbolic");
+        relink_button.set_valign(gtk::Align::Center);
+        relink_button.set_tooltip_text(Some(&gettext("Re-link to Another Photo…")));
+        relink_button.add_css_class("flat");
+        let from = path.clone();
+        relink_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.choose_new_file(&from);
+            }
+        ));
+        row.add_suffix(&relink_button);
+
+        imp.orphan_list.append(&row);
+        imp.rows.borrow_mut().push((path, check));
+    }
+
+    fn selected_paths(&self) -> Vec<String> {
+        self.imp()
+            .rows
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn update_selection(&self) {
+        let imp = self.imp();
+        let has_selection = !self.selected_paths().is_empty();
+        imp.export_button.set_sensitive(has_selection);
+        imp.delete_button.set_sensitive(has_selection);
+    }
+
+    fn choose_new_file(&self, from: &str) {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(&gettext("Images")));
+        filter.add_mime_type("image/*");
+
+        let file_dialog = gtk::FileDialog::builder()
+            .title(gettext("Choose the Photo for This Note"))
+            .modal(true)
+            .default_filter(&filter)
+            .build();
+        if let Some(folder) = Path::new(from).parent().filter(|folder| folder.exists()) {
+            file_dialog.set_initial_folder(Some(&gio::File::for_path(folder)));
+        }
+
+        let from = from.to_string();
+        let parent = self.root().and_downcast::<gtk::Window>();
+        file_dialog.open(parent.as_ref(), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |result| {
+                if let Some(to) = result.ok().and_then(|file| file.path()) {
+                    dialog.relink(&from, &to.to_string_lossy());
+                }
+            }
+        ));
+    }
+
+    fn relink(&self, from: &str, to: &str) {
+        let result = self.manager().borrow_mut().relink_note(from, to);
+        match result {
+            Ok(()) => {
+                self.imp().relinked.borrow_mut().push(to.to_string());
+                self.reload();
+                self.show_toast(&gettext("Note re-linked"));
+            }
+            Err(err) => self.show_error(&err),
+        }
+    }
+
+    fn export_selected(&self) {
+        let paths = self.selected_paths();
+        if paths.is_empty() {
+            return;
+        }
+
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("JSON"));
+        filter.add_mime_type("application/json");
+        filter.add_suffix("json");
+
+        let file_dialog = gtk::FileDialog::builder()
+            .title(gettext("Export Notes"))
+            .modal(true)
+            .initial_name("notia-orphaned-notes.json")
+            .default_filter(&filter)
+            .build();
+
+        let parent = self.root().and_downcast::<gtk::Window>();
+        file_dialog.save(parent.as_ref(), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |result| {
+                let Some(dest) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let result = dialog.manager().borrow().export_notes(&paths, &dest);
+                match result {
+                    Ok(()) => {
+                        let message = ngettext("Exported {} note", "Exported {} notes", paths.len() as u32)
+                            .replace("{}", &paths.len().to_string());
+                        dialog.show_toast(&message);
+                    }
+                    Err(err) => dialog.show_error(&err),
+                }
+            }
+        ));
+    }
+
+    fn confirm_delete_selected(&self) {
+        let count = self.selected_paths().len();
+        if count == 0 {
+            return;
+        }
+
+        let heading = ngettext("Delete {} Orphaned Note?", "Delete {} Orphaned Notes?", count as u32)
+            .replace("{}", &count.to_string());
+        let alert = adw::AlertDialog::new(
+            Some(&heading),
+            Some(&gettext("The notes and tags will be removed permanently. Export them first to keep a copy.")),
+        );
+        alert.add_responses(&[
+            ("cancel", &gettext("_Cancel")),
+            ("export", &gettext("_Export First")),
+            ("delete", &gettext("_Delete")),
+        ]);
+        alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+
+        alert.connect_response(None, glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_, response| {
+                match response {
+                    "delete" => dialog.delete_selected(),
+                    "export" => dialog.export_selected(),
+                    _ => {}
+                }
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn delete_selected(&self) {
+        let paths = self.selected_paths();
+        let result = self.manager().borrow_mut().remove_notes(&paths);
+        match result {
+            Ok(()) => {
+                self.reload();
+                let message = ngettext("Deleted {} note", "Deleted {} notes", paths.len() as u32)
+                    .replace("{}", &paths.len().to_string());
+                self.show_toast(&message);
+            }
+            Err(err) => self.show_error(&err),
+        }
+    }
+
+    fn show_toast(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    fn show_error(&self, err: &StorageError) {
+        let toast = adw::Toast::builder()
+            .title(format!("{}: {}", gettext("Could not update notes"), err))
+            .use_markup(false)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
@@ -1,22 +1,606 @@
 // photo_manager.rs
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use xxhash_rust::xxh3::Xxh3;
+use crate::formats;
+use crate::metadata::PhotoMetadata;
+use crate::orientation::Orientation;
+use crate::query::Query;
+use crate::search::SearchIndex;
+use crate::sorting::natural_cmp;
+use crate::storage::{self, NoteStore, StorageError};
+use crate::tags::{self, TagTree};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct PhotoNote {
     pub path: String,
     pub note: String,
     pub timestamp: String,
     pub tags: Vec<String>,
+    /// Content fingerprint of the photo, see [`fingerprint`].
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// Rotation and flip the user put on top of the photo's own
+    /// orientation. The file itself is never changed.
+    #[serde(default)]
+    pub orientation: Orientation,
+    /// Star rating from 1 to 5; 0 means unrated.
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub flag: Flag,
+}
+
+impl PhotoNote {
+    /// Whether the rotation, rating or flags are set. These are kept when
+    /// the note text and tags are cleared.
+    pub fn has_marks(&self) -> bool {
+        !self.orientation.is_normal() || self.rating > 0 || self.favorite || self.flag != Flag::Unflagged
+    }
+}
+
+/// Culling decision on a photo.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flag {
+    #[default]
+    Unflagged,
+    Pick,
+    Reject,
+}
+
+impl Flag {
+    /// Reads the value stored in the database; unknown values are unflagged.
+    pub fn from_db(value: i64) -> Flag {
+        match value {
+            1 => Flag::Pick,
+            -1 => Flag::Reject,
+            _ => Flag::Unflagged,
+        }
+    }
+
+    pub fn to_db(self) -> i64 {
+        match self {
+            Flag::Pick => 1,
+            Flag::Reject => -1,
+            Flag::Unflagged => 0,
+        }
+    }
+}
+
+/// A saved search shown as an album that keeps itself up to date.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartAlbum {
+    pub id: i64,
+    pub name: String,
+    /// Query in the search bar's syntax, see [`crate::query`].
+    pub query: String,
+}
+
+/// Photos picked by hand, kept in the order the user arranged them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Album {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    /// Path of the photo chosen as cover, see [`Album::cover_photo`].
+    pub cover: Option<String>,
+    /// Photo paths in album order.
+    pub photos: Vec<String>,
+}
+
+impl Album {
+    /// The chosen cover, or the first photo while none is chosen.
+    pub fn cover_photo(&self) -> Option<&str> {
+        self.cover
+            .as_deref()
+            .filter(|cover| self.photos.iter().any(|photo| photo == cover))
+            .or_else(|| self.photos.first().map(String::as_str))
+    }
+}
+
+/// A note that followed its photo to a new path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relinked {
+    pub from: String,
+    pub to: String,
+}
+
+const FINGERPRINT_CHUNK: u64 = 64 * 1024;
+
+pub const DEFAULT_MAX_DEPTH: usize = 10;
+
+/// Controls which folders and files a library scan visits.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// How many levels of subfolders below each library root are scanned.
+    pub max_depth: usize,
+    /// Glob patterns for files and folders to skip. Patterns without a `/`
+    /// match a single file or folder name; others match the path relative to
+    /// the library root, where `**` spans any number of folders.
+    pub exclude_patterns: Vec<String>,
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            exclude_patterns: ["node_modules", ".thumbnails", ".cache", ".git", "__MACOSX"]
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+            include_hidden: false,
+            follow_symlinks: false,
+        }
+    }
+}
+
+impl ScanOptions {
+    fn is_excluded(&self, name: &str, relative: &str) -> bool {
+        if !self.include_hidden && name.starts_with('.') {
+            return true;
+        }
+        self.exclude_patterns.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern.trim_start_matches('/'), relative)
+            } else {
+                glob_match(pattern, name)
+            }
+        })
+    }
+}
+
+/// Matches `text` against a glob pattern. `*` and `?` never match `/`,
+/// while `**` matches across folders.
+///
+/// Only the last `*` and the last `**` are retried with a longer match, so
+/// the time taken grows with the product of the lengths rather than
+/// exponentially.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Pattern and text positions right after the last `*`, and after the
+    // last `**` together with whether it was followed by a `/`.
+    let mut star: Option<(usize, usize)> = None;
+    let mut double_star: Option<(usize, usize, bool)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') if pattern.get(p + 1) == Some(&'*') => {
+                let whole_folders = pattern.get(p + 2) == Some(&'/');
+                p += if whole_folders { 3 } else { 2 };
+                double_star = Some((p, t, whole_folders));
+                star = None;
+                continue;
+            }
+            Some('*') => {
+                p += 1;
+                star = Some((p, t));
+                continue;
+            }
+            Some('?') if text[t] != '/' => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            Some(&c) if c != '?' && c == text[t] => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some((star_p, star_t)) = star.filter(|&(_, star_t)| text[star_t] != '/') {
+            star = Some((star_p, star_t + 1));
+            (p, t) = (star_p, star_t + 1);
+        } else if let Some((double_p, double_t, whole_folders)) = double_star {
+            // "**/" matches no folders at all or whole folders only
+            let next = if whole_folders {
+                match text[double_t..].iter().position(|&c| c == '/') {
+                    Some(slash) => double_t + slash + 1,
+                    None => return false,
+                }
+            } else {
+                double_t + 1
+            };
+            double_star = Some((double_p, next, whole_folders));
+            star = None;
+            (p, t) = (double_p, next);
+        } else {
+            return false;
+        }
+    }
+
+    // Whatever is left of the pattern has to match an empty string
+    while p < pattern.len() {
+        if pattern[p..].starts_with(&['*', '*', '/']) {
+            p += 3;
+        } else if pattern[p] == '*' {
+            p += 1;
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+/// Photos and folders found by a library scan.
+#[derive(Debug, Default)]
+pub struct LibraryScan {
+    pub photos: Vec<String>,
+    /// Every folder that was read, so that it can be watched for changes.
+    pub folders: Vec<PathBuf>,
+    /// Set when the scan was stopped before it reached every folder.
+    pub cancelled: bool,
+    /// Number of photos per format that are recognized but cannot be
+    /// displayed, usually because an image loader is not installed.
+    pub unrenderable: Vec<(&'static str, usize)>,
+}
+
+/// Called with each folder read and the photos found in it; returning
+/// `false` stops the scan.
+pub type ScanCallback<'a> = &'a mut dyn FnMut(&Path, &[String]) -> bool;
+
+/// Walks the library roots and collects all supported images.
+///
+/// Every folder and file is visited at most once, which guards against
+/// symlink loops, symlinked photos and overlapping library roots.
+pub fn scan_library(roots: &[PathBuf], options: &ScanOptions, on_folder: ScanCallback) -> LibraryScan {
+    let mut scan = LibraryScan::default();
+    let mut visited = HashSet::new();
+    for root in roots {
+        if !scan_folder(root, root, 0, options, &mut visited, &mut scan, on_folder) {
+            scan.cancelled = true;
+            break;
+        }
+    }
+    scan
+}
+
+/// Scans `folder`, which lies `depth` levels below the library `root`.
+/// `visited` holds the canonical paths of the folders and photos seen so
+/// far. Returns `false` if `on_folder` stopped the scan.
+fn scan_folder(
+    root: &Path,
+    folder: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    visited: &mut HashSet<PathBuf>,
+    scan: &mut LibraryScan,
+    on_folder: ScanCallback,
+) -> bool {
+    let mut pending = vec![(folder.to_path_buf(), depth)];
+    while let Some((dir, depth)) = pending.pop() {
+        let real_dir = match fs::canonicalize(&dir) {
+            Ok(real_dir) => real_dir,
+            Err(_) => continue,
+        };
+        if !visited.insert(real_dir.clone()) {
+            continue;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        scan.folders.push(dir.clone());
+        let found = scan.photos.len();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().to_string();
+            if options.is_excluded(&name, &relative) {
+                continue;
+            }
+
+            let mut file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let is_symlink = file_type.is_symlink();
+            if is_symlink {
+                if !options.follow_symlinks {
+                    continue;
+                }
+                // Kırık bağlantıları atla
+                file_type = match fs::metadata(&path) {
+                    Ok(metadata) => metadata.file_type(),
+                    Err(_) => continue,
+                };
+            }
+
+            if file_type.is_dir() {
+                if depth < options.max_depth {
+                    pending.push((path, depth + 1));
+                }
+            } else if file_type.is_file() {
+                let Some(format) = formats::detect(&path) else {
+                    continue;
+                };
+                // Bağlantı değilse gerçek yolu klasörün gerçek yolundan çıkar
+                let real_path = if is_symlink {
+                    match fs::canonicalize(&path) {
+                        Ok(real_path) => real_path,
+                        Err(_) => continue,
+                    }
+                } else {
+                    real_dir.join(entry.file_name())
+                };
+                if !visited.insert(real_path) {
+                    continue;
+                }
+                let Ok(path_str) = path.into_os_string().into_string() else {
+                    continue;
+                };
+                scan.photos.push(path_str);
+                if !formats::can_render(format) {
+                    match scan.unrenderable.iter_mut().find(|(name, _)| *name == format.name) {
+                        Some((_, count)) => *count += 1,
+                        None => scan.unrenderable.push((format.name, 1)),
+                    }
+                }
+            }
+        }
+
+        if !on_folder(&dir, &scan.photos[found..]) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Where `path` ends up when `from` is renamed to `to`, or `None` if it
+/// does not lie inside `from`.
+pub fn renamed_path(from: &Path, to: &Path, path: &str) -> Option<String> {
+    let rest = Path::new(path).strip_prefix(from).ok()?;
+    let new_path = if rest.as_os_str().is_empty() {
+        to.to_path_buf()
+    } else {
+        to.join(rest)
+    };
+    Some(new_path.to_string_lossy().to_string())
+}
+
+/// Fast content fingerprint of a file: its size plus the first and last
+/// 64 KiB, hashed with XXH3. It stays the same when a photo is renamed or
+/// moved, without reading multi-megabyte files in full.
+///
+/// The size is kept in front of the hash as `<size>-<hash>`, so that
+/// photos of another size can be ruled out without reading them.
+pub fn fingerprint(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+
+    let mut hasher = Xxh3::new();
+    hasher.update(&len.to_le_bytes());
+
+    let mut buf = vec![0; FINGERPRINT_CHUNK.min(len) as usize];
+    file.read_exact(&mut buf).ok()?;
+    hasher.update(&buf);
+
+    if len > FINGERPRINT_CHUNK {
+        let tail = FINGERPRINT_CHUNK.min(len - FINGERPRINT_CHUNK);
+        file.seek(SeekFrom::End(-(tail as i64))).ok()?;
+        buf.truncate(tail as usize);
+        file.read_exact(&mut buf).ok()?;
+        hasher.update(&buf);
+    }
+
+    Some(format!("{}-{:016x}", len, hasher.digest()))
+}
+
+/// The file size recorded in a fingerprint, `None` for fingerprints taken
+/// before sizes were recorded.
+pub fn fingerprint_size(hash: &str) -> Option<u64> {
+    hash.split_once('-')?.0.parse().ok()
+}
+
+/// What a scan found out about notes whose photo moved. It is worked out
+/// from a copy of the notes so that the file reads can happen off the main
+/// thread; [`PhotoManager::apply_moved_notes`] then updates the notes.
+#[derive(Debug, Default)]
+pub struct MovedNotes {
+    /// New fingerprints of notes whose photo is still in place but that
+    /// had none, or one without the file size.
+    pub fingerprints: Vec<(String, String)>,
+    pub relinked: Vec<Relinked>,
+}
+
+/// Matches notes whose file disappeared against scanned photos by content
+/// fingerprint. `notes` holds the path and fingerprint of every note.
+///
+/// Only photos without a note and with the size of a missing photo are
+/// fingerprinted. Notes with identical content are paired with photos in
+/// path order, so none of them is dropped.
+pub fn find_moved_notes(notes: &[(String, Option<String>)], photos: &[String]) -> MovedNotes {
+    let mut moved = MovedNotes::default();
+    let mut missing: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut sizes = HashSet::new();
+    for (path, hash) in notes {
+        let size = hash.as_deref().and_then(fingerprint_size);
+        if Path::new(path).exists() {
+            if size.is_none() {
+                if let Some(hash) = fingerprint(Path::new(path)) {
+                    moved.fingerprints.push((path.clone(), hash));
+                }
+            }
+        } else if let (Some(hash), Some(size)) = (hash, size) {
+            // Eski biçimdeki özetlerin boyutu bilinmez; onlar Sahipsiz
+            // Notlar penceresinden elle bağlanır.
+            missing.entry(hash.as_str()).or_default().push(path.as_str());
+            sizes.insert(size);
+        }
+    }
+    if missing.is_empty() {
+        return moved;
+    }
+    for paths in missing.values_mut() {
+        paths.sort_unstable_by(|a, b| b.cmp(a));
+    }
+
+    let noted: HashSet<&str> = notes.iter().map(|(path, _)| path.as_str()).collect();
+    let mut taken = HashSet::new();
+    for photo in photos {
+        if noted.contains(photo.as_str()) || !taken.insert(photo.as_str()) {
+            continue;
+        }
+        let size = fs::metadata(photo).map(|metadata| metadata.len());
+        if !size.is_ok_and(|size| sizes.contains(&size)) {
+            continue;
+        }
+        let Some(hash) = fingerprint(Path::new(photo)) else {
+            continue;
+        };
+        let Some(paths) = missing.get_mut(hash.as_str()) else {
+            continue;
+        };
+        if let Some(from) = paths.pop() {
+            moved.relinked.push(Relinked { from: from.to_string(), to: photo.clone() });
+        }
+        if paths.is_empty() {
+            missing.remove(hash.as_str());
+            if missing.is_empty() {
+                break;
+            }
+        }
+    }
+    moved
+}
+
+/// Version of the serialized notes format written by this build.
+pub const NOTES_FORMAT_VERSION: u64 = 4;
+
+/// Upgrades serialized notes from version `i` to `i + 1`.
+type Migration = fn(Value) -> Result<Value, StorageError>;
+
+/// Forward migrations, indexed by the version they upgrade from.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3, migrate_v3_to_v4];
+
+/// Versioned wrapper around serialized notes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotesEnvelope {
+    pub version: u64,
+    pub notes: Vec<Value>,
+}
+
+/// Version 0 is the bare `[...]` array written before notes were versioned.
+fn migrate_v0_to_v1(value: Value) -> Result<Value, StorageError> {
+    Ok(serde_json::json!({ "version": 1, "notes": value }))
+}
+
+/// Version 2 adds `content_hash`; older notes have none until the next scan.
+fn migrate_v1_to_v2(mut value: Value) -> Result<Value, StorageError> {
+    if let Some(notes) = value.get_mut("notes").and_then(Value::as_array_mut) {
+        for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+            note.entry("content_hash").or_insert(Value::Null);
+        }
+    }
+    value["version"] = 2.into();
+    Ok(value)
+}
+
+/// Version 3 adds the `orientation` override, stored as an EXIF value.
+fn migrate_v2_to_v3(mut value: Value) -> Result<Value, StorageError> {
+    if let Some(notes) = value.get_mut("notes").and_then(Value::as_array_mut) {
+        for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+            note.entry("orientation").or_insert(Value::from(1));
+        }
+    }
+    value["version"] = 3.into();
+    Ok(value)
+}
+
+/// Version 4 adds `rating`, `favorite` and `flag`; older notes are unrated and unmarked.
+fn migrate_v3_to_v4(mut value: Value) -> Result<Value, StorageError> {
+    if let Some(notes) = value.get_mut("notes").and_then(Value::as_array_mut) {
+        for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+            note.entry("rating").or_insert(Value::from(0));
+            note.entry("favorite").or_insert(Value::from(false));
+            note.entry("flag").or_insert(Value::from("unflagged"));
+        }
+    }
+    value["version"] = 4.into();
+    Ok(value)
+}
+
+/// Returns the version of serialized notes; a bare array is version 0.
+fn notes_version(value: &Value) -> Result<u64, StorageError> {
+    match value {
+        Value::Array(_) => Ok(0),
+        Value::Object(map) => map
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| StorageError::Format("missing \"version\" field".to_string())),
+        _ => Err(StorageError::Format("expected an array or an object".to_string())),
+    }
+}
+
+/// Serializes notes in the current versioned envelope format.
+pub fn serialize_notes(notes: &[PhotoNote]) -> Result<String, StorageError> {
+    let envelope = NotesEnvelope {
+        version: NOTES_FORMAT_VERSION,
+        notes: notes
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<_, _>>()?,
+    };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
+
+/// Parses serialized notes of any known version and upgrades them to
+/// `NOTES_FORMAT_VERSION`. Entries are returned unparsed so that a single
+/// malformed note does not discard the rest.
+pub fn migrate_notes(mut value: Value) -> Result<NotesEnvelope, StorageError> {
+    let mut version = notes_version(&value)?;
+    if version > NOTES_FORMAT_VERSION {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+    while version < NOTES_FORMAT_VERSION {
+        value = MIGRATIONS[version as usize](value)?;
+        version = notes_version(&value)?;
+    }
+    Ok(serde_json::from_value(value)?)
 }
 
 #[derive(Debug)]
 pub struct PhotoManager {
     pub photos: Vec<String>,
+    /// The same photos as `photos`, for quick lookups.
+    known_photos: HashSet<String>,
     pub notes: HashMap<String, PhotoNote>,
     pub notes_file: PathBuf,
+    /// Folders scanned for photos, including their subfolders.
+    pub library_roots: Vec<PathBuf>,
+    pub scan_options: ScanOptions,
+    /// Folders read by the last scan, kept up to date by `add_path` and
+    /// `remove_path`.
+    pub scanned_folders: Vec<PathBuf>,
+    /// Set when the notes database could not be opened; notes then live in
+    /// memory only and the file on disk is left untouched.
+    pub safe_mode: bool,
+    load_errors: Vec<StorageError>,
+    store: NoteStore,
+    /// Metadata read so far, by photo path.
+    metadata: HashMap<String, PhotoMetadata>,
+    search_index: SearchIndex,
+    /// Set when a note changed in a way that can change search results.
+    search_changed: bool,
+    /// In the order shown in the navigation sidebar.
+    smart_albums: Vec<SmartAlbum>,
+    /// In the order shown in the navigation sidebar.
+    albums: Vec<Album>,
+    /// Palette indices chosen for tags in the tag manager.
+    tag_colors: HashMap<String, usize>,
 }
 
 impl Default for PhotoManager {
@@ -27,95 +611,423 @@ impl Default for PhotoManager {
 
 impl PhotoManager {
     pub fn new() -> Self {
+        let notes_file = dirs::data_dir()
+            .unwrap_or_else(|| dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")))
+            .join("notia")
+            .join("notes.db");
+        Self::with_notes_file(notes_file)
+    }
+
+    /// Opens the notes database at `notes_file` and loads what it holds.
+    fn with_notes_file(notes_file: PathBuf) -> Self {
+        let mut load_errors = Vec::new();
+        let (store, safe_mode) = Self::open_store(&notes_file, &mut load_errors);
+
         let mut manager = PhotoManager {
             photos: Vec::new(),
+            known_photos: HashSet::new(),
             notes: HashMap::new(),
-            notes_file: dirs::home_dir().unwrap_or_else(|| PathBuf::from("."))
-                .join(".notia_notes.json"),
+            notes_file,
+            library_roots: Self::default_library_roots(),
+            scan_options: ScanOptions::default(),
+            scanned_folders: Vec::new(),
+            safe_mode,
+            load_errors,
+            store,
+            metadata: HashMap::new(),
+            search_index: SearchIndex::default(),
+            search_changed: false,
+            smart_albums: Vec::new(),
+            albums: Vec::new(),
+            tag_colors: HashMap::new(),
         };
 
-        manager.load_notes();
-        manager.scan_photos();
+        if let Err(err) = manager.import_legacy_notes() {
+            manager.load_errors.push(err);
+        }
+        if let Err(err) = manager.load_notes() {
+            manager.load_errors.push(err);
+        }
+        if let Err(err) = manager.load_smart_albums() {
+            manager.load_errors.push(err);
+        }
+        if let Err(err) = manager.load_albums() {
+            manager.load_errors.push(err);
+        }
+        if let Err(err) = manager.load_tag_colors() {
+            manager.load_errors.push(err);
+        }
         manager
     }
 
-    pub fn scan_photos(&mut self) {
-        let mut photos = Vec::new();
-
-        // Standart resim dizinlerini tara
-        let picture_dirs = vec![
-            dirs::picture_dir(),
-            dirs::home_dir().map(|p| p.join("Pictures")),
-            dirs::home_dir().map(|p| p.join("Resimler")),
-            dirs::home_dir().map(|p| p.join("Downloads")),
-            dirs::home_dir().map(|p| p.join("İndirilenler")),
-        ];
+    /// Opens the notes database. A database that cannot be read is moved
+    /// aside as `.corrupt` and replaced with an empty one; if even that is
+    /// not possible, notes are kept in memory only (safe mode) so that the
+    /// file on disk is never overwritten.
+    fn open_store(notes_file: &Path, errors: &mut Vec<StorageError>) -> (NoteStore, bool) {
+        let err = match NoteStore::open(notes_file) {
+            Ok(store) => return (store, false),
+            Err(err) => err,
+        };
 
-        for dir_opt in picture_dirs {
-            if let Some(dir) = dir_opt {
-                if dir.exists() {
-                    if let Ok(entries) = fs::read_dir(dir) {
-                        for entry in entries.flatten() {
-                            let path = entry.path();
-                            if path.is_file() {
-                                if let Some(ext) = path.extension() {
-                                    if let Some(ext_str) = ext.to_str() {
-                                        match ext_str.to_lowercase().as_str() {
-                                            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" => {
-                                                if let Ok(path_str) = path.into_os_string().into_string() {
-                                                    photos.push(path_str);
-                                                }
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                }
-                            }
-                        }
+        if err.is_corruption() {
+            match storage::quarantine(notes_file) {
+                Ok(moved_to) => {
+                    errors.push(StorageError::Corrupt {
+                        path: notes_file.to_path_buf(),
+                        moved_to,
+                    });
+                    match NoteStore::open(notes_file) {
+                        Ok(store) => return (store, false),
+                        Err(err) => errors.push(err),
                     }
                 }
+                Err(move_err) => {
+                    errors.push(err);
+                    errors.push(move_err);
+                }
             }
+        } else {
+            errors.push(err);
         }
 
-        self.photos = photos;
+        let store = NoteStore::open_in_memory().expect("Could not open in-memory note store");
+        (store, true)
     }
 
-    pub fn load_notes(&mut self) {
-        if self.notes_file.exists() {
-            if let Ok(content) = fs::read_to_string(&self.notes_file) {
-                if let Ok(notes_vec) = serde_json::from_str::<Vec<PhotoNote>>(&content) {
-                    for note in notes_vec {
-                        self.notes.insert(note.path.clone(), note);
-                    }
+    /// Errors encountered while loading the notes, for the UI to report once.
+    pub fn take_load_errors(&mut self) -> Vec<StorageError> {
+        std::mem::take(&mut self.load_errors)
+    }
+
+    /// Takes over the result of a library scan and re-links notes whose
+    /// photos were moved or renamed, as found by [`find_moved_notes`] on the
+    /// scanning thread. Returns the notes that followed their photo.
+    pub fn finish_scan(&mut self, scan: LibraryScan, moved: MovedNotes) -> Result<Vec<Relinked>, StorageError> {
+        self.known_photos = scan.photos.iter().cloned().collect();
+        self.photos = scan.photos;
+        self.scanned_folders = scan.folders;
+        self.apply_moved_notes(moved)
+    }
+
+    /// The library root containing `path` and how many levels below it
+    /// `path` lies, or `None` if a scan would not reach it.
+    fn library_location(&self, path: &Path) -> Option<(PathBuf, usize)> {
+        let (root, relative) = self
+            .library_roots
+            .iter()
+            .find_map(|root| Some((root, path.strip_prefix(root).ok()?)))?;
+
+        let mut depth = 0;
+        for component in relative.components() {
+            depth += 1;
+            let name = component.as_os_str().to_string_lossy();
+            let ancestor: PathBuf = relative.components().take(depth).collect();
+            if self.scan_options.is_excluded(&name, &ancestor.to_string_lossy()) {
+                return None;
+            }
+        }
+
+        let is_symlink = fs::symlink_metadata(path)
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false);
+        if is_symlink && !self.scan_options.follow_symlinks {
+            return None;
+        }
+        Some((root.clone(), depth))
+    }
+
+    /// Adds a file or folder that appeared in the library, returning the
+    /// photos and folders that were not known yet.
+    pub fn add_path(&mut self, path: &Path) -> LibraryScan {
+        let mut scan = LibraryScan::default();
+        let Some((root, depth)) = self.library_location(path) else {
+            return scan;
+        };
+
+        if path.is_dir() {
+            if depth > self.scan_options.max_depth || self.scanned_folders.iter().any(|f| f == path) {
+                return scan;
+            }
+            scan_folder(&root, path, depth, &self.scan_options, &mut HashSet::new(), &mut scan, &mut |_, _| true);
+            scan.photos.retain(|photo| !self.known_photos.contains(photo));
+        } else if path.is_file() && formats::detect(path).is_some() && depth <= self.scan_options.max_depth + 1 {
+            let path = path.to_string_lossy().to_string();
+            if !self.known_photos.contains(&path) {
+                scan.photos.push(path);
+            }
+        }
+
+        self.known_photos.extend(scan.photos.iter().cloned());
+        self.photos.extend(scan.photos.iter().cloned());
+        self.scanned_folders.extend(scan.folders.iter().cloned());
+        scan
+    }
+
+    /// Forgets a file or folder that left the library, returning the photos
+    /// that were removed.
+    pub fn remove_path(&mut self, path: &Path) -> Vec<String> {
+        let (removed, kept): (Vec<String>, Vec<String>) = std::mem::take(&mut self.photos)
+            .into_iter()
+            .partition(|photo| Path::new(photo).starts_with(path));
+        self.photos = kept;
+        for photo in &removed {
+            self.known_photos.remove(photo);
+        }
+        self.scanned_folders.retain(|folder| !folder.starts_with(path));
+        self.metadata.retain(|photo, _| !Path::new(photo).starts_with(path));
+        removed
+    }
+
+    pub fn cached_metadata(&self, path: &str) -> Option<&PhotoMetadata> {
+        self.metadata.get(path)
+    }
+
+    pub fn cache_metadata(&mut self, path: String, metadata: PhotoMetadata) {
+        self.metadata.insert(path, metadata);
+    }
+
+    /// Drops cached metadata of a photo whose file changed.
+    pub fn forget_metadata(&mut self, path: &str) {
+        self.metadata.remove(path);
+    }
+
+    /// Moves cached metadata of the photos below `from` to their new paths
+    /// below `to`. Call it before [`Self::remove_path`] drops it.
+    pub fn rename_metadata(&mut self, from: &Path, to: &Path) {
+        let renamed: Vec<String> = self
+            .metadata
+            .keys()
+            .filter(|photo| Path::new(photo).starts_with(from))
+            .cloned()
+            .collect();
+        for old in renamed {
+            let Some(new) = renamed_path(from, to, &old) else {
+                continue;
+            };
+            if let Some(metadata) = self.metadata.remove(&old) {
+                self.metadata.insert(new, metadata);
+            }
+        }
+    }
+
+    /// Moves the notes and album entries of photos that were renamed from
+    /// `from` to `to` within the library.
+    pub fn relink_renamed(&mut self, from: &Path, to: &Path, removed: &[String]) -> Result<(), StorageError> {
+        for old in removed {
+            let has_note = self.notes.contains_key(old);
+            if !has_note && !self.is_in_album(old) {
+                continue;
+            }
+            if let Some(new) = renamed_path(from, to, old).filter(|new| self.known_photos.contains(new)) {
+                if has_note {
+                    self.relink_note(old, &new)?;
+                } else {
+                    self.relink_album_photos(&[Relinked { from: old.clone(), to: new }])?;
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn save_notes(&self) {
-        let notes_vec: Vec<PhotoNote> = self.notes.values().cloned().collect();
-        if let Ok(json) = serde_json::to_string_pretty(&notes_vec) {
-            let _ = fs::write(&self.notes_file, json);
+    /// The path and fingerprint of every note, for [`find_moved_notes`].
+    pub fn note_fingerprints(&self) -> Vec<(String, Option<String>)> {
+        self.notes
+            .values()
+            .map(|note| (note.path.clone(), note.content_hash.clone()))
+            .collect()
+    }
+
+    /// Stores the fingerprints and moves the notes found by
+    /// [`find_moved_notes`]. Notes that changed in the meantime are left
+    /// alone.
+    pub fn apply_moved_notes(&mut self, moved: MovedNotes) -> Result<Vec<Relinked>, StorageError> {
+        let mut fingerprinted = Vec::new();
+        for (path, hash) in moved.fingerprints {
+            if let Some(note) = self.notes.get_mut(&path) {
+                note.content_hash = Some(hash);
+                fingerprinted.push(note.clone());
+            }
+        }
+        if !fingerprinted.is_empty() {
+            self.store.import(&fingerprinted)?;
+        }
+
+        let relinked: Vec<Relinked> = moved
+            .relinked
+            .into_iter()
+            .filter(|moved| self.notes.contains_key(&moved.from) && !self.notes.contains_key(&moved.to))
+            .collect();
+        if relinked.is_empty() {
+            return Ok(relinked);
+        }
+
+        self.store.relink(&relinked)?;
+        self.relink_album_photos(&relinked)?;
+        for moved in &relinked {
+            if let Some(mut note) = self.notes.remove(&moved.from) {
+                note.path = moved.to.clone();
+                self.notes.insert(moved.to.clone(), note);
+            }
+            self.index_note(&moved.from);
+            self.index_note(&moved.to);
+        }
+        Ok(relinked)
+    }
+
+    /// Folders scanned until the user configures their own library.
+    pub fn default_library_roots() -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+        let candidates = [
+            dirs::picture_dir(),
+            home.as_ref().map(|p| p.join("Pictures")),
+            home.as_ref().map(|p| p.join("Resimler")),
+            dirs::download_dir(),
+            home.as_ref().map(|p| p.join("Downloads")),
+            home.as_ref().map(|p| p.join("İndirilenler")),
+        ];
+
+        let mut roots = Vec::new();
+        for dir in candidates.into_iter().flatten() {
+            if dir.exists() && !roots.contains(&dir) {
+                roots.push(dir);
+            }
+        }
+        roots
+    }
+
+    /// Imports the legacy `~/.notia_notes.json` file into an empty database once.
+    ///
+    /// Entries that cannot be parsed are skipped; the file is then moved aside
+    /// as `.corrupt` rather than `.imported` so nothing in it is lost.
+    fn import_legacy_notes(&mut self) -> Result<(), StorageError> {
+        let legacy_file = match dirs::home_dir() {
+            Some(home) => home.join(".notia_notes.json"),
+            None => return Ok(()),
+        };
+        if self.safe_mode || !legacy_file.exists() || !self.store.is_empty()? {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&legacy_file)?;
+        let envelope = match serde_json::from_str(&content).map_err(StorageError::from).and_then(migrate_notes) {
+            Ok(envelope) => envelope,
+            Err(err) if err.is_corruption() => {
+                let moved_to = storage::quarantine(&legacy_file)?;
+                return Err(StorageError::Corrupt { path: legacy_file, moved_to });
+            }
+            Err(err) => return Err(err),
+        };
+        let entries = envelope.notes;
+
+        let total = entries.len();
+        let notes_vec: Vec<PhotoNote> = entries
+            .into_iter()
+            .filter_map(|entry| serde_json::from_value(entry).ok())
+            .collect();
+        self.store.import(&notes_vec)?;
+
+        if notes_vec.len() < total {
+            let moved_to = storage::quarantine(&legacy_file)?;
+            return Err(StorageError::Corrupt { path: legacy_file, moved_to });
+        }
+        fs::rename(&legacy_file, legacy_file.with_extension("json.imported"))?;
+        Ok(())
+    }
+
+    pub fn load_notes(&mut self) -> Result<(), StorageError> {
+        for note in self.store.load_all()? {
+            self.search_index.update(&note.path, Some(&note));
+            self.notes.insert(note.path.clone(), note);
+        }
+        self.search_changed = true;
+        Ok(())
+    }
+
+    fn index_note(&mut self, photo_path: &str) {
+        if self.search_index.update(photo_path, self.notes.get(photo_path)) {
+            self.search_changed = true;
         }
     }
 
-    pub fn add_note(&mut self, photo_path: &str, note: String) {
+    /// Whether search results may have changed since the last call.
+    pub fn take_search_changed(&mut self) -> bool {
+        std::mem::take(&mut self.search_changed)
+    }
+
+    /// Matches a photo against a search query. Dates compare against the
+    /// capture date, or the modification time without one.
+    pub fn matches_search(&self, photo_path: &str, query: &Query) -> bool {
+        let date = self
+            .cached_metadata(photo_path)
+            .and_then(|metadata| metadata.taken_at.or(metadata.modified))
+            .map(|date| date.date());
+        self.search_index.matches(photo_path, date, query)
+    }
+
+    /// Reuses the known fingerprint of a photo instead of hashing it again.
+    fn content_hash_for(&self, photo_path: &str) -> Option<String> {
+        self.notes
+            .get(photo_path)
+            .and_then(|note| note.content_hash.clone())
+            .or_else(|| fingerprint(Path::new(photo_path)))
+    }
+
+    /// Writes a single note to the store instead of rewriting the whole library.
+    fn save_note(&mut self, photo_path: &str) -> Result<(), StorageError> {
+        self.index_note(photo_path);
+        match self.notes.get(photo_path) {
+            Some(photo_note) => self.store.upsert_note(photo_note),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the note text and tags of a photo in one transaction.
+    pub fn set_note(&mut self, photo_path: &str, note: String, tags: Vec<String>) -> Result<(), StorageError> {
+        let photo_note = PhotoNote {
+            note,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            tags,
+            content_hash: self.content_hash_for(photo_path),
+            ..self.marks_of(photo_path)
+        };
+        self.notes.insert(photo_path.to_string(), photo_note);
+        self.save_note(photo_path)
+    }
+
+    pub fn add_note(&mut self, photo_path: &str, note: String) -> Result<(), StorageError> {
         let photo_note = PhotoNote {
-            path: photo_path.to_string(),
             note,
             timestamp: chrono::Utc::now().to_rfc3339(),
             tags: Vec::new(),
+            content_hash: self.content_hash_for(photo_path),
+            ..self.marks_of(photo_path)
         };
         self.notes.insert(photo_path.to_string(), photo_note);
-        self.save_notes();
+        self.save_note(photo_path)
+    }
+
+    /// An empty note for `photo_path` carrying over its rotation, rating
+    /// and flags.
+    fn marks_of(&self, photo_path: &str) -> PhotoNote {
+        let existing = self.notes.get(photo_path);
+        PhotoNote {
+            path: photo_path.to_string(),
+            orientation: self.orientation(photo_path),
+            rating: existing.map_or(0, |note| note.rating),
+            favorite: existing.is_some_and(|note| note.favorite),
+            flag: existing.map_or(Flag::Unflagged, |note| note.flag),
+            ..Default::default()
+        }
     }
 
-    pub fn add_tag(&mut self, photo_path: &str, tag: String) {
+    pub fn add_tag(&mut self, photo_path: &str, tag: String) -> Result<(), StorageError> {
         if let Some(photo_note) = self.notes.get_mut(photo_path) {
             if !photo_note.tags.contains(&tag) {
                 photo_note.tags.push(tag);
-                self.save_notes();
+                return self.save_note(photo_path);
             }
+            Ok(())
         } else {
             // Eğer not yoksa, boş not ile birlikte etiket ekle
             let photo_note = PhotoNote {
@@ -123,17 +1035,251 @@ impl PhotoManager {
                 note: String::new(),
                 timestamp: chrono::Utc::now().to_rfc3339(),
                 tags: vec![tag],
+                content_hash: fingerprint(Path::new(photo_path)),
+                ..Default::default()
             };
             self.notes.insert(photo_path.to_string(), photo_note);
-            self.save_notes();
+            self.save_note(photo_path)
         }
     }
 
-    pub fn remove_tag(&mut self, photo_path: &str, tag: &str) {
+    pub fn remove_tag(&mut self, photo_path: &str, tag: &str) -> Result<(), StorageError> {
         if let Some(photo_note) = self.notes.get_mut(photo_path) {
             photo_note.tags.retain(|t| t != tag);
-            self.save_notes();
+            return self.save_note(photo_path);
+        }
+        Ok(())
+    }
+
+    /// Writes the notes of several photos in one transaction.
+    fn save_notes(&mut self, photo_paths: &[String]) -> Result<(), StorageError> {
+        for path in photo_paths {
+            self.index_note(path);
         }
+        let notes: Vec<PhotoNote> = photo_paths
+            .iter()
+            .filter_map(|path| self.notes.get(path).cloned())
+            .collect();
+        self.store.import(&notes)
+    }
+
+    /// Adds `tag` to every photo in `photo_paths` with a single save.
+    pub fn add_tag_to_photos(&mut self, photo_paths: &[String], tag: &str) -> Result<(), StorageError> {
+        let mut changed = Vec::new();
+        for path in photo_paths {
+            if self.notes.get(path).is_some_and(|note| note.tags.iter().any(|t| t == tag)) {
+                continue;
+            }
+            let content_hash = self.content_hash_for(path);
+            let orientation = self.orientation(path);
+            let photo_note = self.notes.entry(path.clone()).or_insert_with(|| PhotoNote {
+                path: path.clone(),
+                note: String::new(),
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                content_hash,
+                orientation,
+                ..Default::default()
+            });
+            photo_note.tags.push(tag.to_string());
+            changed.push(path.clone());
+        }
+        self.save_notes(&changed)
+    }
+
+    /// Removes `tag` from every photo in `photo_paths` with a single save.
+    pub fn remove_tag_from_photos(&mut self, photo_paths: &[String], tag: &str) -> Result<(), StorageError> {
+        let mut changed = Vec::new();
+        for path in photo_paths {
+            if let Some(photo_note) = self.notes.get_mut(path) {
+                if photo_note.tags.iter().any(|t| t == tag) {
+                    photo_note.tags.retain(|t| t != tag);
+                    changed.push(path.clone());
+                }
+            }
+        }
+        self.save_notes(&changed)
+    }
+
+    /// Clears the note text and tags of several photos with a single save.
+    /// Like [`PhotoManager::remove_note`], rotation, rating and flags stay.
+    pub fn clear_notes_of(&mut self, photo_paths: &[String]) -> Result<(), StorageError> {
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        for path in photo_paths {
+            match self.notes.get_mut(path) {
+                Some(photo_note) if photo_note.has_marks() => {
+                    photo_note.note.clear();
+                    photo_note.tags.clear();
+                    kept.push(photo_note.clone());
+                }
+                Some(_) => {
+                    self.notes.remove(path);
+                    removed.push(path.clone());
+                }
+                None => {}
+            }
+        }
+        for path in photo_paths {
+            self.index_note(path);
+        }
+        self.store.update_notes(&kept, &removed)
+    }
+
+    /// Applies `change` to the notes of `photo_paths`, creating notes where
+    /// the change marks a photo, and saves them in one write.
+    fn update_notes<F: FnMut(&mut PhotoNote)>(&mut self, photo_paths: &[String], mut change: F) -> Result<(), StorageError> {
+        let mut changed = Vec::new();
+        for path in photo_paths {
+            match self.notes.get_mut(path) {
+                Some(photo_note) => change(photo_note),
+                None => {
+                    let mut photo_note = self.marks_of(path);
+                    change(&mut photo_note);
+                    // Puanı sıfırlamak gibi etkisiz değişiklikler boş not oluşturmasın
+                    if !photo_note.has_marks() {
+                        continue;
+                    }
+                    photo_note.timestamp = chrono::Utc::now().to_rfc3339();
+                    photo_note.content_hash = self.content_hash_for(path);
+                    self.notes.insert(path.clone(), photo_note);
+                }
+            }
+            changed.push(path.clone());
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.save_notes(&changed)
+    }
+
+    /// Rates photos from 1 to 5 stars, or clears their rating with 0.
+    pub fn set_rating(&mut self, photo_paths: &[String], rating: u8) -> Result<(), StorageError> {
+        self.update_notes(photo_paths, |note| note.rating = rating.min(5))
+    }
+
+    pub fn set_favorite(&mut self, photo_paths: &[String], favorite: bool) -> Result<(), StorageError> {
+        self.update_notes(photo_paths, |note| note.favorite = favorite)
+    }
+
+    pub fn set_flag(&mut self, photo_paths: &[String], flag: Flag) -> Result<(), StorageError> {
+        self.update_notes(photo_paths, |note| note.flag = flag)
+    }
+
+    /// Every tag of the library arranged by level.
+    pub fn tag_tree(&self) -> TagTree {
+        TagTree::new(self.notes.values().map(|note| &note.tags))
+    }
+
+    /// Tags used by any of `photo_paths`, sorted by name.
+    pub fn tags_of(&self, photo_paths: &[String]) -> Vec<String> {
+        let mut tags: Vec<String> = photo_paths
+            .iter()
+            .filter_map(|path| self.notes.get(path))
+            .flat_map(|note| note.tags.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        tags.sort();
+        tags
+    }
+
+    /// Every tag with the number of photos using it, sorted by tag.
+    pub fn tag_usage(&self) -> Vec<(String, usize)> {
+        let mut usage: HashMap<&str, usize> = HashMap::new();
+        for tag in self.notes.values().flat_map(|note| note.tags.iter()) {
+            *usage.entry(tag.as_str()).or_default() += 1;
+        }
+        let mut usage: Vec<(String, usize)> = usage
+            .into_iter()
+            .map(|(tag, count)| (tag.to_string(), count))
+            .collect();
+        usage.sort_by(|(a, _), (b, _)| natural_cmp(a, b));
+        usage
+    }
+
+    /// Renames `from` and the tags below it. A photo that already has the
+    /// new tag keeps it once, so renaming onto an existing tag merges the
+    /// two. A tag cannot be moved below itself; that changes nothing.
+    /// Returns the number of photos that changed.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, StorageError> {
+        // "places" -> "places/turkey" alt etiketleri de taşıyıp bozardı
+        if from == to || tags::is_below(to, from) {
+            return Ok(0);
+        }
+        let mut changed = Vec::new();
+        for (path, photo_note) in self.notes.iter_mut() {
+            if !photo_note.tags.iter().any(|tag| tags::renamed(tag, from, to).is_some()) {
+                continue;
+            }
+            let mut renamed: Vec<String> = Vec::new();
+            for tag in &photo_note.tags {
+                let tag = tags::renamed(tag, from, to).unwrap_or_else(|| tag.clone());
+                if !renamed.contains(&tag) {
+                    renamed.push(tag);
+                }
+            }
+            photo_note.tags = renamed;
+            changed.push(path.clone());
+        }
+        self.save_notes(&changed)?;
+
+        // Renkler yeni ada taşınır; birleşmede hedefin rengi kalır
+        let mut colors = Vec::new();
+        for (tag, color) in &self.tag_colors {
+            if let Some(new) = tags::renamed(tag, from, to) {
+                colors.push((tag.clone(), None));
+                if !self.tag_colors.contains_key(&new) {
+                    colors.push((new, Some(*color)));
+                }
+            }
+        }
+        self.update_tag_colors(&colors)?;
+        Ok(changed.len())
+    }
+
+    /// Removes `tags` from every photo, leaving the tags below them alone.
+    /// Their colors go too. Returns the number of photos that changed.
+    pub fn delete_tags(&mut self, tags: &[String]) -> Result<usize, StorageError> {
+        let mut changed = Vec::new();
+        for (path, photo_note) in self.notes.iter_mut() {
+            if photo_note.tags.iter().any(|tag| tags.contains(tag)) {
+                photo_note.tags.retain(|tag| !tags.contains(tag));
+                changed.push(path.clone());
+            }
+        }
+        self.save_notes(&changed)?;
+        // Silinen etiketin rengi, aynı adla yeni bir etikete geçmesin
+        self.store.delete_tags(tags)?;
+        for tag in tags {
+            self.tag_colors.remove(tag);
+        }
+        Ok(changed.len())
+    }
+
+    /// The palette index chosen for `tag`, if any.
+    pub fn tag_color(&self, tag: &str) -> Option<usize> {
+        self.tag_colors.get(tag).copied()
+    }
+
+    /// Chooses the color of `tag`; `None` goes back to the default one.
+    pub fn set_tag_color(&mut self, tag: &str, color: Option<usize>) -> Result<(), StorageError> {
+        self.update_tag_colors(&[(tag.to_string(), color)])
+    }
+
+    fn update_tag_colors(&mut self, colors: &[(String, Option<usize>)]) -> Result<(), StorageError> {
+        self.store.set_tag_colors(colors)?;
+        for (tag, color) in colors {
+            match color {
+                Some(color) => self.tag_colors.insert(tag.clone(), *color),
+                None => self.tag_colors.remove(tag),
+            };
+        }
+        Ok(())
+    }
+
+    fn load_tag_colors(&mut self) -> Result<(), StorageError> {
+        self.tag_colors = self.store.load_tag_colors()?;
+        Ok(())
     }
 
     pub fn get_tags(&self, photo_path: &str) -> Vec<String> {
@@ -148,15 +1294,779 @@ impl PhotoManager {
         self.notes.get(photo_path)
     }
 
-    pub fn remove_note(&mut self, photo_path: &str) {
+    pub fn remove_note(&mut self, photo_path: &str) -> Result<(), StorageError> {
+        // Döndürme, puan ve işaretler notla birlikte silinmez
+        if let Some(photo_note) = self.notes.get_mut(photo_path) {
+            if photo_note.has_marks() {
+                photo_note.note.clear();
+                photo_note.tags.clear();
+                return self.save_note(photo_path);
+            }
+        }
         self.notes.remove(photo_path);
-        self.save_notes();
+        self.index_note(photo_path);
+        self.store.delete_note(photo_path)
     }
 
+    /// The rotate/flip override of a photo.
+    pub fn orientation(&self, photo_path: &str) -> Orientation {
+        self.notes
+            .get(photo_path)
+            .map(|photo_note| photo_note.orientation)
+            .unwrap_or_default()
+    }
 
+    /// Turns a photo further by `transform`, e.g. a quarter turn.
+    pub fn transform_photo(&mut self, photo_path: &str, transform: Orientation) -> Result<(), StorageError> {
+        let orientation = self.orientation(photo_path).then(transform);
+        self.set_orientation(photo_path, orientation)
+    }
 
-    pub fn clear_notes(&mut self) {
+    pub fn set_orientation(&mut self, photo_path: &str, orientation: Orientation) -> Result<(), StorageError> {
+        if let Some(photo_note) = self.notes.get_mut(photo_path) {
+            photo_note.orientation = orientation;
+            return self.save_note(photo_path);
+        }
+        if orientation.is_normal() {
+            return Ok(());
+        }
+        let photo_note = PhotoNote {
+            path: photo_path.to_string(),
+            note: String::new(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            content_hash: self.content_hash_for(photo_path),
+            orientation,
+            ..Default::default()
+        };
+        self.notes.insert(photo_path.to_string(), photo_note);
+        self.save_note(photo_path)
+    }
+
+    /// Notes whose photo no longer exists on disk, sorted by path.
+    pub fn orphaned_notes(&self) -> Vec<&PhotoNote> {
+        let mut orphans: Vec<&PhotoNote> = self
+            .notes
+            .values()
+            .filter(|note| !Path::new(&note.path).exists())
+            .collect();
+        orphans.sort_by(|a, b| a.path.cmp(&b.path));
+        orphans
+    }
+
+    /// Points the note of `from` at another file. If that file already has a
+    /// note, the two are merged so that nothing is lost.
+    pub fn relink_note(&mut self, from: &str, to: &str) -> Result<(), StorageError> {
+        if from == to {
+            return Ok(());
+        }
+        let mut note = match self.notes.get(from) {
+            Some(note) => note.clone(),
+            None => return Ok(()),
+        };
+
+        if let Some(existing) = self.notes.get(to) {
+            let mut text = existing.note.clone();
+            if !text.is_empty() && !note.note.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&note.note);
+            note.note = text;
+
+            let mut tags = existing.tags.clone();
+            for tag in note.tags {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            note.tags = tags;
+
+            if note.orientation.is_normal() {
+                note.orientation = existing.orientation;
+            }
+            note.rating = note.rating.max(existing.rating);
+            note.favorite |= existing.favorite;
+            if note.flag == Flag::Unflagged {
+                note.flag = existing.flag;
+            }
+        }
+        note.path = to.to_string();
+        note.content_hash = fingerprint(Path::new(to));
+
+        self.store.move_note(from, &note)?;
+        self.notes.remove(from);
+        self.notes.insert(to.to_string(), note);
+        self.index_note(from);
+        self.index_note(to);
+        self.relink_album_photos(&[Relinked { from: from.to_string(), to: to.to_string() }])
+    }
+
+    /// Deletes the notes of several photos in one transaction.
+    pub fn remove_notes(&mut self, photo_paths: &[String]) -> Result<(), StorageError> {
+        self.store.delete_notes(photo_paths)?;
+        for path in photo_paths {
+            self.notes.remove(path);
+            self.index_note(path);
+        }
+        Ok(())
+    }
+
+    /// Writes the notes of `photo_paths` to `dest` as versioned JSON.
+    pub fn export_notes(&self, photo_paths: &[String], dest: &Path) -> Result<(), StorageError> {
+        let notes: Vec<PhotoNote> = photo_paths
+            .iter()
+            .filter_map(|path| self.notes.get(path).cloned())
+            .collect();
+        storage::write_atomic(dest, serialize_notes(&notes)?.as_bytes())
+    }
+
+    /// Writes a timestamped backup of the notes database and keeps only the
+    /// `keep` most recent ones.
+    pub fn create_backup(&self, keep: usize) -> Result<PathBuf, StorageError> {
+        let backup = storage::backup_path(&self.notes_file);
+        self.store.backup_to(&backup)?;
+        storage::prune_backups(&self.notes_file, keep.max(1));
+        Ok(backup)
+    }
+
+    pub fn list_backups(&self) -> Vec<PathBuf> {
+        storage::list_backups(&self.notes_file)
+    }
+
+    /// Replaces all notes with the contents of `backup`.
+    pub fn restore_backup(&mut self, backup: &Path) -> Result<(), StorageError> {
+        self.store.restore_from(backup)?;
+        self.notes.clear();
+        self.search_index.clear();
+        self.load_notes()?;
+        self.load_smart_albums()?;
+        self.load_albums()?;
+        self.load_tag_colors()
+    }
+
+    pub fn clear_notes(&mut self) -> Result<(), StorageError> {
         self.notes.clear();
-        self.save_notes();
+        self.search_index.clear();
+        self.search_changed = true;
+        self.store.clear()
+    }
+
+    fn load_smart_albums(&mut self) -> Result<(), StorageError> {
+        self.smart_albums = self.store.load_smart_albums()?;
+        Ok(())
+    }
+
+    pub fn smart_albums(&self) -> &[SmartAlbum] {
+        &self.smart_albums
+    }
+
+    pub fn smart_album(&self, id: i64) -> Option<&SmartAlbum> {
+        self.smart_albums.iter().find(|album| album.id == id)
+    }
+
+    fn smart_album_index(&self, id: i64) -> Option<usize> {
+        self.smart_albums.iter().position(|album| album.id == id)
+    }
+
+    /// Saves `query` as a smart album at the end of the list.
+    pub fn add_smart_album(&mut self, name: &str, query: &str) -> Result<i64, StorageError> {
+        let id = self.store.insert_smart_album(name, query, self.smart_albums.len())?;
+        self.smart_albums.push(SmartAlbum { id, name: name.to_string(), query: query.to_string() });
+        Ok(id)
+    }
+
+    pub fn rename_smart_album(&mut self, id: i64, name: &str) -> Result<(), StorageError> {
+        let Some(index) = self.smart_album_index(id) else {
+            return Ok(());
+        };
+        let mut album = self.smart_albums[index].clone();
+        album.name = name.to_string();
+        self.store.update_smart_album(&album)?;
+        self.smart_albums[index] = album;
+        Ok(())
+    }
+
+    /// Copies a smart album under a new name, right below the original.
+    pub fn duplicate_smart_album(&mut self, id: i64, name: &str) -> Result<Option<i64>, StorageError> {
+        let Some(index) = self.smart_album_index(id) else {
+            return Ok(None);
+        };
+        let query = self.smart_albums[index].query.clone();
+        let copy = self.add_smart_album(name, &query)?;
+        self.move_smart_album(copy, index + 1)?;
+        Ok(Some(copy))
+    }
+
+    /// Moves a smart album to `index` in the list.
+    pub fn move_smart_album(&mut self, id: i64, index: usize) -> Result<(), StorageError> {
+        let Some(from) = self.smart_album_index(id) else {
+            return Ok(());
+        };
+        let mut albums = self.smart_albums.clone();
+        let album = albums.remove(from);
+        albums.insert(index.min(albums.len()), album);
+        let order: Vec<i64> = albums.iter().map(|album| album.id).collect();
+        self.store.reorder_smart_albums(&order)?;
+        self.smart_albums = albums;
+        Ok(())
+    }
+
+    pub fn delete_smart_album(&mut self, id: i64) -> Result<(), StorageError> {
+        self.store.delete_smart_album(id)?;
+        self.smart_albums.retain(|album| album.id != id);
+        Ok(())
+    }
+
+    fn load_albums(&mut self) -> Result<(), StorageError> {
+        self.albums = self.store.load_albums()?;
+        Ok(())
+    }
+
+    pub fn albums(&self) -> &[Album] {
+        &self.albums
+    }
+
+    pub fn album(&self, id: i64) -> Option<&Album> {
+        self.albums.iter().find(|album| album.id == id)
+    }
+
+    fn album_index(&self, id: i64) -> Option<usize> {
+        self.albums.iter().position(|album| album.id == id)
+    }
+
+    fn is_in_album(&self, photo_path: &str) -> bool {
+        self.albums.iter().any(|album| album.photos.iter().any(|photo| photo == photo_path))
+    }
+
+    /// Creates an empty album at the end of the list.
+    pub fn create_album(&mut self, name: &str, description: &str) -> Result<i64, StorageError> {
+        let id = self.store.insert_album(name, description, self.albums.len())?;
+        self.albums.push(Album {
+            id,
+            name: name.to_string(),
+            description: description.to_string(),
+            cover: None,
+            photos: Vec::new(),
+        });
+        Ok(id)
+    }
+
+    /// Applies `change` to a copy of the album and stores it in one write.
+    fn update_album<F: FnOnce(&mut Album)>(&mut self, id: i64, change: F) -> Result<(), StorageError> {
+        let Some(index) = self.album_index(id) else {
+            return Ok(());
+        };
+        let mut album = self.albums[index].clone();
+        change(&mut album);
+        if album != self.albums[index] {
+            self.store.update_album(&album)?;
+            self.albums[index] = album;
+        }
+        Ok(())
+    }
+
+    pub fn edit_album(&mut self, id: i64, name: &str, description: &str) -> Result<(), StorageError> {
+        self.update_album(id, |album| {
+            album.name = name.to_string();
+            album.description = description.to_string();
+        })
+    }
+
+    /// Sets the cover of an album; `None` falls back to the first photo.
+    pub fn set_album_cover(&mut self, id: i64, cover: Option<&str>) -> Result<(), StorageError> {
+        self.update_album(id, |album| album.cover = cover.map(str::to_string))
+    }
+
+    /// Appends the photos that are not in the album yet and returns how
+    /// many were added.
+    pub fn add_to_album(&mut self, id: i64, photo_paths: &[String]) -> Result<usize, StorageError> {
+        let mut added = 0;
+        self.update_album(id, |album| {
+            for path in photo_paths {
+                if !album.photos.contains(path) {
+                    album.photos.push(path.clone());
+                    added += 1;
+                }
+            }
+        })?;
+        Ok(added)
+    }
+
+    pub fn remove_from_album(&mut self, id: i64, photo_paths: &[String]) -> Result<(), StorageError> {
+        self.update_album(id, |album| {
+            album.photos.retain(|photo| !photo_paths.contains(photo));
+            if album.cover.as_ref().is_some_and(|cover| photo_paths.contains(cover)) {
+                album.cover = None;
+            }
+        })
+    }
+
+    /// Moves a photo to `index` within its album.
+    pub fn move_in_album(&mut self, id: i64, photo_path: &str, index: usize) -> Result<(), StorageError> {
+        self.update_album(id, |album| {
+            if let Some(from) = album.photos.iter().position(|photo| photo == photo_path) {
+                let photo = album.photos.remove(from);
+                album.photos.insert(index.min(album.photos.len()), photo);
+            }
+        })
+    }
+
+    /// Moves an album to `index` in the list.
+    pub fn move_album(&mut self, id: i64, index: usize) -> Result<(), StorageError> {
+        let Some(from) = self.album_index(id) else {
+            return Ok(());
+        };
+        let mut albums = self.albums.clone();
+        let album = albums.remove(from);
+        albums.insert(index.min(albums.len()), album);
+        let order: Vec<i64> = albums.iter().map(|album| album.id).collect();
+        self.store.reorder_albums(&order)?;
+        self.albums = albums;
+        Ok(())
+    }
+
+    pub fn delete_album(&mut self, id: i64) -> Result<(), StorageError> {
+        self.store.delete_album(id)?;
+        self.albums.retain(|album| album.id != id);
+        Ok(())
+    }
+
+    /// Keeps album entries on photos that moved. A photo that ends up twice
+    /// in an album keeps its first place.
+    fn relink_album_photos(&mut self, moves: &[Relinked]) -> Result<(), StorageError> {
+        if !moves.iter().any(|moved| self.is_in_album(&moved.from)) {
+            return Ok(());
+        }
+        self.store.relink_album_photos(moves)?;
+        for album in &mut self.albums {
+            for moved in moves {
+                if let Some(index) = album.photos.iter().position(|photo| *photo == moved.from) {
+                    if album.photos.contains(&moved.to) {
+                        album.photos.remove(index);
+                    } else {
+                        album.photos[index] = moved.to.clone();
+                    }
+                }
+                if album.cover.as_deref() == Some(moved.from.as_str()) {
+                    album.cover = Some(moved.to.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn manager_in(dir: &Path) -> PhotoManager {
+        PhotoManager::with_notes_file(dir.join("notes.db"))
+    }
+
+    fn sample_note() -> Value {
+        json!({
+            "path": "/home/user/Pictures/a.jpg",
+            "note": "Kapadokya",
+            "timestamp": "2025-01-01T00:00:00+00:00",
+            "tags": ["travel"]
+        })
+    }
+
+    #[test]
+    fn bare_array_is_version_zero() {
+        assert_eq!(notes_version(&json!([])).unwrap(), 0);
+        assert_eq!(notes_version(&json!({ "version": 1, "notes": [] })).unwrap(), 1);
+        assert!(notes_version(&json!("notes")).is_err());
+    }
+
+    #[test]
+    fn migrate_v0_to_v1_wraps_array() {
+        let migrated = migrate_v0_to_v1(json!([sample_note()])).unwrap();
+        assert_eq!(migrated, json!({ "version": 1, "notes": [sample_note()] }));
+    }
+
+    #[test]
+    fn migrate_v1_to_v2_adds_content_hash() {
+        let v1 = json!({ "version": 1, "notes": [sample_note()] });
+        let migrated = migrate_v1_to_v2(v1).unwrap();
+        assert_eq!(migrated["version"], 2);
+        assert_eq!(migrated["notes"][0]["content_hash"], Value::Null);
+        assert_eq!(migrated["notes"][0]["note"], "Kapadokya");
+    }
+
+    #[test]
+    fn migrate_v1_to_v2_keeps_existing_hash() {
+        let mut note = sample_note();
+        note["content_hash"] = json!("00ff");
+        let migrated = migrate_v1_to_v2(json!({ "version": 1, "notes": [note] })).unwrap();
+        assert_eq!(migrated["notes"][0]["content_hash"], "00ff");
+    }
+
+    #[test]
+    fn migrate_v2_to_v3_adds_orientation() {
+        let v2 = json!({ "version": 2, "notes": [sample_note()] });
+        let migrated = migrate_v2_to_v3(v2).unwrap();
+        assert_eq!(migrated["version"], 3);
+        let note: PhotoNote = serde_json::from_value(migrated["notes"][0].clone()).unwrap();
+        assert_eq!(note.orientation, Orientation::NORMAL);
+
+        let mut note = sample_note();
+        note["orientation"] = json!(6);
+        let migrated = migrate_v2_to_v3(json!({ "version": 2, "notes": [note] })).unwrap();
+        let note: PhotoNote = serde_json::from_value(migrated["notes"][0].clone()).unwrap();
+        assert_eq!(note.orientation, Orientation::ROTATE_CLOCKWISE);
+    }
+
+    #[test]
+    fn fingerprint_ignores_file_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.join("a.jpg"), &content).unwrap();
+        fs::write(dir.join("b.jpg"), &content).unwrap();
+        let mut changed = content.clone();
+        changed[199_999] ^= 1;
+        fs::write(dir.join("c.jpg"), &changed).unwrap();
+
+        let a = fingerprint(&dir.join("a.jpg")).unwrap();
+        assert_eq!(fingerprint_size(&a), Some(200_000));
+        assert_eq!(Some(a.clone()), fingerprint(&dir.join("b.jpg")));
+        assert_ne!(Some(a), fingerprint(&dir.join("c.jpg")));
+        assert_eq!(fingerprint(&dir.join("missing.jpg")), None);
+        assert_eq!(fingerprint_size("00ff00ff00ff00ff"), None);
+    }
+
+    #[test]
+    fn moved_notes_with_identical_content_are_all_relinked() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        fs::write(dir.join("copy1.jpg"), b"same").unwrap();
+        fs::write(dir.join("copy2.jpg"), b"same").unwrap();
+        fs::write(dir.join("other.jpg"), b"different").unwrap();
+        fs::write(dir.join("kept.jpg"), b"kept").unwrap();
+        let hash = fingerprint(&dir.join("copy1.jpg"));
+
+        let notes = vec![
+            (path("gone1.jpg"), hash.clone()),
+            (path("gone2.jpg"), hash),
+            (path("legacy.jpg"), Some("00ff00ff00ff00ff".to_string())),
+            (path("kept.jpg"), None),
+        ];
+        let photos = vec![path("copy1.jpg"), path("copy2.jpg"), path("other.jpg"), path("kept.jpg")];
+        let moved = find_moved_notes(&notes, &photos);
+
+        assert_eq!(
+            moved.relinked,
+            vec![
+                Relinked { from: path("gone1.jpg"), to: path("copy1.jpg") },
+                Relinked { from: path("gone2.jpg"), to: path("copy2.jpg") },
+            ]
+        );
+        assert_eq!(moved.fingerprints, vec![(path("kept.jpg"), fingerprint(&dir.join("kept.jpg")).unwrap())]);
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("node_modules", "node_modules"));
+        assert!(glob_match("*.tmp", "photo.tmp"));
+        assert!(glob_match("IMG_????.jpg", "IMG_0042.jpg"));
+        assert!(!glob_match("IMG_????.jpg", "IMG_42.jpg"));
+        assert!(!glob_match("*.tmp", "cache/photo.tmp"));
+        assert!(glob_match("exports/*", "exports/a.jpg"));
+        assert!(!glob_match("exports/*", "exports/2024/a.jpg"));
+        assert!(glob_match("**/raw", "2024/trip/raw"));
+        assert!(glob_match("**/raw", "raw"));
+        assert!(glob_match("exports/**", "exports/2024/a.jpg"));
+        assert!(glob_match("**/trip/*.jpg", "2024/summer/trip/a.jpg"));
+        assert!(!glob_match("**/trip/*.jpg", "2024/trip/summer/a.jpg"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("*?", ""));
+        assert!(glob_match("**", ""));
+    }
+
+    #[test]
+    fn glob_match_is_not_exponential() {
+        let text = "a".repeat(64);
+        let pattern = format!("{}b", "*a".repeat(32));
+        assert!(!glob_match(&pattern, &text));
+        let pattern = format!("{}b", "**/a".repeat(32));
+        assert!(!glob_match(&pattern, &"a/".repeat(64)));
+    }
+
+    #[test]
+    fn scan_options_skip_hidden_and_excluded_names() {
+        let options = ScanOptions::default();
+        assert!(options.is_excluded(".thumbnails", ".thumbnails"));
+        assert!(options.is_excluded(".hidden.jpg", "a/.hidden.jpg"));
+        assert!(options.is_excluded("node_modules", "web/node_modules"));
+        assert!(!options.is_excluded("holiday.jpg", "2024/holiday.jpg"));
+
+        let options = ScanOptions {
+            include_hidden: true,
+            exclude_patterns: vec!["2024/drafts".to_string()],
+            ..ScanOptions::default()
+        };
+        assert!(!options.is_excluded(".hidden.jpg", ".hidden.jpg"));
+        assert!(options.is_excluded("drafts", "2024/drafts"));
+        assert!(!options.is_excluded("drafts", "2025/drafts"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scan_library_follows_symlinks_without_looping() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::create_dir_all(root.join("sub/deeper")).unwrap();
+        fs::create_dir_all(root.join("node_modules")).unwrap();
+        fs::create_dir_all(root.join(".hidden")).unwrap();
+        for file in ["a.jpg", "sub/b.png", "sub/deeper/c.gif", "node_modules/d.jpg", ".hidden/e.jpg", "notes.txt"] {
+            fs::write(root.join(file), b"x").unwrap();
+        }
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("a.jpg"), root.join("sub/link.jpg")).unwrap();
+
+        let scan = |options: &ScanOptions| {
+            let mut photos: Vec<String> = scan_library(std::slice::from_ref(&root), options, &mut |_, _| true)
+                .photos
+                .into_iter()
+                .map(|p| Path::new(&p).strip_prefix(&root).unwrap().to_string_lossy().to_string())
+                .collect();
+            photos.sort();
+            photos
+        };
+
+        let options = ScanOptions::default();
+        assert_eq!(scan(&options), vec!["a.jpg", "sub/b.png", "sub/deeper/c.gif"]);
+
+        let options = ScanOptions { max_depth: 1, ..ScanOptions::default() };
+        assert_eq!(scan(&options), vec!["a.jpg", "sub/b.png"]);
+
+        let options = ScanOptions { follow_symlinks: true, ..ScanOptions::default() };
+        assert_eq!(scan(&options), vec!["a.jpg", "sub/b.png", "sub/deeper/c.gif"]);
+
+        let options = ScanOptions { include_hidden: true, exclude_patterns: Vec::new(), ..ScanOptions::default() };
+        assert_eq!(
+            scan(&options),
+            vec![".hidden/e.jpg", "a.jpg", "node_modules/d.jpg", "sub/b.png", "sub/deeper/c.gif"]
+        );
+
+        let mut folders_seen = 0;
+        let scan = scan_library(std::slice::from_ref(&root), &ScanOptions::default(), &mut |_, _| {
+            folders_seen += 1;
+            false
+        });
+        assert!(scan.cancelled);
+        assert_eq!(folders_seen, 1);
+        assert_eq!(scan.folders.len(), 1);
+    }
+
+    #[test]
+    fn renamed_path_maps_files_inside_the_renamed_folder() {
+        let from = Path::new("/photos/trip");
+        let to = Path::new("/photos/2024 trip");
+        assert_eq!(renamed_path(from, to, "/photos/trip/a.jpg").as_deref(), Some("/photos/2024 trip/a.jpg"));
+        assert_eq!(renamed_path(from, to, "/photos/trip").as_deref(), Some("/photos/2024 trip"));
+        assert_eq!(renamed_path(from, to, "/photos/trips/a.jpg"), None);
+    }
+
+    #[test]
+    fn migrate_notes_upgrades_bare_array() {
+        let envelope = migrate_notes(json!([sample_note()])).unwrap();
+        assert_eq!(envelope.version, NOTES_FORMAT_VERSION);
+        let note: PhotoNote = serde_json::from_value(envelope.notes[0].clone()).unwrap();
+        assert_eq!(note.note, "Kapadokya");
+        assert_eq!(note.tags, vec!["travel".to_string()]);
+    }
+
+    #[test]
+    fn migrate_notes_keeps_current_version() {
+        let current = json!({ "version": NOTES_FORMAT_VERSION, "notes": [sample_note()] });
+        let envelope = migrate_notes(current).unwrap();
+        assert_eq!(envelope.notes, vec![sample_note()]);
+    }
+
+    #[test]
+    fn migrate_notes_rejects_newer_version() {
+        let future = json!({ "version": NOTES_FORMAT_VERSION + 1, "notes": [] });
+        assert!(matches!(
+            migrate_notes(future),
+            Err(StorageError::UnsupportedVersion(v)) if v == NOTES_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn serialized_notes_round_trip() {
+        let note: PhotoNote = serde_json::from_value(sample_note()).unwrap();
+        let json = serialize_notes(std::slice::from_ref(&note)).unwrap();
+        let envelope = migrate_notes(serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(envelope.version, NOTES_FORMAT_VERSION);
+        let parsed: PhotoNote = serde_json::from_value(envelope.notes[0].clone()).unwrap();
+        assert_eq!(parsed.path, note.path);
+        assert_eq!(parsed.tags, note.tags);
+    }
+
+    #[test]
+    fn album_cover_falls_back_to_first_photo() {
+        let mut album = Album {
+            id: 1,
+            name: "Trip".into(),
+            description: String::new(),
+            cover: None,
+            photos: vec!["/a.jpg".into(), "/b.jpg".into()],
+        };
+        assert_eq!(album.cover_photo(), Some("/a.jpg"));
+        album.cover = Some("/b.jpg".into());
+        assert_eq!(album.cover_photo(), Some("/b.jpg"));
+        album.cover = Some("/gone.jpg".into());
+        assert_eq!(album.cover_photo(), Some("/a.jpg"));
+        album.photos.clear();
+        assert_eq!(album.cover_photo(), None);
+    }
+
+    #[test]
+    fn migrate_v3_to_v4_adds_rating_and_flags() {
+        let mut note = sample_note();
+        note["orientation"] = json!(1);
+        let migrated = migrate_v3_to_v4(json!({ "version": 3, "notes": [note] })).unwrap();
+        assert_eq!(migrated["version"], 4);
+        let note: PhotoNote = serde_json::from_value(migrated["notes"][0].clone()).unwrap();
+        assert_eq!((note.rating, note.favorite, note.flag), (0, false, Flag::Unflagged));
+        assert!(!note.has_marks());
+
+        let mut note = sample_note();
+        note["flag"] = json!("reject");
+        let migrated = migrate_v3_to_v4(json!({ "version": 3, "notes": [note] })).unwrap();
+        let note: PhotoNote = serde_json::from_value(migrated["notes"][0].clone()).unwrap();
+        assert_eq!(note.flag, Flag::Reject);
+        assert!(note.has_marks());
+    }
+
+    #[test]
+    fn every_version_has_a_migration() {
+        assert_eq!(MIGRATIONS.len() as u64, NOTES_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn renaming_onto_an_existing_tag_merges_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        manager.set_note("/a.jpg", String::new(), vec!["trip".into(), "travel".into()]).unwrap();
+        manager.set_note("/b.jpg", String::new(), vec!["trip".into()]).unwrap();
+
+        assert_eq!(manager.rename_tag("trip", "travel").unwrap(), 2);
+        assert_eq!(manager.get_tags("/a.jpg"), ["travel"]);
+        assert_eq!(manager.get_tags("/b.jpg"), ["travel"]);
+        let reloaded = manager_in(dir.path());
+        assert_eq!(reloaded.get_tags("/a.jpg"), ["travel"]);
+    }
+
+    #[test]
+    fn renaming_a_tag_moves_the_tags_below_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        manager.set_note("/a.jpg", String::new(), vec!["places/turkey".into(), "other".into()]).unwrap();
+
+        // Bir etiket kendi altına taşınamaz
+        assert_eq!(manager.rename_tag("places", "places/x").unwrap(), 0);
+        assert_eq!(manager.get_tags("/a.jpg"), ["places/turkey", "other"]);
+
+        assert_eq!(manager.rename_tag("places", "trips/places").unwrap(), 1);
+        assert_eq!(manager.get_tags("/a.jpg"), ["trips/places/turkey", "other"]);
+    }
+
+    #[test]
+    fn renaming_a_tag_carries_its_color() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        manager.set_note("/a.jpg", String::new(), vec!["places".into(), "trip".into(), "travel".into()]).unwrap();
+        manager.set_tag_color("places", Some(2)).unwrap();
+        manager.set_tag_color("trip", Some(1)).unwrap();
+        manager.set_tag_color("travel", Some(3)).unwrap();
+
+        manager.rename_tag("places", "world").unwrap();
+        assert_eq!(manager.tag_color("world"), Some(2));
+        assert_eq!(manager.tag_color("places"), None);
+
+        // Birleşmede hedefin rengi kalır
+        manager.rename_tag("trip", "travel").unwrap();
+        assert_eq!(manager.tag_color("travel"), Some(3));
+        assert_eq!(manager.tag_color("trip"), None);
+
+        let reloaded = manager_in(dir.path());
+        assert_eq!(reloaded.tag_color("world"), Some(2));
+        assert_eq!(reloaded.tag_color("travel"), Some(3));
+        assert_eq!(reloaded.tag_color("places"), None);
+    }
+
+    #[test]
+    fn deleting_tags_removes_them_and_their_colors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        manager.set_note("/a.jpg", String::new(), vec!["places".into(), "places/turkey".into(), "trip".into()]).unwrap();
+        manager.set_note("/b.jpg", String::new(), vec!["trip".into()]).unwrap();
+        manager.set_tag_color("places", Some(2)).unwrap();
+        manager.set_tag_color("trip", Some(1)).unwrap();
+
+        let deleted = ["places".to_string(), "trip".to_string()];
+        assert_eq!(manager.delete_tags(&deleted).unwrap(), 2);
+        assert_eq!(manager.get_tags("/a.jpg"), ["places/turkey"]);
+        assert!(manager.get_tags("/b.jpg").is_empty());
+        assert_eq!(manager.tag_color("places"), None);
+
+        let reloaded = manager_in(dir.path());
+        assert_eq!(reloaded.get_tags("/a.jpg"), ["places/turkey"]);
+        assert_eq!(reloaded.tag_color("places"), None);
+        assert_eq!(reloaded.tag_color("trip"), None);
+    }
+
+    #[test]
+    fn clearing_the_rating_of_a_photo_without_a_note_creates_none() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+
+        manager.set_rating(&["/a.jpg".to_string()], 0).unwrap();
+        assert!(manager.get_note("/a.jpg").is_none());
+        assert!(manager_in(dir.path()).notes.is_empty());
+    }
+
+    #[test]
+    fn marking_a_photo_without_a_note_creates_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("a.jpg");
+        fs::write(&photo, b"pixels").unwrap();
+        let photo = photo.to_string_lossy().to_string();
+        let mut manager = manager_in(dir.path());
+
+        let photos = [photo.clone()];
+        manager.set_favorite(&photos, true).unwrap();
+        manager.set_rating(&photos, 4).unwrap();
+
+        let reloaded = manager_in(dir.path());
+        assert_eq!(reloaded.notes.len(), 1);
+        let note = reloaded.get_note(&photo).unwrap();
+        assert!(note.favorite);
+        assert_eq!(note.rating, 4);
+        assert_eq!(note.content_hash, fingerprint(Path::new(&photo)));
+        assert!(note.content_hash.is_some());
+    }
+
+    #[test]
+    fn relinking_onto_a_noted_photo_merges_the_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        manager.set_note("/old.jpg", "beach".into(), vec!["trip".into(), "sea".into()]).unwrap();
+        manager.set_note("/new.jpg", "sunset".into(), vec!["sea".into(), "evening".into()]).unwrap();
+        manager.set_rating(&["/new.jpg".to_string()], 3).unwrap();
+
+        manager.relink_note("/old.jpg", "/new.jpg").unwrap();
+
+        let reloaded = manager_in(dir.path());
+        assert!(reloaded.get_note("/old.jpg").is_none());
+        let note = reloaded.get_note("/new.jpg").unwrap();
+        assert_eq!(note.tags, ["sea", "evening", "trip"]);
+        assert_eq!(note.note, "sunset\n\nbeach");
+        assert_eq!(note.rating, 3);
     }
 }
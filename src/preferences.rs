@@ -0,0 +1,295 @@
+// preferences.rs
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gtk::{gio, glib, CompositeTemplate};
+use gettextrs::gettext;
+use std::path::{Path, PathBuf};
+use crate::photo_manager::{PhotoManager, ScanOptions};
+
+/// Library folders from the settings, or the default folders while the
+/// user has not configured any.
+pub fn library_folders(settings: &gio::Settings) -> Vec<PathBuf> {
+    if settings.user_value("library-folders").is_none() {
+        return PhotoManager::default_library_roots();
+    }
+    settings
+        .strv("library-folders")
+        .iter()
+        .map(|folder| PathBuf::from(folder.as_str()))
+        .collect()
+}
+
+/// Scan options from the settings.
+pub fn scan_options(settings: &gio::Settings) -> ScanOptions {
+    ScanOptions {
+        max_depth: settings.int("scan-max-depth").max(0) as usize,
+        exclude_patterns: settings
+            .strv("scan-exclude-patterns")
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect(),
+        include_hidden: settings.boolean("scan-include-hidden"),
+        follow_symlinks: settings.boolean("scan-follow-symlinks"),
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/preferences.ui")]
+    pub struct NotiaPreferencesDialog {
+        #[template_child]
+        pub add_folder_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub folder_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub max_depth_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub include_hidden_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub follow_symlinks_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub exclude_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub exclude_entry: TemplateChild<adw::EntryRow>,
+        #[template_child]
+        pub backup_count_row: TemplateChild<adw::SpinRow>,
+
+        pub settings: gio::Settings,
+    }
+
+    impl Default for NotiaPreferencesDialog {
+        fn default() -> Self {
+            Self {
+                add_folder_button: TemplateChild::default(),
+                folder_list: TemplateChild::default(),
+                max_depth_row: TemplateChild::default(),
+                include_hidden_row: TemplateChild::default(),
+                follow_symlinks_row: TemplateChild::default(),
+                exclude_list: TemplateChild::default(),
+                exclude_entry: TemplateChild::default(),
+                backup_count_row: TemplateChild::default(),
+                settings: gio::Settings::new("com.vastsea.notia"),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for NotiaPreferencesDialog {
+        const NAME: &'static str = "NotiaPreferencesDialog";
+        type Type = super::NotiaPreferencesDialog;
+        type ParentType = adw::PreferencesDialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for NotiaPreferencesDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.setup_callbacks();
+            obj.reload_folders();
+            obj.reload_exclude_patterns();
+        }
+    }
+
+    impl WidgetImpl for NotiaPreferencesDialog {}
+    impl AdwDialogImpl for NotiaPreferencesDialog {}
+    impl PreferencesDialogImpl for NotiaPreferencesDialog {}
+}
+
+glib::wrapper! {
+    pub struct NotiaPreferencesDialog(ObjectSubclass<imp::NotiaPreferencesDialog>)
+        @extends gtk::Widget, adw::Dialog, adw::PreferencesDialog;
+}
+
+impl NotiaPreferencesDialog {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    fn setup_callbacks(&self) {
+        let imp = self.imp();
+
+        imp.add_folder_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.choose_folder();
+            }
+        ));
+
+        let placeholder = gtk::Label::new(Some(&gettext("No library folders")));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(12);
+        placeholder.set_margin_bottom(12);
+        imp.folder_list.set_placeholder(Some(&placeholder));
+
+        imp.exclude_entry.connect_apply(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |entry| {
+                let pattern = entry.text().trim().to_string();
+                if !pattern.is_empty() {
+                    dialog.add_exclude_pattern(&pattern);
+                }
+                entry.set_text("");
+            }
+        ));
+
+        Self::bind_int(&imp.settings, "scan-max-depth", &imp.max_depth_row);
+        imp.settings.bind("scan-include-hidden", &*imp.include_hidden_row, "active").build();
+        imp.settings.bind("scan-follow-symlinks", &*imp.follow_symlinks_row, "active").build();
+        Self::bind_int(&imp.settings, "backup-count", &imp.backup_count_row);
+    }
+
+    /// Keeps an integer setting and a spin row in sync.
+    fn bind_int(settings: &gio::Settings, key: &'static str, row: &adw::SpinRow) {
+        row.set_value(settings.int(key) as f64);
+        row.connect_value_notify(glib::clone!(
+            #[strong]
+            settings,
+            move |row| {
+                let value = row.value() as i32;
+                if settings.int(key) != value {
+                    let _ = settings.set_int(key, value);
+                }
+            }
+        ));
+    }
+
+    fn reload_folders(&self) {
+        let imp = self.imp();
+        imp.folder_list.remove_all();
+
+        for folder in library_folders(&imp.settings) {
+            let row = adw::ActionRow::builder()
+                .title(folder.file_name().unwrap_or_default().to_string_lossy().to_string())
+                .subtitle(folder.to_string_lossy().to_string())
+                .use_markup(false)
+                .build();
+            row.add_prefix(&gtk::Image::from_icon_name("folder-symbolic"));
+
+            let remove_button = gtk::Button::from_icon_name("list-remove-symbolic");
+            remove_button.set_valign(gtk::Align::Center);
+            remove_button.set_tooltip_text(Some(&gettext("Remove Folder")));
+            remove_button.add_css_class("flat");
+            remove_button.connect_clicked(glib::clone!(
+                #[weak(rename_to = dialog)]
+                self,
+                move |_| {
+                    dialog.remove_folder(&folder);
+                }
+            ));
+            row.add_suffix(&remove_button);
+
+            imp.folder_list.append(&row);
+        }
+    }
+
+    fn save_folders(&self, folders: &[PathBuf]) {
+        let folders: Vec<String> = folders
+            .iter()
+            .map(|folder| folder.to_string_lossy().to_string())
+            .collect();
+        if let Err(err) = self.imp().settings.set_strv("library-folders", folders) {
+            self.show_settings_error(&gettext("Could not save library folders"), &err);
+        }
+        self.reload_folders();
+    }
+
+    /// Tells the user a setting could not be changed, e.g. because an
+    /// administrator locked it.
+    fn show_settings_error(&self, message: &str, err: &glib::BoolError) {
+        let toast = adw::Toast::builder()
+            .title(format!("{}: {}", message, err))
+            .use_markup(false)
+            .build();
+        self.add_toast(toast);
+    }
+
+    fn choose_folder(&self) {
+        let file_dialog = gtk::FileDialog::builder()
+            .title(gettext("Add Library Folder"))
+            .modal(true)
+            .build();
+
+        let parent = self.root().and_downcast::<gtk::Window>();
+        file_dialog.select_folder(parent.as_ref(), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |result| {
+                if let Some(folder) = result.ok().and_then(|file| file.path()) {
+                    let mut folders = library_folders(&dialog.imp().settings);
+                    if !folders.contains(&folder) {
+                        folders.push(folder);
+                        dialog.save_folders(&folders);
+                    }
+                }
+            }
+        ));
+    }
+
+    fn remove_folder(&self, folder: &Path) {
+        let mut folders = library_folders(&self.imp().settings);
+        folders.retain(|f| f.as_path() != folder);
+        self.save_folders(&folders);
+    }
+
+    fn reload_exclude_patterns(&self) {
+        let imp = self.imp();
+        imp.exclude_list.remove_all();
+
+        for pattern in imp.settings.strv("scan-exclude-patterns") {
+            let pattern = pattern.to_string();
+            let row = adw::ActionRow::builder()
+                .title(pattern.as_str())
+                .use_markup(false)
+                .build();
+
+            let remove_button = gtk::Button::from_icon_name("list-remove-symbolic");
+            remove_button.set_valign(gtk::Align::Center);
+            remove_button.set_tooltip_text(Some(&gettext("Remove Pattern")));
+            remove_button.add_css_class("flat");
+            remove_button.connect_clicked(glib::clone!(
+                #[weak(rename_to = dialog)]
+                self,
+                move |_| {
+                    dialog.remove_exclude_pattern(&pattern);
+                }
+            ));
+            row.add_suffix(&remove_button);
+
+            imp.exclude_list.append(&row);
+        }
+        imp.exclude_list.set_visible(imp.exclude_list.first_child().is_some());
+    }
+
+    fn save_exclude_patterns(&self, patterns: Vec<String>) {
+        if let Err(err) = self.imp().settings.set_strv("scan-exclude-patterns", patterns) {
+            self.show_settings_error(&gettext("Could not save exclude patterns"), &err);
+        }
+        self.reload_exclude_patterns();
+    }
+
+    fn add_exclude_pattern(&self, pattern: &str) {
+        let mut patterns = scan_options(&self.imp().settings).exclude_patterns;
+        if !patterns.iter().any(|p| p == pattern) {
+            patterns.push(pattern.to_string());
+            self.save_exclude_patterns(patterns);
+        }
+    }
+
+    fn remove_exclude_pattern(&self, pattern: &str) {
+        let mut patterns = scan_options(&self.imp().settings).exclude_patterns;
+        patterns.retain(|p| p != pattern);
+        self.save_exclude_patterns(patterns);
+    }
+}
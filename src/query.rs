@@ -0,0 +1,567 @@
+// query.rs
+//! The search bar's query language, e.g.
+//! `tag:client-a AND (tag:final OR tag:approved) -tag:rejected note:"invoice" before:2025-01-01`.
+//!
+//! Words without a field match the file name, note or tags. Terms next to
+//! each other must all match; `OR` needs only one side, `-` or `NOT`
+//! excludes. Tag values may use `*` and `?`, and a tag also matches the
+//! photos tagged below it, so `tag:places` finds `places/turkey`. Dates are `YYYY`, `YYYY-MM`
+//! or `YYYY-MM-DD`. `rating:` takes a number of stars, optionally after
+//! `>=`, `>`, `<=` or `<`; `is:` takes `favorite`, `pick`, `reject` or
+//! `unflagged`.
+use chrono::{Datelike, NaiveDate};
+use gettextrs::gettext;
+use std::fmt;
+use crate::photo_manager::{glob_match, Flag};
+use crate::tags;
+use crate::search::normalize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// The query ends where a search term is needed, e.g. after `OR`.
+    UnexpectedEnd { after: String },
+    /// `AND` or `OR` without a term in front of it.
+    MisplacedOperator { operator: String, column: usize },
+    UnclosedParenthesis { column: usize },
+    UnmatchedParenthesis { column: usize },
+    UnterminatedQuote { column: usize },
+    UnknownField { field: String, column: usize },
+    EmptyValue { field: String, column: usize },
+    InvalidDate { value: String, column: usize },
+    InvalidRating { value: String, column: usize },
+    UnknownState { value: String, column: usize },
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Kullanıcının yazdığı değer en son yerleştirilir ki içindeki
+        // "{column}" gibi metinler değiştirilmesin
+        let message = match self {
+            QueryError::UnexpectedEnd { after } => {
+                gettext("a search term is missing after “{after}”").replace("{after}", after)
+            }
+            QueryError::MisplacedOperator { operator, column } => {
+                gettext("“{operator}” at column {column} needs a search term before it")
+                    .replace("{column}", &column.to_string())
+                    .replace("{operator}", operator)
+            }
+            QueryError::UnclosedParenthesis { column } => {
+                gettext("the parenthesis opened at column {column} is never closed")
+                    .replace("{column}", &column.to_string())
+            }
+            QueryError::UnmatchedParenthesis { column } => {
+                gettext("the parenthesis at column {column} closes nothing").replace("{column}", &column.to_string())
+            }
+            QueryError::UnterminatedQuote { column } => {
+                gettext("the quote opened at column {column} is never closed").replace("{column}", &column.to_string())
+            }
+            QueryError::UnknownField { field, column } => gettext(
+                "unknown field “{field}:” at column {column}; use tag:, note:, name:, before:, after:, rating: or is:",
+            )
+            .replace("{column}", &column.to_string())
+            .replace("{field}", field),
+            QueryError::EmptyValue { field, column } => gettext("“{field}:” at column {column} needs a value")
+                .replace("{column}", &column.to_string())
+                .replace("{field}", field),
+            QueryError::InvalidDate { value, column } => {
+                gettext("“{value}” at column {column} is not a date; use YYYY, YYYY-MM or YYYY-MM-DD")
+                    .replace("{column}", &column.to_string())
+                    .replace("{value}", value)
+            }
+            QueryError::InvalidRating { value, column } => {
+                gettext("“{value}” at column {column} is not a rating; use 0 to 5 stars, e.g. 4 or >=3")
+                    .replace("{column}", &column.to_string())
+                    .replace("{value}", value)
+            }
+            QueryError::UnknownState { value, column } => gettext(
+                "“is:{value}” at column {column} is unknown; use is:favorite, is:pick, is:reject or is:unflagged",
+            )
+            .replace("{column}", &column.to_string())
+            .replace("{value}", value),
+        };
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// A parsed query. Text values are normalized with
+/// [`normalize`](crate::search::normalize).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Query {
+    /// The empty query, matching every photo.
+    #[default]
+    All,
+    Text(String),
+    Tag(String),
+    /// A tag and the tags below it, without wildcards. Not written in
+    /// queries; selecting a tag in the tag list shows its photos this way.
+    TagBranch(String),
+    Note(String),
+    Name(String),
+    /// Taken before the given day.
+    Before(NaiveDate),
+    /// Taken on or after the given day.
+    After(NaiveDate),
+    /// Rated within the range of stars, both ends included.
+    Rating(u8, u8),
+    Favorite,
+    Flag(Flag),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+/// What a query is matched against, normalized like the query.
+pub struct Candidate<'a> {
+    pub name: &'a str,
+    pub note: &'a str,
+    pub tags: &'a [String],
+    pub date: Option<NaiveDate>,
+    pub rating: u8,
+    pub favorite: bool,
+    pub flag: Flag,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(text)?;
+        if tokens.is_empty() {
+            return Ok(Query::All);
+        }
+        let mut parser = Parser { tokens, position: 0 };
+        let query = parser.parse_or()?;
+        match parser.tokens.get(parser.position) {
+            Some(token) => Err(QueryError::UnmatchedParenthesis { column: token.column }),
+            None => Ok(query),
+        }
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        match self {
+            Query::All => true,
+            Query::Text(text) => {
+                candidate.name.contains(text.as_str())
+                    || candidate.note.contains(text.as_str())
+                    || candidate.tags.iter().any(|tag| tag.contains(text.as_str()))
+            }
+            Query::Tag(pattern) => candidate
+                .tags
+                .iter()
+                .any(|tag| tags::self_and_ancestors(tag).any(|tag| glob_match(pattern, tag))),
+            Query::TagBranch(branch) => candidate
+                .tags
+                .iter()
+                .any(|tag| tags::self_and_ancestors(tag).any(|tag| tag == branch)),
+            Query::Note(text) => candidate.note.contains(text.as_str()),
+            Query::Name(text) => candidate.name.contains(text.as_str()),
+            Query::Before(day) => candidate.date.is_some_and(|date| date < *day),
+            Query::After(day) => candidate.date.is_some_and(|date| date >= *day),
+            Query::Rating(min, max) => (*min..=*max).contains(&candidate.rating),
+            Query::Favorite => candidate.favorite,
+            Query::Flag(flag) => candidate.flag == *flag,
+            Query::Not(query) => !query.matches(candidate),
+            Query::And(queries) => queries.iter().all(|query| query.matches(candidate)),
+            Query::Or(queries) => queries.iter().any(|query| query.matches(candidate)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    Not,
+    And,
+    Or,
+    Word(String),
+    Field(String, String),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// 1-based column of the first character, for error messages.
+    column: usize,
+    text: String,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let kind = match c {
+            '(' => {
+                i += 1;
+                TokenKind::Open
+            }
+            ')' => {
+                i += 1;
+                TokenKind::Close
+            }
+            // "-" yalnızca bir terimin başındaysa dışlama anlamına gelir
+            '-' if chars.get(i + 1).is_some_and(|next| !next.is_whitespace()) => {
+                i += 1;
+                TokenKind::Not
+            }
+            '"' => {
+                let (value, end) = read_quoted(&chars, i)?;
+                i = end;
+                TokenKind::Word(value)
+            }
+            _ => {
+                let start = i;
+                while i < chars.len() && !is_boundary(chars[i]) && chars[i] != ':' {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let is_field = chars.get(i) == Some(&':') && !word.is_empty() && word.chars().all(char::is_alphabetic);
+                if is_field {
+                    i += 1;
+                    let value = if chars.get(i) == Some(&'"') {
+                        let (value, end) = read_quoted(&chars, i)?;
+                        i = end;
+                        value
+                    } else {
+                        let value_start = i;
+                        while i < chars.len() && !is_boundary(chars[i]) {
+                            i += 1;
+                        }
+                        chars[value_start..i].iter().collect()
+                    };
+                    TokenKind::Field(word.to_lowercase(), value)
+                } else {
+                    // "10:30" gibi alan olmayan iki noktalar kelimeye dahildir
+                    while i < chars.len() && !is_boundary(chars[i]) {
+                        i += 1;
+                    }
+                    let word: String = chars[start..i].iter().collect();
+                    match word.as_str() {
+                        "AND" => TokenKind::And,
+                        "OR" => TokenKind::Or,
+                        "NOT" => TokenKind::Not,
+                        _ => TokenKind::Word(word),
+                    }
+                }
+            }
+        };
+        let token_text = chars[column - 1..i].iter().collect();
+        tokens.push(Token { kind, column, text: token_text });
+    }
+    Ok(tokens)
+}
+
+fn is_boundary(c: char) -> bool {
+    c.is_whitespace() || c == '(' || c == ')' || c == '"'
+}
+
+/// Reads a quoted value starting at the quote at `start`, returning it and
+/// the index after the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let end = chars[start + 1..]
+        .iter()
+        .position(|&c| c == '"')
+        .map(|offset| start + 1 + offset)
+        .ok_or(QueryError::UnterminatedQuote { column: start + 1 })?;
+    Ok((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut alternatives = vec![self.parse_and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+            alternatives.push(self.parse_and()?);
+        }
+        Ok(if alternatives.len() == 1 { alternatives.remove(0) } else { Query::Or(alternatives) })
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(TokenKind::And) => {
+                    self.position += 1;
+                    terms.push(self.parse_unary()?);
+                }
+                Some(TokenKind::Or | TokenKind::Close) | None => break,
+                // Yan yana terimler "AND" ile bağlanmış sayılır
+                Some(_) => terms.push(self.parse_unary()?),
+            }
+        }
+        Ok(if terms.len() == 1 { terms.remove(0) } else { Query::And(terms) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, QueryError> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.position += 1;
+            return Ok(Query::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Query, QueryError> {
+        let previous = self.position.checked_sub(1).map(|index| self.tokens[index].text.clone());
+        let Some(token) = self.next() else {
+            return Err(QueryError::UnexpectedEnd { after: previous.unwrap_or_default() });
+        };
+        match token.kind {
+            TokenKind::Open => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::Close, .. }) => Ok(query),
+                    _ => Err(QueryError::UnclosedParenthesis { column: token.column }),
+                }
+            }
+            TokenKind::Close => Err(QueryError::UnmatchedParenthesis { column: token.column }),
+            TokenKind::And | TokenKind::Or => Err(QueryError::MisplacedOperator {
+                operator: token.text,
+                column: token.column,
+            }),
+            TokenKind::Not => unreachable!("handled by parse_unary"),
+            TokenKind::Word(word) => Ok(Query::Text(normalize(&word))),
+            TokenKind::Field(field, value) => field_query(&field, &value, token.column),
+        }
+    }
+}
+
+fn field_query(field: &str, value: &str, column: usize) -> Result<Query, QueryError> {
+    let value = value.trim();
+    let known = matches!(field, "tag" | "note" | "name" | "before" | "after" | "rating" | "is");
+    if !known {
+        return Err(QueryError::UnknownField { field: field.to_string(), column });
+    }
+    if value.is_empty() {
+        return Err(QueryError::EmptyValue { field: field.to_string(), column });
+    }
+    let date_range = || {
+        parse_date_range(value).ok_or_else(|| QueryError::InvalidDate { value: value.to_string(), column })
+    };
+    Ok(match field {
+        "tag" => Query::Tag(normalize(value)),
+        "note" => Query::Note(normalize(value)),
+        "name" => Query::Name(normalize(value)),
+        "before" => Query::Before(date_range()?.0),
+        "after" => Query::After(date_range()?.1),
+        "rating" => {
+            let (min, max) = parse_rating_range(value)
+                .ok_or_else(|| QueryError::InvalidRating { value: value.to_string(), column })?;
+            Query::Rating(min, max)
+        }
+        _ => match normalize(value).as_str() {
+            "favorite" => Query::Favorite,
+            "pick" => Query::Flag(Flag::Pick),
+            "reject" => Query::Flag(Flag::Reject),
+            "unflagged" => Query::Flag(Flag::Unflagged),
+            _ => return Err(QueryError::UnknownState { value: value.to_string(), column }),
+        },
+    })
+}
+
+/// The lowest and highest number of stars a `rating:` value allows.
+fn parse_rating_range(value: &str) -> Option<(u8, u8)> {
+    let (operator, stars) = match value.find(|c: char| c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => return None,
+    };
+    let stars: u8 = stars.parse().ok().filter(|stars| *stars <= 5)?;
+    match operator {
+        "" | "=" => Some((stars, stars)),
+        ">=" => Some((stars, 5)),
+        ">" if stars < 5 => Some((stars + 1, 5)),
+        "<=" => Some((0, stars)),
+        "<" if stars > 0 => Some((0, stars - 1)),
+        _ => None,
+    }
+}
+
+/// The first day of a year, month or day and the first day after it.
+fn parse_date_range(value: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let number = |index: usize| parts.get(index).and_then(|part| part.parse::<u32>().ok());
+    let year = parts.first().filter(|year| year.len() == 4)?.parse::<i32>().ok()?;
+    match parts.len() {
+        1 => Some((NaiveDate::from_ymd_opt(year, 1, 1)?, NaiveDate::from_ymd_opt(year + 1, 1, 1)?)),
+        2 => {
+            let start = NaiveDate::from_ymd_opt(year, number(1)?, 1)?;
+            let end = if start.month() == 12 {
+                NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+            } else {
+                NaiveDate::from_ymd_opt(year, start.month() + 1, 1)?
+            };
+            Some((start, end))
+        }
+        3 => {
+            let day = NaiveDate::from_ymd_opt(year, number(1)?, number(2)?)?;
+            Some((day, day.succ_opt()?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn candidate<'a>(tags: &'a [String], note: &'a str, date: Option<NaiveDate>) -> Candidate<'a> {
+        Candidate {
+            name: "img_0042.jpg",
+            note,
+            tags,
+            date,
+            rating: 0,
+            favorite: false,
+            flag: Flag::Unflagged,
+        }
+    }
+
+    #[test]
+    fn parses_the_full_syntax() {
+        let query = Query::parse(
+            "tag:client-a AND (tag:final OR tag:approved) -tag:rejected note:\"Invoice No\" before:2025-01-01",
+        )
+        .unwrap();
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Tag("client-a".into()),
+                Query::Or(vec![Query::Tag("final".into()), Query::Tag("approved".into())]),
+                Query::Not(Box::new(Query::Tag("rejected".into()))),
+                Query::Note("invoice no".into()),
+                Query::Before(date("2025-01-01")),
+            ])
+        );
+        assert_eq!(Query::parse("  ").unwrap(), Query::All);
+        assert_eq!(Query::parse("10:30 Işık").unwrap(), Query::And(vec![
+            Query::Text("10:30".into()),
+            Query::Text("isik".into()),
+        ]));
+        assert_eq!(Query::parse("after:2024-02").unwrap(), Query::After(date("2024-03-01")));
+        assert_eq!(Query::parse("NOT name:x OR \"a b\"").unwrap(), Query::Or(vec![
+            Query::Not(Box::new(Query::Name("x".into()))),
+            Query::Text("a b".into()),
+        ]));
+    }
+
+    #[test]
+    fn reports_where_the_query_is_wrong() {
+        assert_eq!(Query::parse("tag:a OR").unwrap_err(), QueryError::UnexpectedEnd { after: "OR".into() });
+        assert_eq!(Query::parse("(tag:a").unwrap_err(), QueryError::UnclosedParenthesis { column: 1 });
+        assert_eq!(Query::parse("tag:a)").unwrap_err(), QueryError::UnmatchedParenthesis { column: 6 });
+        assert_eq!(Query::parse("note:\"abc").unwrap_err(), QueryError::UnterminatedQuote { column: 6 });
+        assert_eq!(
+            Query::parse("OR tag:a").unwrap_err(),
+            QueryError::MisplacedOperator { operator: "OR".into(), column: 1 }
+        );
+        assert_eq!(
+            Query::parse("x colour:red").unwrap_err(),
+            QueryError::UnknownField { field: "colour".into(), column: 3 }
+        );
+        assert_eq!(Query::parse("tag:").unwrap_err(), QueryError::EmptyValue { field: "tag".into(), column: 1 });
+        assert_eq!(
+            Query::parse("before:2025-13").unwrap_err(),
+            QueryError::InvalidDate { value: "2025-13".into(), column: 1 }
+        );
+        assert!(Query::parse("(tag:a").unwrap_err().to_string().contains("column 1"));
+    }
+
+    #[test]
+    fn matches_candidates() {
+        let tags = vec!["client-a".to_string(), "final".to_string()];
+        let photo = candidate(&tags, "invoice for march", Some(date("2024-12-31")));
+        let matches = |text: &str| Query::parse(text).unwrap().matches(&photo);
+
+        assert!(matches(""));
+        assert!(matches("tag:client-a AND (tag:final OR tag:approved) -tag:rejected note:invoice before:2025"));
+        assert!(matches("tag:client-* 0042"));
+        assert!(!matches("tag:client"));
+        assert!(!matches("tag:final -tag:client-a"));
+        assert!(matches("after:2024-12-30 before:2025-01-01"));
+        assert!(!matches("after:2024-12-31"));
+        assert!(!matches("after:2025"));
+        assert!(matches("march OR nothing"));
+
+        let undated = candidate(&tags, "", None);
+        assert!(!Query::parse("before:2030").unwrap().matches(&undated));
+        assert!(Query::parse("-before:2030").unwrap().matches(&undated));
+    }
+
+    #[test]
+    fn filters_by_rating_and_flags() {
+        assert_eq!(Query::parse("rating:>=4").unwrap(), Query::Rating(4, 5));
+        assert_eq!(Query::parse("rating:<2").unwrap(), Query::Rating(0, 1));
+        assert_eq!(Query::parse("rating:3").unwrap(), Query::Rating(3, 3));
+        assert_eq!(Query::parse("is:Pick").unwrap(), Query::Flag(Flag::Pick));
+        assert_eq!(
+            Query::parse("rating:>5").unwrap_err(),
+            QueryError::InvalidRating { value: ">5".into(), column: 1 }
+        );
+        assert_eq!(
+            Query::parse("is:blurry").unwrap_err(),
+            QueryError::UnknownState { value: "blurry".into(), column: 1 }
+        );
+
+        let tags = Vec::new();
+        let photo = Candidate { rating: 4, favorite: true, ..candidate(&tags, "", None) };
+        assert!(Query::parse("rating:>=3 is:favorite -is:reject").unwrap().matches(&photo));
+        assert!(!Query::parse("rating:5 OR is:pick").unwrap().matches(&photo));
+    }
+
+    #[test]
+    fn parent_tags_match_their_descendants() {
+        let tags = vec!["places/turkey/istanbul".to_string(), "travel".to_string()];
+        let photo = candidate(&tags, "", None);
+        let matches = |text: &str| Query::parse(text).unwrap().matches(&photo);
+        assert!(matches("tag:places"));
+        assert!(matches("tag:places/turkey"));
+        assert!(matches("tag:places/*"));
+        assert!(matches("tag:**/istanbul"));
+        assert!(!matches("tag:*/istanbul"));
+        assert!(matches("tag:travel"));
+        assert!(!matches("tag:places/greece"));
+        assert!(!matches("tag:turkey"));
+        assert!(!matches("tag:place"));
+
+        let branch = |tag: &str| Query::TagBranch(tag.into()).matches(&photo);
+        assert!(branch("places"));
+        assert!(branch("places/turkey/istanbul"));
+        assert!(!branch("places/*"));
+        assert!(!branch("place"));
+    }
+
+    #[test]
+    fn tag_patterns_with_many_stars() {
+        let tags = vec![format!("{}/{}", "a".repeat(40), "a".repeat(40))];
+        let photo = candidate(&tags, "", None);
+        assert!(!Query::parse(&format!("tag:{}b", "*a".repeat(20))).unwrap().matches(&photo));
+        assert!(Query::parse(&format!("tag:{}", "*a".repeat(20))).unwrap().matches(&photo));
+        assert!(!Query::parse("tag:**/**/**/**/**/**/**/b").unwrap().matches(&photo));
+        assert!(Query::parse("tag:**/**/**/**/**/**/**/a*").unwrap().matches(&photo));
+    }
+}
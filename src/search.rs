@@ -0,0 +1,135 @@
+// search.rs
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::path::Path;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use crate::photo_manager::{Flag, PhotoNote};
+use crate::query::{Candidate, Query};
+
+/// Folds text for searching: lower case and without diacritics, so that
+/// "Işık", "ISIK" and "isik" all match. Both Turkish i's become a plain
+/// "i", whatever case they were typed in.
+pub fn normalize(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(|c| match c {
+            // İ ayrıştırılınca I olur, ı ise ayrıştırılamaz
+            'ı' => 'i'.to_lowercase(),
+            c => c.to_lowercase(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct IndexEntry {
+    note: String,
+    tags: Vec<String>,
+    rating: u8,
+    favorite: bool,
+    flag: Flag,
+}
+
+/// Normalized note text, tags, ratings and flags by photo path, kept up to date by the
+/// photo manager whenever a note changes.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    entries: HashMap<String, IndexEntry>,
+}
+
+impl SearchIndex {
+    /// Re-indexes the note of `path`; returns whether search results may
+    /// change because of it.
+    pub fn update(&mut self, path: &str, note: Option<&PhotoNote>) -> bool {
+        let searchable = |note: &&PhotoNote| {
+            !note.note.is_empty() || !note.tags.is_empty() || note.rating > 0 || note.favorite || note.flag != Flag::Unflagged
+        };
+        match note.filter(searchable) {
+            Some(note) => {
+                let entry = IndexEntry {
+                    note: normalize(&note.note),
+                    tags: note.tags.iter().map(|tag| normalize(tag)).collect(),
+                    rating: note.rating,
+                    favorite: note.favorite,
+                    flag: note.flag,
+                };
+                self.entries.insert(path.to_string(), entry.clone()) != Some(entry)
+            }
+            None => self.entries.remove(path).is_some(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Matches the photo at `path`, taken on `date`, against `query`.
+    pub fn matches(&self, path: &str, date: Option<NaiveDate>, query: &Query) -> bool {
+        if *query == Query::All {
+            return true;
+        }
+        let name = Path::new(path)
+            .file_name()
+            .map(|name| normalize(&name.to_string_lossy()))
+            .unwrap_or_default();
+        let entry = self.entries.get(path);
+        query.matches(&Candidate {
+            name: &name,
+            note: entry.map_or("", |entry| entry.note.as_str()),
+            tags: entry.map_or(&[], |entry| entry.tags.as_slice()),
+            date,
+            rating: entry.map_or(0, |entry| entry.rating),
+            favorite: entry.is_some_and(|entry| entry.favorite),
+            flag: entry.map_or(Flag::Unflagged, |entry| entry.flag),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orientation::Orientation;
+
+    fn note(path: &str, text: &str, tags: &[&str]) -> PhotoNote {
+        PhotoNote {
+            path: path.to_string(),
+            note: text.to_string(),
+            timestamp: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            content_hash: None,
+            orientation: Orientation::NORMAL,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn normalize_ignores_case_and_diacritics() {
+        assert_eq!(normalize("Işık"), "isik");
+        assert_eq!(normalize("İSTANBUL"), "istanbul");
+        assert_eq!(normalize("Çağlayan Şelalesi"), "caglayan selalesi");
+        assert_eq!(normalize("Crème Brûlée"), "creme brulee");
+        assert_eq!(normalize("ırmak"), normalize("IRMAK"));
+    }
+
+    #[test]
+    fn all_terms_must_match_somewhere() {
+        let mut index = SearchIndex::default();
+        index.update("/photos/IMG_0042.jpg", Some(&note("/photos/IMG_0042.jpg", "Kız Kulesi'nde gün batımı", &["İstanbul"])));
+
+        let photo = "/photos/IMG_0042.jpg";
+        let matches = |index: &SearchIndex, text: &str| index.matches(photo, None, &Query::parse(text).unwrap());
+        assert!(matches(&index, ""));
+        assert!(matches(&index, "kiz kulesi"));
+        assert!(matches(&index, "ISTANBUL img_0042"));
+        assert!(matches(&index, "batimi"));
+        assert!(matches(&index, "tag:istanbul"));
+        assert!(!matches(&index, "istanbul ankara"));
+        // Klasör adları aranmaz
+        assert!(!matches(&index, "photos"));
+
+        assert!(!index.update(photo, Some(&note(photo, "Kız Kulesi'nde gün batımı", &["İstanbul"]))));
+        assert!(index.update(photo, None));
+        assert!(!matches(&index, "istanbul"));
+        assert!(matches(&index, "0042"));
+    }
+}
@@ -1,10 +1,20 @@
-use gtk::prelude::*;
+use adw::prelude::*;
 use gtk::subclass::prelude::*;
-use gtk::{glib, CompositeTemplate};
-use std::cell::RefCell;
+use gtk::{gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
 use std::rc::Rc;
 use gettextrs::gettext;
+use crate::formats;
+use crate::metadata::PhotoMetadata;
+use crate::orientation::Orientation;
 use crate::photo_manager::PhotoManager;
+use crate::storage::StorageError;
+use crate::tags;
+use crate::NotiaWindow;
+
+/// Number of `tag-color-*` classes in style.css.
+pub const TAG_COLOR_COUNT: usize = 20;
 
 pub struct SidebarData {
     pub photo_path: Option<String>,
@@ -12,6 +22,8 @@ pub struct SidebarData {
     pub note_text: Option<String>,
     pub note_status: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// The user's rotate/flip override for the preview.
+    pub orientation: Orientation,
 }
 
 mod imp {
@@ -25,6 +37,24 @@ mod imp {
         #[template_child]
         pub photo_label: TemplateChild<gtk::Label>,
         #[template_child]
+        pub album_menu_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub details_row: TemplateChild<adw::ExpanderRow>,
+        #[template_child]
+        pub taken_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub camera_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub lens_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub exposure_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub dimensions_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub file_size_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub format_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
         pub note_label: TemplateChild<gtk::Label>,
         #[template_child]
         pub note_text_view: TemplateChild<gtk::TextView>,
@@ -41,6 +71,10 @@ mod imp {
         
         pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
         pub current_photo_path: RefCell<Option<String>>,
+        /// Counts preview requests so that a slow decode does not replace
+        /// the preview of a photo chosen after it.
+        pub preview_generation: Cell<u64>,
+        pub preview_path: RefCell<Option<PathBuf>>,
     }
 
     #[glib::object_subclass]
@@ -86,8 +120,7 @@ impl Sidebar {
         }
         // Fotoğraf önizlemesi için photo_path kullanılabilir
         if let Some(photo_path) = data.photo_path {
-            let file = gio::File::for_path(photo_path);
-            imp.selected_photo_preview.set_file(Some(&file));
+            self.set_preview(&photo_path, data.orientation);
         }
         
         // Etiketleri güncelle
@@ -104,6 +137,44 @@ impl Sidebar {
         }
     }
 
+    /// Redraws the preview, e.g. after the photo was rotated. Leaves the
+    /// note being edited alone. The photo is decoded in the background; a
+    /// rotated photo stays visible until then, another one does not.
+    pub fn set_preview(&self, photo_path: &str, orientation: Orientation) {
+        let imp = self.imp();
+        let generation = imp.preview_generation.get() + 1;
+        imp.preview_generation.set(generation);
+        let photo_path = PathBuf::from(photo_path);
+        if imp.preview_path.replace(Some(photo_path.clone())).as_ref() != Some(&photo_path) {
+            formats::show_loading(&imp.selected_photo_preview);
+        }
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = sidebar)]
+            self,
+            async move {
+                let texture = formats::load_texture(&photo_path, orientation).await;
+                let imp = sidebar.imp();
+                if imp.preview_generation.get() != generation {
+                    return;
+                }
+                let picture = &imp.selected_photo_preview;
+                match texture {
+                    Some(texture) => {
+                        picture.set_paintable(Some(&texture));
+                        picture.set_tooltip_text(None);
+                    }
+                    None => formats::show_placeholder(picture),
+                }
+            }
+        ));
+    }
+
+    /// Menu of the "Add to Album" button, kept up to date by the window.
+    pub fn set_album_menu(&self, menu: &gio::Menu) {
+        self.imp().album_menu_button.set_menu_model(Some(menu));
+    }
+
     pub fn get_note_text(&self) -> String {
         let imp = self.imp();
         let buffer = imp.note_text_view.buffer();
@@ -137,9 +208,10 @@ impl Sidebar {
         let add_btn = imp.add_tag_button.clone();
         let sidebar = self.clone();
         add_btn.connect_clicked(move |_| {
-            let text = entry.text().to_string();
-            if !text.trim().is_empty() {
-                sidebar.add_tag_chip(text.trim());
+            // "yerler / türkiye" gibi seviyeler boşluksuz saklanır
+            let tag = tags::normalize_tag(&entry.text());
+            if !tag.is_empty() {
+                sidebar.add_tag_chip(&tag);
                 entry.set_text("");
             }
         });
@@ -150,6 +222,33 @@ impl Sidebar {
         *imp.photo_manager.borrow_mut() = Some(manager);
     }
 
+    /// Fills the Details section; `None` empties it, e.g. while the
+    /// metadata is still being read.
+    pub fn set_metadata(&self, metadata: Option<&PhotoMetadata>) {
+        let imp = self.imp();
+        let taken_at = metadata
+            .and_then(|metadata| metadata.taken_at)
+            .map(|taken_at| taken_at.format("%Y-%m-%d %H:%M").to_string());
+        Self::set_detail(&imp.taken_row, taken_at);
+        Self::set_detail(&imp.camera_row, metadata.and_then(|metadata| metadata.camera.clone()));
+        Self::set_detail(&imp.lens_row, metadata.and_then(|metadata| metadata.lens.clone()));
+        Self::set_detail(&imp.exposure_row, metadata.and_then(|metadata| metadata.exposure_summary()));
+        Self::set_detail(&imp.dimensions_row, metadata.and_then(|metadata| metadata.dimensions()));
+        Self::set_detail(
+            &imp.file_size_row,
+            metadata.map(|metadata| glib::format_size(metadata.file_size).to_string()),
+        );
+        Self::set_detail(&imp.format_row, metadata.and_then(|metadata| metadata.format.map(str::to_string)));
+
+        imp.details_row.set_sensitive(metadata.is_some());
+        imp.details_row.set_subtitle(&metadata.and_then(|metadata| metadata.camera.clone()).unwrap_or_default());
+    }
+
+    fn set_detail(row: &adw::ActionRow, value: Option<String>) {
+        row.set_visible(value.is_some());
+        row.set_subtitle(&value.unwrap_or_default());
+    }
+
     pub fn set_current_photo_path(&self, photo_path: String) {
         let imp = self.imp();
         *imp.current_photo_path.borrow_mut() = Some(photo_path);
@@ -183,7 +282,11 @@ impl Sidebar {
         label.add_css_class("tag-label");
         
         // Tag rengini CSS class olarak ekle
-        let color_class = format!("tag-color-{}", Self::get_tag_color_index(tag));
+        let color_index = match imp.photo_manager.borrow().as_ref() {
+            Some(manager) => Self::tag_color_index(&manager.borrow(), tag),
+            None => Self::get_tag_color_index(tag),
+        };
+        let color_class = format!("tag-color-{}", color_index);
         chip.add_css_class(&color_class);
         label.set_margin_top(2);
         label.set_margin_bottom(2);
@@ -214,29 +317,48 @@ impl Sidebar {
 
     fn add_tag_to_manager(&self, tag: &str) {
         let imp = self.imp();
+        let mut result = Ok(());
         if let Some(manager) = imp.photo_manager.borrow().as_ref() {
             if let Some(photo_path) = imp.current_photo_path.borrow().as_ref() {
                 let mut manager = manager.borrow_mut();
-                manager.add_tag(photo_path, tag.to_string());
+                result = manager.add_tag(photo_path, tag.to_string());
             }
         }
+        self.report_storage_result(result);
     }
 
     fn remove_tag_from_manager(&self, tag: &str) {
         let imp = self.imp();
+        let mut result = Ok(());
         if let Some(manager) = imp.photo_manager.borrow().as_ref() {
             if let Some(photo_path) = imp.current_photo_path.borrow().as_ref() {
                 let mut manager = manager.borrow_mut();
-                manager.remove_tag(photo_path, tag);
+                result = manager.remove_tag(photo_path, tag);
             }
         }
+        self.report_storage_result(result);
+    }
+
+    fn report_storage_result(&self, result: Result<(), StorageError>) {
+        let window = self.root().and_downcast::<NotiaWindow>();
+        match (result, window) {
+            (Ok(()), Some(window)) => window.notes_changed(),
+            (Ok(()), None) => {}
+            (Err(err), Some(window)) => window.show_storage_error(&err),
+            (Err(err), None) => NotiaWindow::report_storage_error(self, &err),
+        }
+    }
+
+    /// The palette color of `tag`: the one chosen in the tag manager, or
+    /// one derived from its name.
+    pub fn tag_color_index(manager: &PhotoManager, tag: &str) -> usize {
+        manager.tag_color(tag).unwrap_or_else(|| Self::get_tag_color_index(tag))
     }
 
     fn get_tag_color_index(tag: &str) -> usize {
         // Basit hash-based renk seçimi
-        let colors_count = 20;
         let hash = tag.chars().map(|c| c as u32).sum::<u32>();
-        (hash % colors_count as u32) as usize
+        (hash % TAG_COLOR_COUNT as u32) as usize
     }
 
     fn get_tag_color(tag: &str) -> String {
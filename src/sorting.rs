@@ -0,0 +1,206 @@
+// sorting.rs
+use chrono::DateTime;
+use std::cmp::Ordering;
+use std::path::Path;
+use crate::photo_manager::{Flag, PhotoManager};
+
+/// What the gallery is sorted by, stored as the `sort-key` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Capture date from EXIF, or the modification time without one.
+    #[default]
+    DateTaken,
+    Name,
+    Size,
+    /// When the note of the photo was last saved.
+    NoteDate,
+    TagCount,
+    Rating,
+    Favorite,
+    /// Rejected photos, then unflagged ones, then picks.
+    Flag,
+}
+
+impl SortKey {
+    pub fn from_nick(nick: &str) -> SortKey {
+        match nick {
+            "name" => SortKey::Name,
+            "size" => SortKey::Size,
+            "note-date" => SortKey::NoteDate,
+            "tag-count" => SortKey::TagCount,
+            "rating" => SortKey::Rating,
+            "favorite" => SortKey::Favorite,
+            "flag" => SortKey::Flag,
+            _ => SortKey::DateTaken,
+        }
+    }
+
+    /// Whether the order can change when a note, its tags or its marks
+    /// change.
+    pub fn depends_on_notes(self) -> bool {
+        !matches!(self, SortKey::DateTaken | SortKey::Name | SortKey::Size)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    /// Compares two photos by their paths. Photos without a value for the
+    /// key, e.g. without a note, come last in both directions; ties are
+    /// broken by name.
+    pub fn compare(self, manager: &PhotoManager, a: &str, b: &str) -> Ordering {
+        let by_key = match self.key {
+            SortKey::DateTaken => {
+                let date = |path| {
+                    let metadata = manager.cached_metadata(path)?;
+                    metadata.taken_at.or(metadata.modified)
+                };
+                compare_values(date(a), date(b), self.descending)
+            }
+            SortKey::Name => {
+                let ordering = natural_cmp(file_name(a), file_name(b));
+                if self.descending { ordering.reverse() } else { ordering }
+            }
+            SortKey::Size => {
+                let size = |path| manager.cached_metadata(path).map(|metadata| metadata.file_size);
+                compare_values(size(a), size(b), self.descending)
+            }
+            SortKey::NoteDate => {
+                let note_date = |path| {
+                    let note = manager.get_note(path)?;
+                    DateTime::parse_from_rfc3339(&note.timestamp).ok()
+                };
+                compare_values(note_date(a), note_date(b), self.descending)
+            }
+            SortKey::TagCount => {
+                let tag_count = |path| manager.get_note(path).map_or(0, |note| note.tags.len());
+                compare_values(Some(tag_count(a)), Some(tag_count(b)), self.descending)
+            }
+            SortKey::Rating => {
+                let rating = |path| manager.get_note(path).map_or(0, |note| note.rating);
+                compare_values(Some(rating(a)), Some(rating(b)), self.descending)
+            }
+            SortKey::Favorite => {
+                let favorite = |path| manager.get_note(path).is_some_and(|note| note.favorite);
+                compare_values(Some(favorite(a)), Some(favorite(b)), self.descending)
+            }
+            SortKey::Flag => {
+                let flag = |path| manager.get_note(path).map_or(Flag::Unflagged, |note| note.flag);
+                compare_values(Some(flag_rank(flag(a))), Some(flag_rank(flag(b))), self.descending)
+            }
+        };
+        by_key.then_with(|| natural_cmp(a, b))
+    }
+}
+
+/// Orders present values in the requested direction, missing ones last.
+fn compare_values<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn flag_rank(flag: Flag) -> u8 {
+    match flag {
+        Flag::Reject => 0,
+        Flag::Unflagged => 1,
+        Flag::Pick => 2,
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(path)
+}
+
+/// Compares names the way people read them: "IMG_2.jpg" before
+/// "IMG_10.jpg", ignoring case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut left = a.chars().peekable();
+    let mut right = b.chars().peekable();
+    loop {
+        match (left.peek().copied(), right.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut left);
+                let y_digits = take_digits(&mut right);
+                let x_number = x_digits.trim_start_matches('0');
+                let y_number = y_digits.trim_start_matches('0');
+                // Uzun sayı büyüktür; eşit uzunlukta basamaklar karşılaştırılır
+                let ordering = x_number
+                    .len()
+                    .cmp(&y_number.len())
+                    .then_with(|| x_number.cmp(y_number));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                left.next();
+                right.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(digit);
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = vec!["IMG_10.jpg", "img_2.jpg", "IMG_1.jpg", "IMG_002b.jpg", "a.jpg", "IMG_.jpg"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, vec!["a.jpg", "IMG_.jpg", "IMG_1.jpg", "img_2.jpg", "IMG_002b.jpg", "IMG_10.jpg"]);
+        assert_eq!(natural_cmp("photo 007", "photo 7"), "photo 007".cmp("photo 7"));
+        assert_ne!(natural_cmp("A.jpg", "a.jpg"), Ordering::Equal);
+    }
+
+    #[test]
+    fn missing_values_sort_last_in_both_directions() {
+        assert_eq!(compare_values(Some(1), Some(2), false), Ordering::Less);
+        assert_eq!(compare_values(Some(1), Some(2), true), Ordering::Greater);
+        assert_eq!(compare_values(Some(1), None, false), Ordering::Less);
+        assert_eq!(compare_values(Some(1), None, true), Ordering::Less);
+        assert_eq!(compare_values::<u8>(None, None, true), Ordering::Equal);
+    }
+
+    #[test]
+    fn picks_rank_above_unflagged_and_rejected_photos() {
+        let mut flags = vec![Flag::Pick, Flag::Reject, Flag::Unflagged];
+        flags.sort_by_key(|flag| flag_rank(*flag));
+        assert_eq!(flags, vec![Flag::Reject, Flag::Unflagged, Flag::Pick]);
+    }
+
+    #[test]
+    fn sort_keys_are_read_from_their_nicks() {
+        assert_eq!(SortKey::from_nick("name"), SortKey::Name);
+        assert_eq!(SortKey::from_nick("tag-count"), SortKey::TagCount);
+        assert_eq!(SortKey::from_nick("flag"), SortKey::Flag);
+        assert_eq!(SortKey::from_nick("unknown"), SortKey::DateTaken);
+    }
+}
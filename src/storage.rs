@@ -0,0 +1,873 @@
+// storage.rs
+use rusqlite::backup::Progress;
+use rusqlite::{params, Connection, DatabaseName, ErrorCode, OpenFlags, OptionalExtension};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crate::orientation::Orientation;
+use crate::photo_manager::{Album, Flag, PhotoNote, Relinked, SmartAlbum};
+
+/// Database schema migrations; entry `i` upgrades `user_version` `i` to `i + 1`.
+const SCHEMA_MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE IF NOT EXISTS photos (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS notes (
+        photo_id INTEGER PRIMARY KEY REFERENCES photos(id) ON DELETE CASCADE,
+        note TEXT NOT NULL,
+        timestamp TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tags (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE
+    );
+    CREATE TABLE IF NOT EXISTS photo_tags (
+        photo_id INTEGER NOT NULL REFERENCES photos(id) ON DELETE CASCADE,
+        tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (photo_id, tag_id)
+    );
+    CREATE INDEX IF NOT EXISTS idx_photo_tags_tag ON photo_tags(tag_id);
+    CREATE INDEX IF NOT EXISTS idx_notes_timestamp ON notes(timestamp);
+    ",
+    "
+    ALTER TABLE photos ADD COLUMN content_hash TEXT;
+    CREATE INDEX IF NOT EXISTS idx_photos_content_hash ON photos(content_hash);
+    ",
+    "
+    ALTER TABLE notes ADD COLUMN orientation INTEGER NOT NULL DEFAULT 1;
+    ",
+    "
+    CREATE TABLE IF NOT EXISTS smart_albums (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        query TEXT NOT NULL,
+        position INTEGER NOT NULL
+    );
+    ",
+    "
+    CREATE TABLE IF NOT EXISTS albums (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT NOT NULL DEFAULT '',
+        cover TEXT,
+        position INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS album_photos (
+        album_id INTEGER NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+        path TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (album_id, path)
+    );
+    CREATE INDEX IF NOT EXISTS idx_album_photos_path ON album_photos(path);
+    ",
+    "
+    ALTER TABLE notes ADD COLUMN rating INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE notes ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE notes ADD COLUMN flag INTEGER NOT NULL DEFAULT 0;
+    ",
+    "
+    ALTER TABLE tags ADD COLUMN color INTEGER;
+    ",
+];
+
+const BACKUP_SUFFIX: &str = ".bak";
+const CORRUPT_SUFFIX: &str = ".corrupt";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S-%6f";
+/// Backups taken before sub-second names were introduced.
+const LEGACY_BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io(io::Error),
+    Database(rusqlite::Error),
+    Parse(serde_json::Error),
+    /// `PRAGMA quick_check` reported a problem with the database file.
+    Integrity(String),
+    /// Serialized notes do not have the expected layout.
+    Format(String),
+    /// The data was written by a newer version of Notia.
+    UnsupportedVersion(u64),
+    /// A file that could not be read was moved aside instead of being overwritten.
+    Corrupt { path: PathBuf, moved_to: PathBuf },
+}
+
+impl StorageError {
+    /// Whether the error means the file itself is unreadable, as opposed to
+    /// a transient failure such as a permission problem or a full disk.
+    pub fn is_corruption(&self) -> bool {
+        match self {
+            StorageError::Database(rusqlite::Error::SqliteFailure(err, _)) => matches!(
+                err.code,
+                ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt
+            ),
+            StorageError::Parse(_) | StorageError::Integrity(_) | StorageError::Format(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io(err) => write!(f, "{}", err),
+            StorageError::Database(err) => write!(f, "{}", err),
+            StorageError::Parse(err) => write!(f, "{}", err),
+            StorageError::Integrity(report) => write!(f, "integrity check failed: {}", report),
+            StorageError::Format(reason) => write!(f, "unexpected notes format: {}", reason),
+            StorageError::UnsupportedVersion(version) => write!(
+                f,
+                "notes were saved by a newer version of Notia (format {})",
+                version
+            ),
+            StorageError::Corrupt { path, moved_to } => write!(
+                f,
+                "{} could not be read and was moved to {}",
+                path.display(),
+                moved_to.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StorageError::Io(err) => Some(err),
+            StorageError::Database(err) => Some(err),
+            StorageError::Parse(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(err: io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(err: rusqlite::Error) -> Self {
+        StorageError::Database(err)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(err: serde_json::Error) -> Self {
+        StorageError::Parse(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+/// SQLite-backed persistence for photo notes and tags.
+#[derive(Debug)]
+pub struct NoteStore {
+    conn: Connection,
+}
+
+impl NoteStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = FULL;",
+        )?;
+        let report: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if report != "ok" {
+            return Err(StorageError::Integrity(report));
+        }
+        migrate(&conn)?;
+        Ok(NoteStore { conn })
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        let count: i64 = self.conn.query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0))?;
+        Ok(count == 0)
+    }
+
+    pub fn load_all(&self) -> Result<Vec<PhotoNote>> {
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = self.conn.prepare(
+            "SELECT pt.photo_id, t.name FROM photo_tags pt
+             JOIN tags t ON t.id = pt.tag_id
+             ORDER BY pt.photo_id, pt.position",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (photo_id, name) = row?;
+            tags.entry(photo_id).or_default().push(name);
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id, p.path, n.note, n.timestamp, p.content_hash, n.orientation, n.rating, n.favorite, n.flag
+             FROM photos p JOIN notes n ON n.photo_id = p.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            Ok(PhotoNote {
+                path: row.get(1)?,
+                note: row.get(2)?,
+                timestamp: row.get(3)?,
+                tags: tags.get(&id).cloned().unwrap_or_default(),
+                content_hash: row.get(4)?,
+                orientation: Orientation::from_exif(row.get(5)?),
+                rating: row.get::<_, u8>(6)?.min(5),
+                favorite: row.get(7)?,
+                flag: Flag::from_db(row.get(8)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn upsert_note(&mut self, note: &PhotoNote) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        write_note(&tx, note)?;
+        Ok(tx.commit()?)
+    }
+
+    /// Writes many notes in a single transaction.
+    pub fn import(&mut self, notes: &[PhotoNote]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for note in notes {
+            write_note(&tx, note)?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Writes `notes` and deletes the notes of `removed` in one transaction.
+    pub fn update_notes(&mut self, notes: &[PhotoNote], removed: &[String]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for note in notes {
+            write_note(&tx, note)?;
+        }
+        for path in removed {
+            tx.execute("DELETE FROM photos WHERE path = ?1", params![path])?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Moves notes to the new paths of their photos in one transaction.
+    pub fn relink(&mut self, moves: &[Relinked]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for moved in moves {
+            tx.execute(
+                "UPDATE photos SET path = ?2 WHERE path = ?1",
+                params![moved.from, moved.to],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Replaces the note stored under `from` with `note`, which may live
+    /// under another path.
+    pub fn move_note(&mut self, from: &str, note: &PhotoNote) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM photos WHERE path = ?1", params![from])?;
+        write_note(&tx, note)?;
+        Ok(tx.commit()?)
+    }
+
+    pub fn delete_note(&mut self, path: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM photos WHERE path = ?1", params![path])?;
+        Ok(())
+    }
+
+    pub fn delete_notes(&mut self, paths: &[String]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for path in paths {
+            tx.execute("DELETE FROM photos WHERE path = ?1", params![path])?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Removes tags by name, together with their colors.
+    pub fn delete_tags(&mut self, names: &[String]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for name in names {
+            tx.execute("DELETE FROM tags WHERE name = ?1", params![name])?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM photos", [])?;
+        // Renkler etiketler yeniden kullanılınca geri gelsin
+        tx.execute("DELETE FROM tags WHERE color IS NULL", [])?;
+        Ok(tx.commit()?)
+    }
+
+    /// Colors chosen for tags, as indices into the tag palette.
+    pub fn load_tag_colors(&self) -> Result<HashMap<String, usize>> {
+        let mut stmt = self.conn.prepare("SELECT name, color FROM tags WHERE color IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Stores the colors of tags; `None` goes back to the default color.
+    pub fn set_tag_colors(&mut self, colors: &[(String, Option<usize>)]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (tag, color) in colors {
+            tx.execute(
+                "INSERT INTO tags (name, color) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET color = excluded.color",
+                params![tag, color.map(|color| color as i64)],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    pub fn load_smart_albums(&self) -> Result<Vec<SmartAlbum>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, query FROM smart_albums ORDER BY position, id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SmartAlbum {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                query: row.get(2)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn insert_smart_album(&mut self, name: &str, query: &str, position: usize) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO smart_albums (name, query, position) VALUES (?1, ?2, ?3)",
+            params![name, query, position as i64],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn update_smart_album(&mut self, album: &SmartAlbum) -> Result<()> {
+        self.conn.execute(
+            "UPDATE smart_albums SET name = ?2, query = ?3 WHERE id = ?1",
+            params![album.id, album.name, album.query],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the order of the smart albums, given as their ids.
+    pub fn reorder_smart_albums(&mut self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE smart_albums SET position = ?2 WHERE id = ?1",
+                params![id, position as i64],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    pub fn delete_smart_album(&mut self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM smart_albums WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn load_albums(&self) -> Result<Vec<Album>> {
+        let mut photos: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = self
+            .conn
+            .prepare("SELECT album_id, path FROM album_photos ORDER BY album_id, position")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (album_id, path) = row?;
+            photos.entry(album_id).or_default().push(path);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, description, cover FROM albums ORDER BY position, id")?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            Ok(Album {
+                id,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                cover: row.get(3)?,
+                photos: photos.remove(&id).unwrap_or_default(),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn insert_album(&mut self, name: &str, description: &str, position: usize) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO albums (name, description, position) VALUES (?1, ?2, ?3)",
+            params![name, description, position as i64],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Writes the details and the ordered photos of an album in one
+    /// transaction.
+    pub fn update_album(&mut self, album: &Album) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE albums SET name = ?2, description = ?3, cover = ?4 WHERE id = ?1",
+            params![album.id, album.name, album.description, album.cover],
+        )?;
+        tx.execute("DELETE FROM album_photos WHERE album_id = ?1", params![album.id])?;
+        for (position, path) in album.photos.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO album_photos (album_id, path, position) VALUES (?1, ?2, ?3)",
+                params![album.id, path, position as i64],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Stores the order of the albums, given as their ids.
+    pub fn reorder_albums(&mut self, ids: &[i64]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for (position, id) in ids.iter().enumerate() {
+            tx.execute(
+                "UPDATE albums SET position = ?2 WHERE id = ?1",
+                params![id, position as i64],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    pub fn delete_album(&mut self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM albums WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Points album entries and covers at the new paths of moved photos.
+    /// An entry is dropped if its album already holds the new path.
+    pub fn relink_album_photos(&mut self, moves: &[Relinked]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for moved in moves {
+            tx.execute(
+                "UPDATE OR IGNORE album_photos SET path = ?2 WHERE path = ?1",
+                params![moved.from, moved.to],
+            )?;
+            tx.execute("DELETE FROM album_photos WHERE path = ?1", params![moved.from])?;
+            tx.execute(
+                "UPDATE albums SET cover = ?2 WHERE cover = ?1",
+                params![moved.from, moved.to],
+            )?;
+        }
+        Ok(tx.commit()?)
+    }
+
+    /// Copies a consistent snapshot of the database to `dest`.
+    ///
+    /// The snapshot is written to a temporary file, fsynced and then renamed
+    /// into place, so `dest` is either complete or absent after a crash.
+    pub fn backup_to(&self, dest: &Path) -> Result<()> {
+        let tmp = dest.with_extension("tmp");
+        let _ = fs::remove_file(&tmp);
+        self.conn.backup(DatabaseName::Main, &tmp, None)?;
+        Ok(persist(&tmp, dest)?)
+    }
+
+    /// Replaces the database contents with those of a backup file and
+    /// brings the restored schema up to date. A backup written by a newer
+    /// version of Notia is refused before anything is replaced.
+    pub fn restore_from(&mut self, src: &Path) -> Result<()> {
+        let backup = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let version: u64 = backup.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        drop(backup);
+        if version > SCHEMA_MIGRATIONS.len() as u64 {
+            return Err(StorageError::UnsupportedVersion(version));
+        }
+        self.conn
+            .restore(DatabaseName::Main, src, None::<fn(Progress)>)?;
+        migrate(&self.conn)
+    }
+}
+
+/// Moves an unreadable file (and any SQLite sidecar files) aside as
+/// `<name>.<timestamp>.corrupt` so that it is never overwritten.
+pub fn quarantine(path: &Path) -> Result<PathBuf> {
+    let stamp = chrono::Local::now().format(BACKUP_TIME_FORMAT);
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("notes");
+    let moved_to = path.with_file_name(format!("{}.{}{}", name, stamp, CORRUPT_SUFFIX));
+    fs::rename(path, &moved_to)?;
+
+    for sidecar in ["-wal", "-shm"] {
+        let from = path.with_file_name(format!("{}{}", name, sidecar));
+        if from.exists() {
+            let to = moved_to.with_file_name(format!(
+                "{}{}",
+                moved_to.file_name().and_then(|n| n.to_str()).unwrap_or_default(),
+                sidecar
+            ));
+            let _ = fs::rename(from, to);
+        }
+    }
+    Ok(moved_to)
+}
+
+/// Brings the schema up to date, one transaction per step.
+fn migrate(conn: &Connection) -> Result<()> {
+    let version: u64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > SCHEMA_MIGRATIONS.len() as u64 {
+        return Err(StorageError::UnsupportedVersion(version));
+    }
+    // Each step runs in its own transaction, which is rolled back when the
+    // step fails so the database stays at the last complete version.
+    for (index, step) in SCHEMA_MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&format!("{} PRAGMA user_version = {};", step, index + 1))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `dest` and atomically
+/// renames it into place.
+pub fn write_atomic(dest: &Path, contents: &[u8]) -> Result<()> {
+    let name = dest.file_name().and_then(|n| n.to_str()).unwrap_or("notia");
+    let tmp = dest.with_file_name(format!(".{}.tmp", name));
+    let mut file = File::create(&tmp)?;
+    file.write_all(contents)?;
+    drop(file);
+    Ok(persist(&tmp, dest)?)
+}
+
+/// Fsyncs `tmp`, renames it over `dest` and fsyncs the containing directory.
+pub fn persist(tmp: &Path, dest: &Path) -> io::Result<()> {
+    File::open(tmp)?.sync_all()?;
+    fs::rename(tmp, dest)?;
+    if let Some(parent) = dest.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+/// Returns the path of a new timestamped backup next to `db_path`. The
+/// time is moved forward by a microsecond until the name is unused, so two
+/// backups in quick succession never overwrite each other.
+pub fn backup_path(db_path: &Path) -> PathBuf {
+    let name = db_path.file_name().and_then(|n| n.to_str()).unwrap_or("notes.db");
+    let mut time = chrono::Local::now().naive_local();
+    loop {
+        let path = db_path.with_file_name(format!("{}.{}{}", name, time.format(BACKUP_TIME_FORMAT), BACKUP_SUFFIX));
+        if !path.exists() {
+            return path;
+        }
+        time += chrono::TimeDelta::microseconds(1);
+    }
+}
+
+/// Lists the backups of `db_path`, newest first.
+pub fn list_backups(db_path: &Path) -> Vec<PathBuf> {
+    let (dir, name) = match (db_path.parent(), db_path.file_name().and_then(|n| n.to_str())) {
+        (Some(dir), Some(name)) => (dir, format!("{}.", name)),
+        _ => return Vec::new(),
+    };
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|n| n.starts_with(&name) && n.ends_with(BACKUP_SUFFIX))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Parses the creation time encoded in a backup file name.
+pub fn backup_time(backup: &Path) -> Option<chrono::NaiveDateTime> {
+    let name = backup.file_name()?.to_str()?.strip_suffix(BACKUP_SUFFIX)?;
+    let stamp = name.rsplit('.').next()?;
+    chrono::NaiveDateTime::parse_from_str(stamp, BACKUP_TIME_FORMAT)
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(stamp, LEGACY_BACKUP_TIME_FORMAT))
+        .ok()
+}
+
+/// Removes all but the `keep` newest backups of `db_path`.
+pub fn prune_backups(db_path: &Path, keep: usize) {
+    for old in list_backups(db_path).into_iter().skip(keep) {
+        let _ = fs::remove_file(old);
+    }
+}
+
+fn write_note(conn: &Connection, note: &PhotoNote) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO photos (path, content_hash) VALUES (?1, ?2)
+         ON CONFLICT(path) DO UPDATE SET content_hash = excluded.content_hash",
+        params![note.path, note.content_hash],
+    )?;
+    let photo_id: i64 = conn.query_row(
+        "SELECT id FROM photos WHERE path = ?1",
+        params![note.path],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO notes (photo_id, note, timestamp, orientation, rating, favorite, flag)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(photo_id) DO UPDATE SET note = excluded.note, timestamp = excluded.timestamp,
+             orientation = excluded.orientation, rating = excluded.rating,
+             favorite = excluded.favorite, flag = excluded.flag",
+        params![
+            photo_id,
+            note.note,
+            note.timestamp,
+            note.orientation.to_exif(),
+            note.rating,
+            note.favorite,
+            note.flag.to_db()
+        ],
+    )?;
+
+    conn.execute("DELETE FROM photo_tags WHERE photo_id = ?1", params![photo_id])?;
+    for (position, tag) in note.tags.iter().enumerate() {
+        let tag_id = tag_id(conn, tag)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO photo_tags (photo_id, tag_id, position) VALUES (?1, ?2, ?3)",
+            params![photo_id, tag_id, position as i64],
+        )?;
+    }
+    Ok(())
+}
+
+fn tag_id(conn: &Connection, name: &str) -> rusqlite::Result<i64> {
+    if let Some(id) = conn
+        .query_row("SELECT id FROM tags WHERE name = ?1", params![name], |row| row.get(0))
+        .optional()?
+    {
+        return Ok(id);
+    }
+    conn.execute("INSERT INTO tags (name) VALUES (?1)", params![name])?;
+    Ok(conn.last_insert_rowid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(conn: &Connection) -> u64 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrate_creates_current_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        assert_eq!(user_version(&conn), SCHEMA_MIGRATIONS.len() as u64);
+        conn.execute("INSERT INTO photos (path) VALUES ('/a.jpg')", []).unwrap();
+    }
+
+    #[test]
+    fn migrate_v0_keeps_unversioned_tables() {
+        // Databases created before schema versioning already have the tables.
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA_MIGRATIONS[0]).unwrap();
+        conn.execute("INSERT INTO photos (path) VALUES ('/a.jpg')", []).unwrap();
+        migrate(&conn).unwrap();
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(user_version(&conn), SCHEMA_MIGRATIONS.len() as u64);
+    }
+
+    #[test]
+    fn migrate_v1_adds_content_hash() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!("{} PRAGMA user_version = 1;", SCHEMA_MIGRATIONS[0])).unwrap();
+        conn.execute("INSERT INTO photos (path) VALUES ('/a.jpg')", []).unwrap();
+        migrate(&conn).unwrap();
+        let hash: Option<String> = conn
+            .query_row("SELECT content_hash FROM photos WHERE path = '/a.jpg'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(hash, None);
+    }
+
+    #[test]
+    fn migrate_v2_adds_orientation() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(&format!(
+            "{} {} PRAGMA user_version = 2;",
+            SCHEMA_MIGRATIONS[0], SCHEMA_MIGRATIONS[1]
+        ))
+        .unwrap();
+        conn.execute("INSERT INTO photos (id, path) VALUES (1, '/a.jpg')", []).unwrap();
+        conn.execute("INSERT INTO notes (photo_id, note, timestamp) VALUES (1, 'x', 't')", []).unwrap();
+        migrate(&conn).unwrap();
+        let orientation: u16 = conn
+            .query_row("SELECT orientation FROM notes WHERE photo_id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(orientation, 1);
+    }
+
+    #[test]
+    fn failed_migration_is_rolled_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        // The last step adds a column that already exists.
+        let previous = SCHEMA_MIGRATIONS.len() - 1;
+        conn.execute_batch(&format!("PRAGMA user_version = {};", previous)).unwrap();
+        assert!(migrate(&conn).is_err());
+        assert!(conn.is_autocommit());
+        assert_eq!(user_version(&conn), previous as u64);
+    }
+
+    #[test]
+    fn backup_names_are_unique_and_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("notes.db");
+        let first = backup_path(&db);
+        File::create(&first).unwrap();
+        let second = backup_path(&db);
+        assert_ne!(first, second);
+        assert!(backup_time(&second).is_some());
+        assert!(backup_time(&dir.path().join("notes.db.20240101-120000.bak")).is_some());
+    }
+
+    #[test]
+    fn ratings_and_flags_round_trip() {
+        let mut store = NoteStore::open_in_memory().unwrap();
+        let note = PhotoNote {
+            path: "/a.jpg".into(),
+            timestamp: "t".into(),
+            rating: 4,
+            favorite: true,
+            flag: Flag::Reject,
+            ..Default::default()
+        };
+        store.update_notes(&[note], &[]).unwrap();
+        let loaded = store.load_all().unwrap();
+        assert_eq!((loaded[0].rating, loaded[0].favorite, loaded[0].flag), (4, true, Flag::Reject));
+
+        store.update_notes(&[], &["/a.jpg".to_string()]).unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tag_colors_survive_clearing_notes() {
+        let mut store = NoteStore::open_in_memory().unwrap();
+        let note = PhotoNote {
+            path: "/a.jpg".into(),
+            timestamp: "t".into(),
+            tags: vec!["travel".into(), "work".into()],
+            ..Default::default()
+        };
+        store.import(&[note]).unwrap();
+        store
+            .set_tag_colors(&[("travel".into(), Some(7)), ("places/turkey".into(), Some(2))])
+            .unwrap();
+        store.clear().unwrap();
+        store.set_tag_colors(&[("places/turkey".into(), None)]).unwrap();
+
+        let colors = store.load_tag_colors().unwrap();
+        assert_eq!(colors, HashMap::from([("travel".to_string(), 7)]));
+    }
+
+    #[test]
+    fn smart_albums_keep_their_order() {
+        let mut store = NoteStore::open_in_memory().unwrap();
+        let first = store.insert_smart_album("Clients", "tag:client-*", 0).unwrap();
+        let second = store.insert_smart_album("2024", "after:2024 before:2025", 1).unwrap();
+        store.reorder_smart_albums(&[second, first]).unwrap();
+        store
+            .update_smart_album(&SmartAlbum { id: first, name: "All clients".into(), query: "tag:client-*".into() })
+            .unwrap();
+
+        let names: Vec<String> = store.load_smart_albums().unwrap().into_iter().map(|album| album.name).collect();
+        assert_eq!(names, vec!["2024", "All clients"]);
+
+        store.delete_smart_album(second).unwrap();
+        assert_eq!(store.load_smart_albums().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn albums_keep_photo_order_across_moves() {
+        let mut store = NoteStore::open_in_memory().unwrap();
+        let id = store.insert_album("Trip", "", 0).unwrap();
+        let mut album = Album {
+            id,
+            name: "Trip".into(),
+            description: "Summer 2024".into(),
+            cover: Some("/b.jpg".into()),
+            photos: vec!["/c.jpg".into(), "/a.jpg".into(), "/b.jpg".into()],
+        };
+        store.update_album(&album).unwrap();
+        store
+            .relink_album_photos(&[
+                Relinked { from: "/b.jpg".into(), to: "/d.jpg".into() },
+                Relinked { from: "/c.jpg".into(), to: "/a.jpg".into() },
+            ])
+            .unwrap();
+
+        album.photos = vec!["/a.jpg".into(), "/d.jpg".into()];
+        album.cover = Some("/d.jpg".into());
+        assert_eq!(store.load_albums().unwrap(), vec![album]);
+
+        store.delete_album(id).unwrap();
+        assert!(store.load_albums().unwrap().is_empty());
+        let entries: i64 = store.conn.query_row("SELECT COUNT(*) FROM album_photos", [], |row| row.get(0)).unwrap();
+        assert_eq!(entries, 0);
+    }
+
+    #[test]
+    fn restoring_an_old_backup_migrates_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("notes.db.bak");
+        let conn = Connection::open(&backup).unwrap();
+        conn.execute_batch(&format!("{} PRAGMA user_version = 1;", SCHEMA_MIGRATIONS[0])).unwrap();
+        conn.execute("INSERT INTO photos (id, path) VALUES (1, '/a.jpg')", []).unwrap();
+        conn.execute("INSERT INTO notes (photo_id, note, timestamp) VALUES (1, 'old', 't')", []).unwrap();
+        drop(conn);
+
+        let mut store = NoteStore::open_in_memory().unwrap();
+        store.restore_from(&backup).unwrap();
+        assert_eq!(user_version(&store.conn), SCHEMA_MIGRATIONS.len() as u64);
+        let notes = store.load_all().unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].note, "old");
+        assert_eq!(notes[0].rating, 0);
+    }
+
+    #[test]
+    fn restoring_a_newer_backup_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let backup = dir.path().join("notes.db.bak");
+        let newer = SCHEMA_MIGRATIONS.len() as u64 + 1;
+        let conn = Connection::open(&backup).unwrap();
+        conn.execute_batch(&format!("PRAGMA user_version = {};", newer)).unwrap();
+        drop(conn);
+
+        let mut store = NoteStore::open_in_memory().unwrap();
+        store.conn.execute("INSERT INTO photos (path) VALUES ('/a.jpg')", []).unwrap();
+        assert!(matches!(store.restore_from(&backup), Err(StorageError::UnsupportedVersion(v)) if v == newer));
+        let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn migrate_rejects_newer_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        let newer = SCHEMA_MIGRATIONS.len() + 1;
+        conn.execute_batch(&format!("PRAGMA user_version = {};", newer)).unwrap();
+        assert!(matches!(migrate(&conn), Err(StorageError::UnsupportedVersion(v)) if v == newer as u64));
+    }
+}
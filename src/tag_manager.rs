@@ -0,0 +1,396 @@
+// tag_manager.rs
+use adw::prelude::*;
+use adw::subclass::prelude::*;
+use gtk::{glib, CompositeTemplate};
+use gettextrs::{gettext, ngettext};
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::photo_manager::PhotoManager;
+use crate::search;
+use crate::sidebar::{Sidebar, TAG_COLOR_COUNT};
+use crate::storage::StorageError;
+use crate::tags;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/tag_manager.ui")]
+    pub struct TagManagerDialog {
+        #[template_child]
+        pub toast_overlay: TemplateChild<adw::ToastOverlay>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub tag_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub action_bar: TemplateChild<gtk::ActionBar>,
+        #[template_child]
+        pub select_all_check: TemplateChild<gtk::CheckButton>,
+        #[template_child]
+        pub delete_button: TemplateChild<gtk::Button>,
+
+        pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
+        /// Listed tags together with the check button selecting each.
+        pub rows: RefCell<Vec<(String, gtk::CheckButton)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TagManagerDialog {
+        const NAME: &'static str = "TagManagerDialog";
+        type Type = super::TagManagerDialog;
+        type ParentType = adw::Dialog;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TagManagerDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_callbacks();
+        }
+    }
+
+    impl WidgetImpl for TagManagerDialog {}
+    impl AdwDialogImpl for TagManagerDialog {}
+}
+
+glib::wrapper! {
+    pub struct TagManagerDialog(ObjectSubclass<imp::TagManagerDialog>)
+        @extends gtk::Widget, adw::Dialog;
+}
+
+impl TagManagerDialog {
+    pub fn new(manager: Rc<RefCell<PhotoManager>>) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        *dialog.imp().photo_manager.borrow_mut() = Some(manager);
+        dialog.reload();
+        dialog
+    }
+
+    fn setup_callbacks(&self) {
+        let imp = self.imp();
+
+        // Yüzlerce etiket arasında arama
+        imp.tag_list.set_filter_func(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            #[upgrade_or]
+            true,
+            move |row| dialog.filter_row(row)
+        ));
+        imp.search_entry.connect_search_changed(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.imp().tag_list.invalidate_filter();
+            }
+        ));
+
+        imp.select_all_check.connect_toggled(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |check| {
+                let active = check.is_active();
+                // Yalnızca aramayla gösterilen etiketler seçilir
+                for (_, row_check) in dialog.imp().rows.borrow().iter() {
+                    if row_check.ancestor(gtk::ListBoxRow::static_type()).is_some_and(|row| row.is_child_visible()) {
+                        row_check.set_active(active);
+                    }
+                }
+            }
+        ));
+
+        imp.delete_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.confirm_delete_selected();
+            }
+        ));
+    }
+
+    fn manager(&self) -> Rc<RefCell<PhotoManager>> {
+        self.imp()
+            .photo_manager
+            .borrow()
+            .clone()
+            .expect("TagManagerDialog needs a photo manager")
+    }
+
+    /// Rebuilds the list from the tags of all photos.
+    fn reload(&self) {
+        let imp = self.imp();
+        imp.tag_list.remove_all();
+        imp.rows.borrow_mut().clear();
+
+        let usage = self.manager().borrow().tag_usage();
+        let empty = usage.is_empty();
+        for (tag, count) in usage {
+            self.append_row(tag, count);
+        }
+
+        imp.stack.set_visible_child_name(if empty { "empty" } else { "list" });
+        imp.action_bar.set_revealed(!empty);
+        imp.select_all_check.set_active(false);
+        self.update_selection();
+    }
+
+    fn append_row(&self, tag: String, count: usize) {
+        let imp = self.imp();
+        let row = adw::ActionRow::builder()
+            .title(tag.as_str())
+            .subtitle(ngettext("{} photo", "{} photos", count as u32).replace("{}", &count.to_string()))
+            .use_markup(false)
+            .build();
+
+        let check = gtk::CheckButton::new();
+        check.set_valign(gtk::Align::Center);
+        check.connect_toggled(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.update_selection();
+            }
+        ));
+        row.add_prefix(&check);
+        row.set_activatable_widget(Some(&check));
+
+        row.add_suffix(&self.color_button(&tag));
+
+        let rename_button = gtk::Button::from_icon_name("document-edit-symbolic");
+        rename_button.set_valign(gtk::Align::Center);
+        rename_button.set_tooltip_text(Some(&gettext("Rename or Merge…")));
+        rename_button.add_css_class("flat");
+        let from = tag.clone();
+        rename_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_| {
+                dialog.ask_new_name(&from);
+            }
+        ));
+        row.add_suffix(&rename_button);
+
+        imp.tag_list.append(&row);
+        imp.rows.borrow_mut().push((tag, check));
+    }
+
+    /// A button showing the color of `tag` that opens the palette.
+    fn color_button(&self, tag: &str) -> gtk::MenuButton {
+        let manager = self.manager();
+        let current = Sidebar::tag_color_index(&manager.borrow(), tag);
+        let swatch = gtk::Box::builder()
+            .css_classes(["tag-swatch", &format!("tag-color-{}", current)])
+            .build();
+
+        let palette = gtk::Grid::builder()
+            .row_spacing(6)
+            .column_spacing(6)
+            .build();
+        let popover = gtk::Popover::new();
+        for index in 0..TAG_COLOR_COUNT {
+            let button = gtk::Button::builder()
+                .css_classes(["tag-swatch", "circular", &format!("tag-color-{}", index)])
+                .build();
+            if index == current {
+                button.set_icon_name("object-select-symbolic");
+            }
+            let tag = tag.to_string();
+            button.connect_clicked(glib::clone!(
+                #[weak(rename_to = dialog)]
+                self,
+                #[weak]
+                popover,
+                move |_| {
+                    popover.popdown();
+                    dialog.set_color(&tag, Some(index));
+                }
+            ));
+            palette.attach(&button, (index % 5) as i32, (index / 5) as i32, 1, 1);
+        }
+        let default_button = gtk::Button::builder()
+            .label(gettext("_Default Color"))
+            .use_underline(true)
+            .sensitive(manager.borrow().tag_color(tag).is_some())
+            .build();
+        default_button.add_css_class("flat");
+        let tag = tag.to_string();
+        default_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            #[weak]
+            popover,
+            move |_| {
+                popover.popdown();
+                dialog.set_color(&tag, None);
+            }
+        ));
+        palette.attach(&default_button, 0, (TAG_COLOR_COUNT / 5) as i32, 5, 1);
+        popover.set_child(Some(&palette));
+
+        gtk::MenuButton::builder()
+            .child(&swatch)
+            .popover(&popover)
+            .tooltip_text(gettext("Color"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build()
+    }
+
+    fn filter_row(&self, row: &gtk::ListBoxRow) -> bool {
+        let text = search::normalize(self.imp().search_entry.text().trim());
+        if text.is_empty() {
+            return true;
+        }
+        row.downcast_ref::<adw::ActionRow>()
+            .is_some_and(|row| search::normalize(&row.title()).contains(&text))
+    }
+
+    fn selected_tags(&self) -> Vec<String> {
+        self.imp()
+            .rows
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+
+    fn update_selection(&self) {
+        let has_selection = !self.selected_tags().is_empty();
+        self.imp().delete_button.set_sensitive(has_selection);
+    }
+
+    /// Asks for the new name of `from`. Entering an existing tag merges
+    /// `from` into it.
+    fn ask_new_name(&self, from: &str) {
+        let existing: Vec<String> = self.imp().rows.borrow().iter().map(|(tag, _)| tag.clone()).collect();
+        let entry = gtk::Entry::builder()
+            .text(from)
+            .activates_default(true)
+            .build();
+        let alert = adw::AlertDialog::new(Some(&gettext("Rename “{}”").replace("{}", from)), None);
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("rename", &gettext("_Rename"))]);
+        alert.set_response_appearance("rename", adw::ResponseAppearance::Suggested);
+        alert.set_default_response(Some("rename"));
+        alert.set_close_response("cancel");
+        alert.set_response_enabled("rename", false);
+        alert.set_extra_child(Some(&entry));
+
+        let from = from.to_string();
+        entry.connect_changed(glib::clone!(
+            #[weak]
+            alert,
+            #[strong]
+            from,
+            move |entry| {
+                let to = tags::normalize_tag(&entry.text());
+                let below_itself = tags::is_below(&to, &from);
+                let merge = to != from && existing.contains(&to);
+                alert.set_response_enabled("rename", !to.is_empty() && to != from && !below_itself);
+                if below_itself {
+                    alert.set_body(&gettext("A tag cannot be moved below itself."));
+                    alert.set_response_label("rename", &gettext("_Rename"));
+                } else if merge {
+                    alert.set_body(&gettext("“{}” already exists. Its photos and these will share one tag.").replace("{}", &to));
+                    alert.set_response_label("rename", &gettext("_Merge"));
+                } else {
+                    alert.set_body("");
+                    alert.set_response_label("rename", &gettext("_Rename"));
+                }
+            }
+        ));
+        alert.connect_response(Some("rename"), glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            #[weak]
+            entry,
+            move |_, _| {
+                dialog.rename(&from, &tags::normalize_tag(&entry.text()));
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn rename(&self, from: &str, to: &str) {
+        let result = self.manager().borrow_mut().rename_tag(from, to);
+        match result {
+            Ok(count) => {
+                self.reload();
+                let message = ngettext("Renamed on {} photo", "Renamed on {} photos", count as u32)
+                    .replace("{}", &count.to_string());
+                self.show_toast(&message);
+            }
+            Err(err) => self.show_error(&err),
+        }
+    }
+
+    fn set_color(&self, tag: &str, color: Option<usize>) {
+        let result = self.manager().borrow_mut().set_tag_color(tag, color);
+        match result {
+            Ok(()) => self.reload(),
+            Err(err) => self.show_error(&err),
+        }
+    }
+
+    fn confirm_delete_selected(&self) {
+        let count = self.selected_tags().len();
+        if count == 0 {
+            return;
+        }
+
+        let heading = ngettext("Delete {} Tag?", "Delete {} Tags?", count as u32)
+            .replace("{}", &count.to_string());
+        let alert = adw::AlertDialog::new(
+            Some(&heading),
+            Some(&gettext("The tags are removed from all photos. The photos and their notes stay.")),
+        );
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("delete", &gettext("_Delete"))]);
+        alert.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+        alert.connect_response(Some("delete"), glib::clone!(
+            #[weak(rename_to = dialog)]
+            self,
+            move |_, _| {
+                dialog.delete_selected();
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn delete_selected(&self) {
+        let tags = self.selected_tags();
+        let result = self.manager().borrow_mut().delete_tags(&tags);
+        match result {
+            Ok(_) => {
+                self.reload();
+                let message = ngettext("Deleted {} tag", "Deleted {} tags", tags.len() as u32)
+                    .replace("{}", &tags.len().to_string());
+                self.show_toast(&message);
+            }
+            Err(err) => self.show_error(&err),
+        }
+    }
+
+    fn show_toast(&self, message: &str) {
+        self.imp().toast_overlay.add_toast(adw::Toast::new(message));
+    }
+
+    fn show_error(&self, err: &StorageError) {
+        let toast = adw::Toast::builder()
+            .title(format!("{}: {}", gettext("Could not update notes"), err))
+            .use_markup(false)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+}
@@ -0,0 +1,152 @@
+// tags.rs
+//! Hierarchical tags. A tag such as `places/turkey/istanbul` lies below
+//! `places/turkey` and `places`; tags without a separator are plain tags
+//! at the top level.
+use std::collections::{BTreeSet, HashMap};
+use crate::sorting::natural_cmp;
+
+pub const SEPARATOR: char = '/';
+
+/// Trims every level of `tag` and drops empty ones, so that
+/// "places / turkey/" becomes "places/turkey".
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split(SEPARATOR)
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The tag one level up, `None` at the top level.
+pub fn parent(tag: &str) -> Option<&str> {
+    tag.rfind(SEPARATOR).map(|index| &tag[..index])
+}
+
+/// `tag` followed by its ancestors, nearest first.
+pub fn self_and_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(tag), |tag| parent(tag))
+}
+
+/// Whether `tag` lies below `ancestor`, at any depth.
+pub fn is_below(tag: &str, ancestor: &str) -> bool {
+    tag.strip_prefix(ancestor).is_some_and(|rest| rest.starts_with(SEPARATOR))
+}
+
+/// The last level of `tag`, as shown in the tag tree.
+pub fn leaf_name(tag: &str) -> &str {
+    tag.rsplit(SEPARATOR).next().unwrap_or(tag)
+}
+
+/// `tag` with its `from` part replaced by `to`, if it is `from` itself or
+/// lies below it.
+pub fn renamed(tag: &str, from: &str, to: &str) -> Option<String> {
+    let rest = tag.strip_prefix(from)?;
+    (rest.is_empty() || rest.starts_with(SEPARATOR)).then(|| format!("{}{}", to, rest))
+}
+
+/// The tags of the library arranged by level, with the number of photos
+/// at or below each tag.
+#[derive(Debug, Default)]
+pub struct TagTree {
+    /// Child tags by parent; the top level is stored under "".
+    children: HashMap<String, Vec<String>>,
+    counts: HashMap<String, usize>,
+}
+
+impl TagTree {
+    /// Builds the tree from the tags of every photo. Parents that are not
+    /// used on their own still get a node, and a photo is counted once per
+    /// node even if it has several tags below it.
+    pub fn new<'a, P, T>(photos: P) -> Self
+    where
+        P: IntoIterator<Item = T>,
+        T: IntoIterator<Item = &'a String>,
+    {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for tags in photos {
+            let nodes: BTreeSet<&str> = tags.into_iter().flat_map(|tag| self_and_ancestors(tag)).collect();
+            for node in nodes {
+                *counts.entry(node.to_string()).or_default() += 1;
+            }
+        }
+
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for tag in counts.keys() {
+            children
+                .entry(parent(tag).unwrap_or_default().to_string())
+                .or_default()
+                .push(tag.clone());
+        }
+        for tags in children.values_mut() {
+            tags.sort_by(|a, b| natural_cmp(a, b));
+        }
+        TagTree { children, counts }
+    }
+
+    /// Tags directly below `tag`, or the top-level tags for `None`.
+    pub fn children(&self, tag: Option<&str>) -> &[String] {
+        self.children.get(tag.unwrap_or_default()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Number of photos with `tag` or a tag below it.
+    pub fn count(&self, tag: &str) -> usize {
+        self.counts.get(tag).copied().unwrap_or(0)
+    }
+
+    /// Whether both trees have the same tags, whatever their counts.
+    pub fn same_tags(&self, other: &TagTree) -> bool {
+        self.counts.len() == other.counts.len() && self.counts.keys().all(|tag| other.counts.contains_key(tag))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_split_into_levels() {
+        assert_eq!(normalize_tag(" places / turkey//istanbul/ "), "places/turkey/istanbul");
+        assert_eq!(normalize_tag("travel"), "travel");
+        assert_eq!(normalize_tag(" / "), "");
+        assert_eq!(
+            self_and_ancestors("places/turkey/istanbul").collect::<Vec<_>>(),
+            vec!["places/turkey/istanbul", "places/turkey", "places"]
+        );
+        assert_eq!(leaf_name("places/turkey"), "turkey");
+        assert!(is_below("places/turkey", "places"));
+        assert!(!is_below("places", "places"));
+        assert!(!is_below("placesx/turkey", "places"));
+    }
+
+    #[test]
+    fn renaming_a_tag_moves_the_tags_below_it() {
+        assert_eq!(renamed("places", "places", "travel").as_deref(), Some("travel"));
+        assert_eq!(
+            renamed("places/turkey/istanbul", "places/turkey", "türkiye").as_deref(),
+            Some("türkiye/istanbul")
+        );
+        assert_eq!(renamed("placesx", "places", "travel"), None);
+        assert_eq!(renamed("work/places", "places", "travel"), None);
+    }
+
+    #[test]
+    fn tree_counts_each_photo_once_per_node() {
+        let photos = [
+            vec!["places/turkey/istanbul".to_string(), "places/turkey".to_string()],
+            vec!["places/greece".to_string(), "travel".to_string()],
+            vec!["travel".to_string()],
+        ];
+        let tree = TagTree::new(&photos);
+        assert_eq!(tree.children(None), ["places", "travel"]);
+        assert_eq!(tree.children(Some("places")), ["places/greece", "places/turkey"]);
+        assert_eq!(tree.children(Some("travel")), [] as [String; 0]);
+        assert_eq!(tree.count("places"), 2);
+        assert_eq!(tree.count("places/turkey"), 1);
+        assert_eq!(tree.count("travel"), 2);
+        assert_eq!(tree.count("unknown"), 0);
+
+        let recounted = TagTree::new(&photos[..2]);
+        assert!(tree.same_tags(&recounted));
+        assert!(!tree.same_tags(&TagTree::new(&photos[1..])));
+    }
+}
@@ -0,0 +1,167 @@
+// texture_loader.rs
+use gtk::prelude::*;
+use gtk::{gdk, glib};
+use lru::LruCache;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::formats;
+use crate::orientation::Orientation;
+use crate::thumbnails::{self, ThumbnailSize};
+
+/// How many decoded thumbnails are kept in memory. A large thumbnail takes
+/// about 256 KiB, so this stays below 128 MiB.
+const CACHE_CAPACITY: usize = 500;
+
+/// Upper bound for decoding threads, so that fast scrolling does not start
+/// hundreds of threads.
+const MAX_THREADS: usize = 4;
+
+pub enum LoadResult {
+    Loaded(gdk::Texture),
+    /// The file could not be thumbnailed.
+    Failed,
+    /// The request was cancelled before it was decoded.
+    Cancelled,
+}
+
+/// Decodes gallery thumbnails on a small thread pool and keeps the most
+/// recently used ones in memory.
+#[derive(Debug)]
+pub struct TextureLoader {
+    size: ThumbnailSize,
+    pool: glib::ThreadPool,
+    cache: RefCell<LruCache<String, gdk::Texture>>,
+    /// Stop flags of requests that have not finished yet, by photo path.
+    pending: RefCell<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl TextureLoader {
+    pub fn new(size: ThumbnailSize) -> Self {
+        let threads = std::thread::available_parallelism()
+            .map(|threads| threads.get())
+            .unwrap_or(2)
+            .min(MAX_THREADS);
+        TextureLoader {
+            size,
+            pool: glib::ThreadPool::shared(Some(threads as u32)).expect("Could not create the thumbnail thread pool"),
+            cache: RefCell::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap())),
+            pending: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the texture if it is in memory, marking it as recently used.
+    pub fn cached(&self, path: &str) -> Option<gdk::Texture> {
+        self.cache.borrow_mut().get(path).cloned()
+    }
+
+    /// Loads the thumbnail of `path` turned by the user's `orientation`.
+    /// Cached textures are returned as they are, so call [`Self::forget`]
+    /// when the orientation changes.
+    pub async fn load(&self, path: &str, orientation: Orientation) -> LoadResult {
+        if let Some(texture) = self.cached(path) {
+            return LoadResult::Loaded(texture);
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = self.pending.borrow_mut().insert(path.to_string(), cancelled.clone()) {
+            previous.store(true, Ordering::Relaxed);
+        }
+
+        let size = self.size;
+        let file_path = PathBuf::from(path);
+        let stop = cancelled.clone();
+        let result = self.pool.push_future(move || {
+            // Hücre görünümden çıktıysa hiç çözme
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            Some(thumbnails::load(&file_path, size, orientation))
+        });
+        let texture = match result {
+            // A panicking decoder counts as a failed thumbnail
+            Ok(future) => future.await.unwrap_or(Some(None)),
+            Err(_) => Some(None),
+        };
+
+        {
+            let mut pending = self.pending.borrow_mut();
+            if pending.get(path).is_some_and(|current| Arc::ptr_eq(current, &cancelled)) {
+                pending.remove(path);
+            }
+        }
+
+        match texture {
+            Some(Some(texture)) => {
+                self.cache.borrow_mut().put(path.to_string(), texture.clone());
+                LoadResult::Loaded(texture)
+            }
+            Some(None) => LoadResult::Failed,
+            None => LoadResult::Cancelled,
+        }
+    }
+
+    /// Shows the thumbnail of `path` in `picture`, loading it in the
+    /// background if needed. The widget may show another photo by the time
+    /// the texture is ready, so `still_wanted` is asked first.
+    pub fn show_thumbnail<F>(self: &Rc<Self>, picture: &gtk::Picture, path: &str, orientation: Orientation, still_wanted: F)
+    where
+        F: Fn() -> bool + 'static,
+    {
+        if let Some(texture) = self.cached(path) {
+            picture.set_paintable(Some(&texture));
+            picture.set_tooltip_text(None);
+            return;
+        }
+        formats::show_loading(picture);
+
+        // Küçük resim arka planda okunur ya da oluşturulur
+        let loader = self.clone();
+        let path = path.to_string();
+        glib::spawn_future_local(glib::clone!(
+            #[weak]
+            picture,
+            async move {
+                let result = loader.load(&path, orientation).await;
+                if !still_wanted() {
+                    return;
+                }
+                match result {
+                    LoadResult::Loaded(texture) => {
+                        picture.set_paintable(Some(&texture));
+                        picture.set_tooltip_text(None);
+                    }
+                    LoadResult::Failed => formats::show_placeholder(&picture),
+                    LoadResult::Cancelled => {}
+                }
+            }
+        ));
+    }
+
+    /// Cancels a request whose cell went out of view. Decodes that already
+    /// started still finish and end up in the cache.
+    pub fn cancel(&self, path: &str) {
+        if let Some(cancelled) = self.pending.borrow_mut().remove(path) {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Drops the cached texture of a photo that changed or went away.
+    pub fn forget(&self, path: &str) {
+        self.cancel(path);
+        self.cache.borrow_mut().pop(path);
+    }
+
+    /// Drops every cached texture, e.g. after restoring a backup changed
+    /// the orientation of any number of photos.
+    pub fn clear(&self) {
+        for (_, cancelled) in self.pending.borrow_mut().drain() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        self.cache.borrow_mut().clear();
+    }
+}
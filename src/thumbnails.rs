@@ -0,0 +1,198 @@
+// thumbnails.rs
+//! Thumbnails shared with other applications through the freedesktop
+//! thumbnail cache, see
+//! https://specifications.freedesktop.org/thumbnail-spec/latest/
+use gtk::{gdk, gdk_pixbuf, gio, glib};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::config::VERSION;
+use crate::formats::{self, Renderer};
+use crate::metadata;
+use crate::orientation::Orientation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+}
+
+impl ThumbnailSize {
+    pub fn pixels(self) -> i32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+        }
+    }
+
+    fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+        }
+    }
+}
+
+fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("thumbnails")
+}
+
+/// Thumbnails are named after the MD5 hash of the file's URI.
+fn file_name(uri: &str) -> String {
+    format!("{:x}.png", md5::compute(uri))
+}
+
+pub fn thumbnail_path(uri: &str, size: ThumbnailSize) -> PathBuf {
+    cache_dir().join(size.dir_name()).join(file_name(uri))
+}
+
+/// Failed attempts are remembered per application so that broken files
+/// are not decoded again until they change.
+fn failed_path(uri: &str) -> PathBuf {
+    cache_dir()
+        .join("fail")
+        .join(format!("notia-{}", VERSION))
+        .join(file_name(uri))
+}
+
+/// Loads the thumbnail of `path` as a texture turned by `orientation`,
+/// generating it if needed. Blocks on disk I/O and decoding, so call it
+/// off the main thread.
+pub fn load(path: &Path, size: ThumbnailSize, orientation: Orientation) -> Option<gdk::Texture> {
+    thumbnail(path, size).map(|pixbuf| gdk::Texture::for_pixbuf(&orientation.apply(&pixbuf)))
+}
+
+/// Returns a valid cached thumbnail or creates one. Like the files of
+/// other thumbnailers, it is already turned by the EXIF orientation.
+/// `None` means the file could not be thumbnailed, now or in an earlier
+/// attempt.
+pub fn thumbnail(path: &Path, size: ThumbnailSize) -> Option<gdk_pixbuf::Pixbuf> {
+    let uri = glib::filename_to_uri(path, None).ok()?;
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs()
+        .to_string();
+    let file_size = metadata.len().to_string();
+    let info = [
+        ("tEXt::Thumb::URI", uri.as_str()),
+        ("tEXt::Thumb::MTime", mtime.as_str()),
+        ("tEXt::Thumb::Size", file_size.as_str()),
+        ("tEXt::Software", "Notia"),
+    ];
+
+    let thumbnail_path = thumbnail_path(&uri, size);
+    if let Some(pixbuf) = read_valid(&thumbnail_path, &uri, &mtime) {
+        return Some(pixbuf);
+    }
+    let failed_path = failed_path(&uri);
+    if read_valid(&failed_path, &uri, &mtime).is_some() {
+        return None;
+    }
+
+    match generate(path, size) {
+        Ok(pixbuf) => {
+            // The cache is only an optimization; the thumbnail is shown anyway
+            let _ = save(&pixbuf, &thumbnail_path, &info);
+            Some(pixbuf)
+        }
+        Err(_) => {
+            // Remember the failure so that the photo is not decoded again
+            let marker = gdk_pixbuf::Pixbuf::new(gdk_pixbuf::Colorspace::Rgb, true, 8, 1, 1)?;
+            let _ = save(&marker, &failed_path, &info);
+            None
+        }
+    }
+}
+
+/// Loads a cached thumbnail if it belongs to the current version of the file.
+fn read_valid(thumbnail_path: &Path, uri: &str, mtime: &str) -> Option<gdk_pixbuf::Pixbuf> {
+    if !thumbnail_path.exists() {
+        return None;
+    }
+    let pixbuf = gdk_pixbuf::Pixbuf::from_file(thumbnail_path).ok()?;
+    let valid = pixbuf.option("tEXt::Thumb::URI").as_deref() == Some(uri)
+        && pixbuf.option("tEXt::Thumb::MTime").as_deref() == Some(mtime);
+    valid.then_some(pixbuf)
+}
+
+fn generate(path: &Path, size: ThumbnailSize) -> Result<gdk_pixbuf::Pixbuf, glib::Error> {
+    let pixels = size.pixels();
+    let format = formats::detect(path);
+
+    if format.is_some_and(|format| format.renderer == Renderer::EmbeddedPreview) {
+        let preview = formats::raw_preview(path).ok_or_else(|| {
+            glib::Error::new(gdk_pixbuf::PixbufError::UnknownType, "No embedded preview")
+        })?;
+        let stream = gio::MemoryInputStream::from_bytes(&glib::Bytes::from_owned(preview));
+        let pixbuf = gdk_pixbuf::Pixbuf::from_stream_at_scale(&stream, pixels, pixels, true, gio::Cancellable::NONE)?;
+        return Ok(metadata::read_orientation(path).apply(&pixbuf));
+    }
+
+    // Küçük resimleri büyütme
+    let fits = gdk_pixbuf::Pixbuf::file_info(path)
+        .is_some_and(|(_, width, height)| width <= pixels && height <= pixels);
+    let pixbuf = if fits {
+        gdk_pixbuf::Pixbuf::from_file(path)?
+    } else {
+        gdk_pixbuf::Pixbuf::from_file_at_scale(path, pixels, pixels, true)?
+    };
+    Ok(pixbuf.apply_embedded_orientation().unwrap_or(pixbuf))
+}
+
+/// Writes the thumbnail next to its destination and renames it into place,
+/// so other readers never see a partial file.
+fn save(pixbuf: &gdk_pixbuf::Pixbuf, dest: &Path, info: &[(&str, &str)]) -> Result<(), glib::Error> {
+    let io_error = |err: std::io::Error| glib::Error::new(gio::IOErrorEnum::Failed, &err.to_string());
+
+    let dir = dest.parent().unwrap_or(Path::new("."));
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir).map_err(io_error)?;
+
+    // Several loader threads may write the same thumbnail
+    let tmp = dir.join(format!(
+        ".{}.{}-{:?}.tmp",
+        dest.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id(),
+        std::thread::current().id()
+    ));
+    pixbuf.savev(&tmp, "png", info)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600));
+    }
+    fs::rename(&tmp, dest).map_err(|err| {
+        let _ = fs::remove_file(&tmp);
+        io_error(err)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnails_are_named_after_the_uri_hash() {
+        // Example from the thumbnail specification
+        assert_eq!(file_name("file:///home/jens/photos/me.png"), "c6ee772d9e49320e97ec29a7eb5b1697.png");
+    }
+
+    #[test]
+    fn thumbnail_sizes_use_their_own_folders() {
+        let uri = "file:///a.jpg";
+        let normal = thumbnail_path(uri, ThumbnailSize::Normal);
+        let large = thumbnail_path(uri, ThumbnailSize::Large);
+        assert!(normal.ends_with(Path::new("thumbnails/normal").join(file_name(uri))));
+        assert!(large.ends_with(Path::new("thumbnails/large").join(file_name(uri))));
+        assert!(failed_path(uri).starts_with(cache_dir().join("fail")));
+    }
+}
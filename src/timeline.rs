@@ -0,0 +1,143 @@
+// timeline.rs
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use crate::sorting::natural_cmp;
+
+/// Length of the periods the timeline groups photos by, stored as the
+/// `timeline-grouping` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Grouping {
+    Day,
+    #[default]
+    Month,
+    Year,
+}
+
+impl Grouping {
+    pub fn from_nick(nick: &str) -> Grouping {
+        match nick {
+            "day" => Grouping::Day,
+            "year" => Grouping::Year,
+            _ => Grouping::Month,
+        }
+    }
+
+    /// First day of the period `date` falls in.
+    pub fn period(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Grouping::Day => date,
+            Grouping::Month => date.with_day(1).unwrap_or(date),
+            Grouping::Year => date.with_ordinal(1).unwrap_or(date),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineGroup {
+    /// First day of the period; `None` collects the photos without a date.
+    pub period: Option<NaiveDate>,
+    pub photos: Vec<String>,
+}
+
+/// One line of thumbnails in the timeline. Groups are split into rows so
+/// that the list only creates widgets for what is on screen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineRow {
+    /// Index of the group in the list returned by [`group_photos`].
+    pub group: usize,
+    /// Whether the group's header is shown above this row.
+    pub first: bool,
+    pub photos: Vec<String>,
+}
+
+/// Groups photos by the period they were taken in, newest first. Photos
+/// without a date come last.
+pub fn group_photos(mut photos: Vec<(String, Option<NaiveDateTime>)>, grouping: Grouping) -> Vec<TimelineGroup> {
+    photos.sort_by(|(a_path, a_date), (b_path, b_date)| {
+        b_date.cmp(a_date).then_with(|| natural_cmp(a_path, b_path))
+    });
+
+    let mut groups: Vec<TimelineGroup> = Vec::new();
+    for (path, date) in photos {
+        let period = date.map(|date| grouping.period(date.date()));
+        match groups.last_mut() {
+            Some(group) if group.period == period => group.photos.push(path),
+            _ => groups.push(TimelineGroup { period, photos: vec![path] }),
+        }
+    }
+    groups
+}
+
+/// Number of thumbnails of at least `tile_width` pixels, `spacing` apart,
+/// that fit in `width`. Always at least one.
+pub fn columns_for_width(width: i32, tile_width: i32, spacing: i32) -> usize {
+    ((width + spacing) / (tile_width + spacing).max(1)).max(1) as usize
+}
+
+pub fn rows(groups: &[TimelineGroup], columns: usize) -> Vec<TimelineRow> {
+    groups
+        .iter()
+        .enumerate()
+        .flat_map(|(group, timeline_group)| {
+            timeline_group
+                .photos
+                .chunks(columns.max(1))
+                .enumerate()
+                .map(move |(index, photos)| TimelineRow {
+                    group,
+                    first: index == 0,
+                    photos: photos.to_vec(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn taken(date: &str) -> Option<NaiveDateTime> {
+        Some(NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap())
+    }
+
+    fn sample() -> Vec<(String, Option<NaiveDateTime>)> {
+        vec![
+            ("a.jpg".to_string(), taken("2024-03-02 10:00")),
+            ("b.jpg".to_string(), None),
+            ("c.jpg".to_string(), taken("2024-03-20 09:00")),
+            ("d.jpg".to_string(), taken("2023-12-31 23:59")),
+            ("e.jpg".to_string(), taken("2024-03-02 18:30")),
+        ]
+    }
+
+    #[test]
+    fn photos_are_grouped_newest_first() {
+        let groups = group_photos(sample(), Grouping::Month);
+        let periods: Vec<Option<NaiveDate>> = groups.iter().map(|group| group.period).collect();
+        assert_eq!(
+            periods,
+            vec![NaiveDate::from_ymd_opt(2024, 3, 1), NaiveDate::from_ymd_opt(2023, 12, 1), None]
+        );
+        assert_eq!(groups[0].photos, vec!["c.jpg", "e.jpg", "a.jpg"]);
+        assert_eq!(groups[2].photos, vec!["b.jpg"]);
+
+        assert_eq!(group_photos(sample(), Grouping::Day).len(), 4);
+        assert_eq!(group_photos(sample(), Grouping::Year).len(), 3);
+    }
+
+    #[test]
+    fn columns_follow_the_width() {
+        assert_eq!(columns_for_width(0, 200, 6), 1);
+        assert_eq!(columns_for_width(199, 200, 6), 1);
+        assert_eq!(columns_for_width(406, 200, 6), 2);
+        assert_eq!(columns_for_width(405, 200, 6), 1);
+        assert_eq!(columns_for_width(1030, 200, 6), 5);
+    }
+
+    #[test]
+    fn groups_are_split_into_rows() {
+        let groups = group_photos(sample(), Grouping::Month);
+        let rows = rows(&groups, 2);
+        let shape: Vec<(usize, bool, usize)> = rows.iter().map(|row| (row.group, row.first, row.photos.len())).collect();
+        assert_eq!(shape, vec![(0, true, 2), (0, false, 1), (1, true, 1), (2, true, 1)]);
+    }
+}
@@ -0,0 +1,494 @@
+// timeline_view.rs
+use adw::prelude::*;
+use gtk::subclass::prelude::*;
+use chrono::Datelike;
+use gettextrs::gettext;
+use gtk::glib::subclass::Signal;
+use gtk::{gio, glib, CompositeTemplate};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use crate::photo_manager::PhotoManager;
+use crate::texture_loader::TextureLoader;
+use crate::timeline::{self, Grouping, TimelineGroup, TimelineRow};
+
+/// Smallest width of a thumbnail; rows hold as many as fit.
+const TILE_WIDTH: i32 = 200;
+const TILE_HEIGHT: i32 = 150;
+const TILE_SPACING: i32 = 6;
+
+mod imp {
+    use super::*;
+
+    #[derive(Debug, CompositeTemplate)]
+    #[template(resource = "/com/vastsea/notia/timeline_view.ui")]
+    pub struct TimelineView {
+        #[template_child]
+        pub scrolled_window: TemplateChild<gtk::ScrolledWindow>,
+        #[template_child]
+        pub list_view: TemplateChild<gtk::ListView>,
+        #[template_child]
+        pub sticky_header: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub scrubber: TemplateChild<gtk::Scale>,
+
+        /// `TimelineRow`s wrapped in `BoxedAnyObject`s.
+        pub rows: gio::ListStore,
+        pub groups: RefCell<Vec<TimelineGroup>>,
+        pub grouping: Cell<Grouping>,
+        /// Thumbnails per row, following the width of the list.
+        pub columns: Cell<usize>,
+        pub reflow_pending: Cell<bool>,
+        /// Row position shown by each bound row widget, to find the row at
+        /// the top of the view.
+        pub bound_rows: RefCell<HashMap<gtk::Widget, u32>>,
+        pub photo_manager: RefCell<Option<Rc<RefCell<PhotoManager>>>>,
+        pub texture_loader: RefCell<Option<Rc<TextureLoader>>>,
+    }
+
+    impl Default for TimelineView {
+        fn default() -> Self {
+            Self {
+                scrolled_window: TemplateChild::default(),
+                list_view: TemplateChild::default(),
+                sticky_header: TemplateChild::default(),
+                scrubber: TemplateChild::default(),
+                rows: gio::ListStore::new::<glib::BoxedAnyObject>(),
+                groups: RefCell::new(Vec::new()),
+                grouping: Cell::new(Grouping::default()),
+                columns: Cell::new(1),
+                reflow_pending: Cell::new(false),
+                bound_rows: RefCell::new(HashMap::new()),
+                photo_manager: RefCell::new(None),
+                texture_loader: RefCell::new(None),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TimelineView {
+        const NAME: &'static str = "TimelineView";
+        type Type = super::TimelineView;
+        type ParentType = gtk::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for TimelineView {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_list();
+        }
+
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![Signal::builder("photo-activated")
+                    .param_types([String::static_type()])
+                    .build()]
+            })
+        }
+    }
+
+    impl WidgetImpl for TimelineView {}
+    impl BoxImpl for TimelineView {}
+}
+
+glib::wrapper! {
+    pub struct TimelineView(ObjectSubclass<imp::TimelineView>) @extends gtk::Widget, gtk::Box;
+}
+
+impl TimelineView {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    pub fn set_photo_manager(&self, manager: Rc<RefCell<PhotoManager>>) {
+        *self.imp().photo_manager.borrow_mut() = Some(manager);
+    }
+
+    pub fn set_texture_loader(&self, loader: Rc<TextureLoader>) {
+        *self.imp().texture_loader.borrow_mut() = Some(loader);
+    }
+
+    /// Called with the path of a photo the user clicked.
+    pub fn connect_photo_activated<F: Fn(&Self, &str) + 'static>(&self, callback: F) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "photo-activated",
+            false,
+            glib::closure_local!(move |view: &TimelineView, photo: String| callback(view, &photo)),
+        )
+    }
+
+    /// Shows `groups`, keeping the scroll position as far as possible.
+    pub fn set_groups(&self, groups: Vec<TimelineGroup>, grouping: Grouping) {
+        let imp = self.imp();
+        *imp.groups.borrow_mut() = groups;
+        imp.grouping.set(grouping);
+        self.rebuild_rows();
+    }
+
+    fn rebuild_rows(&self) {
+        let imp = self.imp();
+        let rows = timeline::rows(&imp.groups.borrow(), imp.columns.get());
+        self.update_scrubber(&rows);
+        let objects: Vec<glib::BoxedAnyObject> = rows.into_iter().map(glib::BoxedAnyObject::new).collect();
+        imp.rows.splice(0, imp.rows.n_items(), &objects);
+        self.update_sticky_header();
+    }
+
+    /// Derives the number of columns from the width of the list and splits
+    /// the groups into rows again when it changes.
+    fn update_columns(&self) {
+        let imp = self.imp();
+        let width = imp.list_view.hadjustment().map_or(0.0, |adjustment| adjustment.page_size());
+        if width <= 0.0 {
+            return;
+        }
+        let columns = timeline::columns_for_width(width as i32, TILE_WIDTH, TILE_SPACING);
+        if columns == imp.columns.get() {
+            return;
+        }
+        imp.columns.set(columns);
+
+        // Boyutlandırma sırasında modeli değiştirmemek için boşta yeniden diz
+        if imp.reflow_pending.replace(true) {
+            return;
+        }
+        glib::idle_add_local_once(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move || {
+                view.imp().reflow_pending.set(false);
+                view.reflow();
+            }
+        ));
+    }
+
+    /// Rebuilds the rows for the current column count, keeping the photo at
+    /// the top of the view in sight.
+    fn reflow(&self) {
+        let imp = self.imp();
+        let top_photo = self
+            .top_row()
+            .filter(|_| imp.scrolled_window.vadjustment().value() > 0.0)
+            .and_then(|position| imp.rows.item(position).and_downcast::<glib::BoxedAnyObject>())
+            .and_then(|object| object.borrow::<TimelineRow>().photos.first().cloned());
+        self.rebuild_rows();
+
+        let Some(photo) = top_photo else {
+            return;
+        };
+        let position = (0..imp.rows.n_items()).find(|position| {
+            imp.rows
+                .item(*position)
+                .and_downcast::<glib::BoxedAnyObject>()
+                .is_some_and(|object| object.borrow::<TimelineRow>().photos.contains(&photo))
+        });
+        if let Some(position) = position {
+            self.scroll_to_row(position);
+        }
+    }
+
+    fn setup_list(&self) {
+        let imp = self.imp();
+        let factory = gtk::SignalListItemFactory::new();
+
+        factory.connect_setup(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                item.set_child(Some(&view.create_row()));
+            }
+        ));
+
+        factory.connect_bind(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                view.bind_row(item);
+            }
+        ));
+
+        factory.connect_unbind(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                view.unbind_row(item);
+            }
+        ));
+
+        imp.list_view.set_factory(Some(&factory));
+        imp.list_view.set_model(Some(&gtk::NoSelection::new(Some(imp.rows.clone()))));
+
+        imp.scrolled_window.hadjustment().connect_page_size_notify(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_| {
+                view.update_columns();
+            }
+        ));
+
+        imp.scrolled_window.vadjustment().connect_value_changed(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            move |_| {
+                view.update_sticky_header();
+            }
+        ));
+
+        imp.scrubber.set_increments(1.0, 10.0);
+        imp.scrubber.set_round_digits(0);
+        // change-value yalnızca kullanıcı kaydırıcıyı oynattığında gelir
+        imp.scrubber.connect_change_value(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            #[upgrade_or]
+            glib::Propagation::Proceed,
+            move |_, _, value| {
+                view.scroll_to_row(value.round().max(0.0) as u32);
+                glib::Propagation::Proceed
+            }
+        ));
+    }
+
+    /// A row is a date header followed by a line of thumbnail buttons. The
+    /// buttons are added when the row is bound, as the column count varies.
+    fn create_row(&self) -> gtk::Box {
+        let row = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        row.set_margin_bottom(6);
+
+        let header = gtk::Label::new(None);
+        header.set_xalign(0.0);
+        header.add_css_class("title-4");
+        header.add_css_class("timeline-header");
+        row.append(&header);
+
+        let tiles = gtk::Box::new(gtk::Orientation::Horizontal, TILE_SPACING);
+        tiles.set_homogeneous(true);
+        row.append(&tiles);
+        row
+    }
+
+    fn create_tile(&self, row: &gtk::Widget, column: usize) -> gtk::Button {
+        // Genişlik sütun sayısından gelir, küçük pencerede taşmasın
+        let picture = gtk::Picture::new();
+        picture.set_content_fit(gtk::ContentFit::Cover);
+        picture.set_size_request(-1, TILE_HEIGHT);
+
+        let button = gtk::Button::builder().child(&picture).build();
+        button.add_css_class("flat");
+        button.connect_clicked(glib::clone!(
+            #[weak(rename_to = view)]
+            self,
+            #[weak]
+            row,
+            move |_| {
+                view.activate_tile(&row, column);
+            }
+        ));
+        button
+    }
+
+    /// Adds or removes buttons so that `tiles` has one per column.
+    fn resize_tiles(&self, row: &gtk::Widget, tiles: &gtk::Box) {
+        let columns = self.imp().columns.get();
+        let mut count = 0;
+        let mut child = tiles.first_child();
+        while let Some(tile) = child {
+            child = tile.next_sibling();
+            if count < columns {
+                count += 1;
+            } else {
+                tiles.remove(&tile);
+            }
+        }
+        for column in count..columns {
+            tiles.append(&self.create_tile(row, column));
+        }
+    }
+
+    fn bind_row(&self, item: &gtk::ListItem) {
+        let imp = self.imp();
+        let Some(row) = item.child() else {
+            return;
+        };
+        let Some(object) = item.item().and_downcast::<glib::BoxedAnyObject>() else {
+            return;
+        };
+        imp.bound_rows.borrow_mut().insert(row.clone(), item.position());
+
+        let data = object.borrow::<TimelineRow>();
+        if let Some(header) = row.first_child().and_downcast::<gtk::Label>() {
+            header.set_visible(data.first);
+            if data.first {
+                header.set_text(&self.group_title(data.group));
+            }
+        }
+
+        let loader = imp.texture_loader.borrow().clone();
+        let manager = imp.photo_manager.borrow().clone();
+        let Some(tiles) = row.last_child().and_downcast::<gtk::Box>() else {
+            return;
+        };
+        self.resize_tiles(&row, &tiles);
+        let mut button = tiles.first_child();
+        let mut column = 0;
+        while let Some(tile) = button {
+            let picture = tile.downcast_ref::<gtk::Button>().and_then(|tile| tile.child()).and_downcast::<gtk::Picture>();
+            match (data.photos.get(column), picture) {
+                (Some(photo), Some(picture)) => {
+                    // Boş kalan hücreler de yer kaplar ki son satır genişlemesin
+                    tile.set_opacity(1.0);
+                    tile.set_sensitive(true);
+                    if let (Some(loader), Some(manager)) = (&loader, &manager) {
+                        let orientation = manager.borrow().orientation(photo);
+                        let item = item.downgrade();
+                        let object = object.clone();
+                        loader.show_thumbnail(&picture, photo, orientation, move || {
+                            item.upgrade().and_then(|item| item.item()).as_ref() == Some(object.upcast_ref::<glib::Object>())
+                        });
+                    }
+                }
+                (_, picture) => {
+                    tile.set_opacity(0.0);
+                    tile.set_sensitive(false);
+                    if let Some(picture) = picture {
+                        picture.set_paintable(gtk::gdk::Paintable::NONE);
+                    }
+                }
+            }
+            button = tile.next_sibling();
+            column += 1;
+        }
+    }
+
+    fn unbind_row(&self, item: &gtk::ListItem) {
+        let imp = self.imp();
+        if let Some(row) = item.child() {
+            imp.bound_rows.borrow_mut().remove(&row);
+        }
+        let (Some(loader), Some(object)) = (
+            imp.texture_loader.borrow().clone(),
+            item.item().and_downcast::<glib::BoxedAnyObject>(),
+        ) else {
+            return;
+        };
+        // Görünümden çıkan satırların bekleyen çözümlemelerini iptal et
+        for photo in &object.borrow::<TimelineRow>().photos {
+            loader.cancel(photo);
+        }
+    }
+
+    fn activate_tile(&self, row: &gtk::Widget, column: usize) {
+        let imp = self.imp();
+        let Some(position) = imp.bound_rows.borrow().get(row).copied() else {
+            return;
+        };
+        let Some(object) = imp.rows.item(position).and_downcast::<glib::BoxedAnyObject>() else {
+            return;
+        };
+        let photo = object.borrow::<TimelineRow>().photos.get(column).cloned();
+        if let Some(photo) = photo {
+            self.emit_by_name::<()>("photo-activated", &[&photo]);
+        }
+    }
+
+    fn group_title(&self, group: usize) -> String {
+        let imp = self.imp();
+        let Some(period) = imp.groups.borrow().get(group).and_then(|group| group.period) else {
+            return gettext("Unknown Date");
+        };
+        let format = match imp.grouping.get() {
+            Grouping::Day => "%e %B %Y",
+            Grouping::Month => "%B %Y",
+            Grouping::Year => "%Y",
+        };
+        glib::DateTime::from_local(period.year(), period.month() as i32, period.day() as i32, 0, 0, 0.0)
+            .and_then(|date| date.format(format))
+            .map(|title| title.trim().to_string())
+            .unwrap_or_else(|_| period.to_string())
+    }
+
+    /// Marks the first row of every group on the scrubber, labelling the
+    /// first one of each year.
+    fn update_scrubber(&self, rows: &[TimelineRow]) {
+        let imp = self.imp();
+        let scrubber = &imp.scrubber;
+        scrubber.clear_marks();
+        scrubber.set_visible(rows.len() > 1);
+        scrubber.set_range(0.0, rows.len().saturating_sub(1).max(1) as f64);
+
+        let groups = imp.groups.borrow();
+        let mut last_year = None;
+        for (index, row) in rows.iter().enumerate().filter(|(_, row)| row.first) {
+            let year = groups[row.group].period.map(|period| period.year());
+            let label = (index == 0 || year != last_year)
+                .then(|| year.map_or_else(|| gettext("Unknown Date"), |year| year.to_string()));
+            scrubber.add_mark(index as f64, gtk::PositionType::Left, label.as_deref());
+            last_year = year;
+        }
+    }
+
+    /// Position of the row at the top of the view.
+    fn top_row(&self) -> Option<u32> {
+        let imp = self.imp();
+        let list_view = &imp.list_view;
+        let bound_rows = imp.bound_rows.borrow();
+        let mut widget = list_view.pick(list_view.width() as f64 / 2.0, 1.0, gtk::PickFlags::DEFAULT);
+        while let Some(current) = widget {
+            if let Some(position) = bound_rows.get(&current) {
+                return Some(*position);
+            }
+            widget = current.parent();
+        }
+        None
+    }
+
+    /// Keeps the header of the group at the top visible while scrolling
+    /// and moves the scrubber along.
+    fn update_sticky_header(&self) {
+        let imp = self.imp();
+        let scrolled = imp.scrolled_window.vadjustment().value() > 0.0;
+        let Some(position) = self.top_row().filter(|_| scrolled) else {
+            imp.sticky_header.set_visible(false);
+            if !scrolled {
+                imp.scrubber.set_value(0.0);
+            }
+            return;
+        };
+        imp.scrubber.set_value(position as f64);
+
+        let Some(object) = imp.rows.item(position).and_downcast::<glib::BoxedAnyObject>() else {
+            return;
+        };
+        let group = object.borrow::<TimelineRow>().group;
+        imp.sticky_header.set_text(&self.group_title(group));
+        imp.sticky_header.set_visible(true);
+    }
+
+    fn scroll_to_row(&self, row: u32) {
+        let imp = self.imp();
+        let count = imp.rows.n_items();
+        if count == 0 {
+            return;
+        }
+        let row = row.min(count - 1);
+
+        // Görünür olana kadar kaydırırken yukarıdaki satırlar üste hizalanır,
+        // aşağıdakiler alta. Önce sona gidip satırı hep yukarıdan getir.
+        let adjustment = imp.scrolled_window.vadjustment();
+        adjustment.set_value(adjustment.upper() - adjustment.page_size());
+        imp.list_view.scroll_to(row, gtk::ListScrollFlags::NONE, Some(gtk::ScrollInfo::new()));
+    }
+}
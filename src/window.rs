@@ -1,14 +1,44 @@
 // window.rs
-use gtk::prelude::*;
+use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gtk::{gio, glib};
-use gettextrs::gettext;
-use crate::photo_manager::PhotoManager;
+use gettextrs::{gettext, ngettext};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use crate::album_view::AlbumView;
+use crate::metadata::{self, PhotoMetadata};
+use crate::navigation::NavigationSidebar;
+use crate::orientation::Orientation;
+use crate::orphaned_notes::OrphanedNotesDialog;
+use crate::photo_manager::{self, Flag, LibraryScan, MovedNotes, PhotoManager, PhotoNote, SmartAlbum};
+use crate::preferences::{self, NotiaPreferencesDialog};
+use crate::query::Query;
 use crate::sidebar::Sidebar;
+use crate::search;
+use crate::sorting::{SortKey, SortOrder};
+use crate::tag_manager::TagManagerDialog;
+use crate::storage::{self, StorageError};
+use crate::tags;
+use crate::texture_loader::TextureLoader;
+use crate::thumbnails::ThumbnailSize;
+use crate::timeline::{self, Grouping};
+use crate::timeline_view::TimelineView;
+
+/// Messages sent from the scanning thread to the main loop.
+enum ScanMessage {
+    /// A folder that was read, to be watched right away, and its photos
+    /// with their metadata, read on the worker so that they are sorted
+    /// correctly from the start.
+    Folder(PathBuf, Vec<(String, PhotoMetadata)>),
+    /// The scan result and the notes whose photo moved.
+    Finished(LibraryScan, MovedNotes),
+}
 
 mod imp {
     use super::*;
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
     use std::rc::Rc;
 
     #[derive(Debug, gtk::CompositeTemplate)]
@@ -18,10 +48,30 @@ mod imp {
         #[template_child]
         pub toast_overlay: TemplateChild<adw::ToastOverlay>,
         #[template_child]
+        pub storage_banner: TemplateChild<adw::Banner>,
+        #[template_child]
         pub sidebar_toggle: TemplateChild<gtk::ToggleButton>,
         #[template_child]
+        pub navigation_pane: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub search_bar: TemplateChild<gtk::SearchBar>,
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub content_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub gallery_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
         pub gallery_grid: TemplateChild<gtk::GridView>,
         #[template_child]
+        pub selection_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub selection_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub selection_album_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
+        pub remove_tag_button: TemplateChild<gtk::MenuButton>,
+        #[template_child]
         pub prev_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub photo_counter: TemplateChild<gtk::Label>,
@@ -29,27 +79,129 @@ mod imp {
         pub next_button: TemplateChild<gtk::Button>,
         #[template_child]
         pub sidebar_revealer: TemplateChild<gtk::Revealer>,
+        #[template_child]
+        pub scan_progress: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub scan_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub cancel_scan_button: TemplateChild<gtk::Button>,
         
+        pub settings: gio::Settings,
         pub photo_manager: Rc<RefCell<PhotoManager>>,
         pub current_photo_index: RefCell<usize>,
         pub gallery_model: RefCell<gio::ListStore>,
+        /// Gallery cells on screen by the photo they show.
+        pub bound_cells: RefCell<HashMap<String, gtk::ListItem>>,
+        /// Photos changed on disk whose thumbnail and metadata are reloaded
+        /// together shortly after.
+        pub pending_reloads: RefCell<HashSet<String>>,
+        pub reload_source: RefCell<Option<glib::SourceId>>,
+        /// Position of each photo in `gallery_model`. It is dropped when
+        /// photos are removed or inserted in between and rebuilt on the next
+        /// lookup.
+        pub model_positions: RefCell<Option<HashMap<String, u32>>>,
+        /// Position of each photo in `sort_model`, dropped whenever the
+        /// sorted gallery changes and rebuilt on the next lookup.
+        pub view_positions: RefCell<Option<HashMap<String, u32>>>,
+        /// The photos of `gallery_model` in the selected smart album that
+        /// match the search.
+        pub filter_model: gtk::FilterListModel,
+        pub filter: gtk::CustomFilter,
+        pub search_query: RefCell<Query>,
+        /// Query of the selected smart album.
+        pub album_query: RefCell<Query>,
+        /// `filter_model` in the order shown on screen. Positions such as
+        /// `current_photo_index` refer to this model.
+        pub sort_model: gtk::SortListModel,
+        pub sorter: gtk::CustomSorter,
+        pub sort_order: Cell<SortOrder>,
+        /// Photos selected in the gallery grid, for bulk operations.
+        pub selection: gtk::MultiSelection,
+        /// Tags of the selected photos, offered by "Remove Tag".
+        pub remove_tag_menu: gio::Menu,
         pub sidebar: RefCell<Option<Sidebar>>,
+        pub navigation: RefCell<Option<NavigationSidebar>>,
+        pub timeline_view: RefCell<Option<TimelineView>>,
+        pub album_view: RefCell<Option<AlbumView>>,
+        /// Albums offered by "Add to Album".
+        pub album_menu: gio::Menu,
+        /// Pending timeline rebuild after the gallery changed.
+        pub timeline_update: RefCell<Option<glib::SourceId>>,
+        /// Pending rescan after the library settings changed.
+        pub rescan: RefCell<Option<glib::SourceId>>,
+        /// One monitor per scanned library folder.
+        pub file_monitors: RefCell<HashMap<PathBuf, gio::FileMonitor>>,
+        /// Whether a folder that could not be watched was reported; one
+        /// failure usually means many, e.g. at the inotify limit.
+        pub watch_error_shown: Cell<bool>,
+        pub texture_loader: Rc<TextureLoader>,
+        /// Stop flag of the running library scan.
+        pub scan_cancelled: RefCell<Option<Arc<AtomicBool>>>,
+        /// Paths that changed on disk while a scan was running. They are
+        /// looked at again once the scan is taken over.
+        pub scan_changes: RefCell<Vec<PathBuf>>,
     }
 
      impl Default for NotiaWindow {
         fn default() -> Self {
+            let gallery_model = gio::ListStore::new::<gio::File>();
+            // Süzme ve sıralama işlevleri pencere kurulunca atanır
+            let filter = gtk::CustomFilter::new(|_| true);
+            let filter_model = gtk::FilterListModel::new(Some(gallery_model.clone()), Some(filter.clone()));
+            let sorter = gtk::CustomSorter::new(|_, _| gtk::Ordering::Equal);
+            let sort_model = gtk::SortListModel::new(Some(filter_model.clone()), Some(sorter.clone()));
+            let selection = gtk::MultiSelection::new(Some(sort_model.clone()));
             Self {
                 toast_overlay: TemplateChild::default(),
+                storage_banner: TemplateChild::default(),
                 sidebar_toggle: TemplateChild::default(),
+                navigation_pane: TemplateChild::default(),
+                search_bar: TemplateChild::default(),
+                search_entry: TemplateChild::default(),
+                content_stack: TemplateChild::default(),
+                gallery_stack: TemplateChild::default(),
                 gallery_grid: TemplateChild::default(),
+                selection_revealer: TemplateChild::default(),
+                selection_label: TemplateChild::default(),
+                selection_album_button: TemplateChild::default(),
+                remove_tag_button: TemplateChild::default(),
                 prev_button: TemplateChild::default(),
                 photo_counter: TemplateChild::default(),
                 next_button: TemplateChild::default(),
                 sidebar_revealer: TemplateChild::default(),
+                scan_progress: TemplateChild::default(),
+                scan_label: TemplateChild::default(),
+                cancel_scan_button: TemplateChild::default(),
+                settings: gio::Settings::new("com.vastsea.notia"),
                 photo_manager: Rc::new(RefCell::new(PhotoManager::new())),
                 current_photo_index: RefCell::new(0),
-                gallery_model: RefCell::new(gio::ListStore::new::<gio::File>()),
+                gallery_model: RefCell::new(gallery_model),
+                model_positions: RefCell::new(None),
+                view_positions: RefCell::new(None),
+                bound_cells: RefCell::new(HashMap::new()),
+                pending_reloads: RefCell::new(HashSet::new()),
+                reload_source: RefCell::new(None),
+                filter_model,
+                filter,
+                search_query: RefCell::new(Query::default()),
+                album_query: RefCell::new(Query::default()),
+                sort_model,
+                sorter,
+                sort_order: Cell::new(SortOrder::default()),
+                selection,
+                remove_tag_menu: gio::Menu::new(),
                 sidebar: RefCell::new(None),
+                navigation: RefCell::new(None),
+                timeline_view: RefCell::new(None),
+                album_view: RefCell::new(None),
+                album_menu: gio::Menu::new(),
+                timeline_update: RefCell::new(None),
+                rescan: RefCell::new(None),
+                file_monitors: RefCell::new(HashMap::new()),
+                watch_error_shown: Cell::new(false),
+                scan_cancelled: RefCell::new(None),
+                scan_changes: RefCell::new(Vec::new()),
+                texture_loader: Rc::new(TextureLoader::new(ThumbnailSize::Large)),
             }
         }
     }
@@ -73,12 +225,21 @@ mod imp {
             self.parent_constructed();
             let obj = self.obj();
             obj.setup_callbacks();
+            obj.report_load_errors();
+            obj.backup_notes_daily();
+            obj.apply_library_settings();
+            obj.apply_sort_settings();
             obj.load_photos();
         }
     }
 
     impl WidgetImpl for NotiaWindow {}
-    impl WindowImpl for NotiaWindow {}
+    impl WindowImpl for NotiaWindow {
+        fn close_request(&self) -> glib::Propagation {
+            self.obj().cancel_scan();
+            self.parent_close_request()
+        }
+    }
     impl ApplicationWindowImpl for NotiaWindow {}
     impl AdwApplicationWindowImpl for NotiaWindow {}
 }
@@ -98,6 +259,14 @@ impl NotiaWindow {
 
     fn setup_callbacks(&self) {
         let imp = self.imp();
+
+        imp.gallery_model.borrow().connect_items_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |model, position, removed, added| {
+                window.update_model_positions(model, position, removed, added);
+            }
+        ));
         
         // Initialize sidebar
         let sidebar = Sidebar::new();
@@ -120,6 +289,233 @@ impl NotiaWindow {
         imp.sidebar_revealer.set_child(Some(&sidebar));
         *imp.sidebar.borrow_mut() = Some(sidebar.clone());
         
+        sidebar.set_album_menu(&imp.album_menu);
+
+        // Akıllı albümler ve albümler
+        let navigation = NavigationSidebar::new();
+        navigation.set_photo_manager(imp.photo_manager.clone());
+        navigation.connect_selection_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |navigation| {
+                window.show_navigation_selection(navigation);
+            }
+        ));
+        imp.navigation_pane.append(&navigation);
+        *imp.navigation.borrow_mut() = Some(navigation);
+
+        let album_view = AlbumView::new();
+        album_view.set_photo_manager(imp.photo_manager.clone());
+        album_view.set_texture_loader(imp.texture_loader.clone());
+        album_view.connect_photo_activated(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, photo| {
+                if let Some(position) = window.view_position(photo) {
+                    window.on_photo_selected(position as usize);
+                }
+            }
+        ));
+        imp.content_stack.add_named(&album_view, Some("album"));
+        *imp.album_view.borrow_mut() = Some(album_view);
+
+        let add_to_album = gio::ActionEntry::builder("add-to-album")
+            .parameter_type(Some(glib::VariantTy::INT64))
+            .activate(|window: &Self, _, parameter| {
+                if let Some(id) = parameter.and_then(|parameter| parameter.get::<i64>()) {
+                    window.add_to_album(id);
+                }
+            })
+            .build();
+        let add_to_new_album = gio::ActionEntry::builder("add-to-new-album")
+            .activate(|window: &Self, _, _| window.add_to_new_album())
+            .build();
+        self.add_action_entries([add_to_album, add_to_new_album]);
+        self.update_album_menu();
+
+        // Çoklu seçim ve toplu işlemler
+        imp.selection.connect_selection_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, _, _| {
+                window.on_selection_changed();
+            }
+        ));
+        imp.selection_album_button.set_menu_model(Some(&imp.album_menu));
+        imp.remove_tag_button.set_menu_model(Some(&imp.remove_tag_menu));
+        let select_none = gio::ActionEntry::builder("select-none")
+            .activate(|window: &Self, _, _| {
+                window.imp().selection.unselect_all();
+            })
+            .build();
+        let tag_selection = gio::ActionEntry::builder("tag-selection")
+            .activate(|window: &Self, _, _| window.tag_selection())
+            .build();
+        let remove_tag = gio::ActionEntry::builder("remove-tag-from-selection")
+            .parameter_type(Some(glib::VariantTy::STRING))
+            .activate(|window: &Self, _, parameter| {
+                if let Some(tag) = parameter.and_then(|parameter| parameter.str()) {
+                    window.remove_tag_from_selection(tag);
+                }
+            })
+            .build();
+        let export_selection = gio::ActionEntry::builder("export-selection")
+            .activate(|window: &Self, _, _| window.export_selection())
+            .build();
+        let clear_selection_notes = gio::ActionEntry::builder("clear-selection-notes")
+            .activate(|window: &Self, _, _| window.confirm_clear_selection_notes())
+            .build();
+        self.add_action_entries([select_none, tag_selection, remove_tag, export_selection, clear_selection_notes]);
+
+        // Puan ve işaretler; kısayollar yalnızca galeri odaktayken çalışır
+        // ki not yazarken harfler yakalanmasın
+        let set_rating = gio::ActionEntry::builder("set-rating")
+            .parameter_type(Some(glib::VariantTy::INT32))
+            .activate(|window: &Self, _, parameter| {
+                if let Some(rating) = parameter.and_then(|parameter| parameter.get::<i32>()) {
+                    window.rate_selection(rating.clamp(0, 5) as u8);
+                }
+            })
+            .build();
+        let toggle_favorite = gio::ActionEntry::builder("toggle-favorite")
+            .activate(|window: &Self, _, _| window.toggle_favorite())
+            .build();
+        let toggle_pick = gio::ActionEntry::builder("toggle-pick")
+            .activate(|window: &Self, _, _| window.toggle_flag(Flag::Pick))
+            .build();
+        let toggle_reject = gio::ActionEntry::builder("toggle-reject")
+            .activate(|window: &Self, _, _| window.toggle_flag(Flag::Reject))
+            .build();
+        self.add_action_entries([set_rating, toggle_favorite, toggle_pick, toggle_reject]);
+        let shortcuts = gtk::ShortcutController::new();
+        for rating in 0..=5i32 {
+            let shortcut = gtk::Shortcut::builder()
+                .trigger(&gtk::ShortcutTrigger::parse_string(&rating.to_string()).unwrap())
+                .action(&gtk::NamedAction::new("win.set-rating"))
+                .arguments(&rating.to_variant())
+                .build();
+            shortcuts.add_shortcut(shortcut);
+        }
+        for (key, action) in [("f", "win.toggle-favorite"), ("p", "win.toggle-pick"), ("x", "win.toggle-reject")] {
+            shortcuts.add_shortcut(gtk::Shortcut::new(
+                gtk::ShortcutTrigger::parse_string(key),
+                Some(gtk::NamedAction::new(action)),
+            ));
+        }
+        imp.content_stack.add_controller(shortcuts);
+
+        // Zaman çizelgesi galeriyle aynı fotoğrafları gösterir
+        let timeline_view = TimelineView::new();
+        timeline_view.set_photo_manager(imp.photo_manager.clone());
+        timeline_view.set_texture_loader(imp.texture_loader.clone());
+        timeline_view.connect_photo_activated(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, photo| {
+                if let Some(position) = window.view_position(photo) {
+                    window.on_photo_selected(position as usize);
+                }
+            }
+        ));
+        imp.gallery_stack.add_named(&timeline_view, Some("timeline"));
+        *imp.timeline_view.borrow_mut() = Some(timeline_view);
+
+        imp.settings
+            .bind("view-mode", &*imp.gallery_stack, "visible-child-name")
+            .build();
+        self.add_action(&imp.settings.create_action("view-mode"));
+        self.add_action(&imp.settings.create_action("timeline-grouping"));
+        imp.gallery_stack.connect_visible_child_name_notify(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.schedule_timeline_update();
+            }
+        ));
+        imp.filter_model.connect_items_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, _, _, _| {
+                window.schedule_timeline_update();
+            }
+        ));
+        // Sıralama, süzme ve galeri değişiklikleri hep buradan geçer
+        imp.sort_model.connect_items_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, _, _, _| {
+                window.imp().view_positions.replace(None);
+            }
+        ));
+
+        imp.storage_banner.connect_button_clicked(|banner| {
+            banner.set_revealed(false);
+        });
+
+        imp.cancel_scan_button.connect_clicked(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.cancel_scan();
+            }
+        ));
+        
+        // Rescan when the library configuration changes
+        imp.settings.connect_changed(None, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, key| {
+                if key == "library-folders" || key.starts_with("scan-") {
+                    window.schedule_rescan();
+                } else if key.starts_with("sort-") {
+                    window.apply_sort_settings();
+                } else if key == "timeline-grouping" {
+                    window.schedule_timeline_update();
+                }
+            }
+        ));
+
+        // Sıralama menüsü ayarları doğrudan değiştirir
+        self.add_action(&imp.settings.create_action("sort-key"));
+        self.add_action(&imp.settings.create_action("sort-descending"));
+        imp.sorter.set_sort_func(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[upgrade_or]
+            gtk::Ordering::Equal,
+            move |a, b| window.compare_photos(a, b)
+        ));
+
+        // Arama
+        imp.filter.set_filter_func(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[upgrade_or]
+            true,
+            move |item| window.filter_photo(item)
+        ));
+        imp.search_bar.set_key_capture_widget(Some(self));
+        self.add_action(&gio::PropertyAction::new("search", &*imp.search_bar, "search-mode-enabled"));
+        imp.search_entry.connect_search_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |entry| {
+                window.set_search(&entry.text());
+            }
+        ));
+        let save_search = gio::ActionEntry::builder("save-search")
+            .activate(|window: &Self, _, _| window.save_search())
+            .build();
+        self.add_action_entries([save_search]);
+        self.update_save_search();
+        imp.search_entry.connect_activate(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.on_photo_selected(0);
+            }
+        ));
+        
         // Gallery grid selection
         imp.gallery_grid.connect_activate(glib::clone!(
             #[weak(rename_to = window)]
@@ -177,217 +573,1447 @@ impl NotiaWindow {
         // ));
     }
 
-    pub fn load_photos(&self) {
+    /// Copies the library configuration from GSettings to the photo manager.
+    fn apply_library_settings(&self) {
         let imp = self.imp();
-        
-        // Clear the gallery model
-        imp.gallery_model.borrow_mut().remove_all();
-        
-        // Load photos
-        {
-            let mut manager = imp.photo_manager.borrow_mut();
-            manager.scan_photos();
-            
-            for photo_path in &manager.photos {
-                let file = gio::File::for_path(photo_path);
-                imp.gallery_model.borrow().append(&file);
-            }
-        }
-        
-        // Setup the grid view
-        let selection_model = gtk::SingleSelection::new(Some(imp.gallery_model.borrow().clone()));
-        let factory = gtk::SignalListItemFactory::new();
-        
-        factory.connect_setup(|_, item| {
-            let item = item.downcast_ref::<gtk::ListItem>().unwrap();
-            let picture = gtk::Picture::new();
-            picture.set_content_fit(gtk::ContentFit::Cover);
-            picture.set_size_request(200, 150);
-            item.set_child(Some(&picture));
-        });
-        
-        factory.connect_bind(|_, item| {
-            let item = item.downcast_ref::<gtk::ListItem>().unwrap();
-            let picture = item.child().and_downcast::<gtk::Picture>().unwrap();
-            let file = item.item().and_downcast::<gio::File>().unwrap();
-            
-            picture.set_file(Some(&file));
-        });
-        
-        imp.gallery_grid.set_factory(Some(&factory));
-        imp.gallery_grid.set_model(Some(&selection_model));
-        
-        // Update UI
-        if imp.gallery_model.borrow().n_items() > 0 {
-            *imp.current_photo_index.borrow_mut() = 0;
-            self.update_current_photo();
-        }
+        let mut manager = imp.photo_manager.borrow_mut();
+        manager.library_roots = preferences::library_folders(&imp.settings);
+        manager.scan_options = preferences::scan_options(&imp.settings);
     }
-    
-    fn on_photo_selected(&self, index: usize) {
+
+    /// Re-sorts the gallery after the sort settings changed, keeping the
+    /// current photo selected.
+    fn apply_sort_settings(&self) {
         let imp = self.imp();
-        *imp.current_photo_index.borrow_mut() = index;
-        self.update_current_photo();
+        imp.sort_order.set(SortOrder {
+            key: SortKey::from_nick(&imp.settings.string("sort-key")),
+            descending: imp.settings.boolean("sort-descending"),
+        });
+        let current = self.current_photo_path();
+        imp.sorter.changed(gtk::SorterChange::Different);
+        self.follow_photo(current.as_deref());
     }
-    
-    fn navigate_previous(&self) {
+
+    /// Rebuilds the timeline shortly after the gallery changed, so that a
+    /// scan adding photos folder by folder does not regroup on every batch.
+    /// Nothing is done while the grid is shown.
+    fn schedule_timeline_update(&self) {
         let imp = self.imp();
-        let count = imp.gallery_model.borrow().n_items();
-        if count == 0 {
+        if imp.gallery_stack.visible_child_name().as_deref() != Some("timeline") {
             return;
         }
-        
-        let mut current = imp.current_photo_index.borrow_mut();
-        if *current > 0 {
-            *current -= 1;
-            self.update_current_photo();
+        if imp.timeline_update.borrow().is_some() {
+            return;
         }
+        let source = glib::timeout_add_local_once(
+            std::time::Duration::from_millis(300),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move || {
+                    window.imp().timeline_update.take();
+                    window.update_timeline();
+                }
+            ),
+        );
+        *imp.timeline_update.borrow_mut() = Some(source);
     }
-    
-    fn navigate_next(&self) {
+
+    /// Rescans the library once the library settings stopped changing, so
+    /// that stepping through the depth spin row scans only once.
+    fn schedule_rescan(&self) {
         let imp = self.imp();
-        let count = imp.gallery_model.borrow().n_items();
-        if count == 0 {
-            return;
-        }
-        
-        let mut current = imp.current_photo_index.borrow_mut();
-        if *current < count as usize - 1 {
-            *current += 1;
-            self.update_current_photo();
+        if let Some(source) = imp.rescan.take() {
+            source.remove();
         }
+        let source = glib::timeout_add_local_once(
+            std::time::Duration::from_millis(500),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move || {
+                    window.imp().rescan.take();
+                    window.apply_library_settings();
+                    window.load_photos();
+                }
+            ),
+        );
+        *imp.rescan.borrow_mut() = Some(source);
     }
 
-    pub fn update_current_photo(&self) {
+    fn update_timeline(&self) {
         let imp = self.imp();
-        let current_index = *imp.current_photo_index.borrow();
-        let count = imp.gallery_model.borrow().n_items();
-        
-        if count == 0 {
-            imp.photo_counter.set_text("0 / 0");
-            // Update sidebar with empty data
-            if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
-                sidebar.update_sidebar(crate::sidebar::SidebarData {
-                    photo_path: None,
-                    photo_name: Some(gettext("No photos available")),
-                    note_text: Some("".to_string()),
-                    note_status: Some(gettext("No photos to add notes to")),
-                    tags: None,
-                });
-            }
+        let Some(timeline_view) = imp.timeline_view.borrow().clone() else {
             return;
-        }
-        
-        // Get the current photo file
-        let file = imp.gallery_model.borrow().item(current_index as u32)
-            .and_downcast::<gio::File>().unwrap();
-        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
-        
-        // Update photo counter
-        imp.photo_counter.set_text(&format!("{} / {}", current_index + 1, count));
-        
-        // Get photo name
-        let photo_name = std::path::Path::new(&photo_path)
-            .file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or(&gettext("Unknown File"))
-            .to_string();
-        
-        // Get note info
-        let (note_text, note_status) = {
+        };
+        let photos: Vec<(String, Option<chrono::NaiveDateTime>)> = {
             let manager = imp.photo_manager.borrow();
-            if let Some(note) = manager.get_note(&photo_path) {
-                (note.note.clone(), format!("{}: {}", gettext("Note"), note.timestamp))
-            } else {
-                (String::new(), gettext("No note added yet"))
+            imp.filter_model
+                .iter::<gio::File>()
+                .filter_map(|file| file.ok()?.path())
+                .map(|path| {
+                    let path = path.to_string_lossy().to_string();
+                    let date = manager
+                        .cached_metadata(&path)
+                        .and_then(|metadata| metadata.taken_at.or(metadata.modified));
+                    (path, date)
+                })
+                .collect()
+        };
+        let grouping = Grouping::from_nick(&imp.settings.string("timeline-grouping"));
+        timeline_view.set_groups(timeline::group_photos(photos, grouping), grouping);
+    }
+
+    /// Filters the gallery down to the photos matching the query in `text`,
+    /// keeping the current photo if it still matches. While the query has
+    /// an error the previous results stay and the entry explains the error.
+    fn set_search(&self, text: &str) {
+        let imp = self.imp();
+        let query = match Query::parse(text) {
+            Ok(query) => {
+                imp.search_entry.remove_css_class("error");
+                imp.search_entry.set_tooltip_text(None);
+                query
+            }
+            Err(err) => {
+                imp.search_entry.add_css_class("error");
+                imp.search_entry.set_tooltip_text(Some(&format!("{}: {}", gettext("Invalid search"), err)));
+                return;
             }
         };
-        
-        // Update sidebar with photo data
-        if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
-            let tags = {
-                let manager = imp.photo_manager.borrow();
-                manager.get_tags(&photo_path)
-            };
-            
-            // Set current photo path for tag operations
-            sidebar.set_current_photo_path(photo_path.clone());
-            
-            sidebar.update_sidebar(crate::sidebar::SidebarData {
-                photo_path: Some(photo_path.clone()),
-                photo_name: Some(photo_name.clone()),
-                note_text: Some(note_text.clone()),
-                note_status: Some(note_status.clone()),
-                tags: Some(tags),
-            });
+        let change = {
+            let previous = imp.search_query.borrow();
+            if query == *previous {
+                return;
+            }
+            match (&*previous, &query) {
+                (Query::All, _) => gtk::FilterChange::MoreStrict,
+                (_, Query::All) => gtk::FilterChange::LessStrict,
+                _ => gtk::FilterChange::Different,
+            }
+        };
+        imp.search_query.replace(query);
+        self.update_save_search();
+        self.refilter(change);
+    }
+
+    /// Shows what was picked in the navigation sidebar: an album on its own
+    /// page, or the gallery filtered by a smart album.
+    fn show_navigation_selection(&self, navigation: &NavigationSidebar) {
+        let imp = self.imp();
+        let album = navigation.selected_album();
+        if let Some(album_view) = imp.album_view.borrow().as_ref() {
+            album_view.show_album(album);
+        }
+        imp.content_stack.set_visible_child_name(if album.is_some() { "album" } else { "library" });
+        match navigation.selected_tag() {
+            Some(tag) => self.set_album_query(Query::TagBranch(search::normalize(&tag))),
+            None => self.show_smart_album(navigation.selected_smart_album()),
         }
-        
-        // Update navigation button states
-        imp.prev_button.set_sensitive(current_index > 0);
-        imp.next_button.set_sensitive(current_index < count as usize - 1);
     }
 
-    fn save_current_note(&self) {
+    /// Shows the photos of a smart album, or all photos for `None`.
+    fn show_smart_album(&self, album: Option<SmartAlbum>) {
         let imp = self.imp();
-        let current_index = *imp.current_photo_index.borrow();
-        let count = imp.gallery_model.borrow().n_items();
-        
-        if count == 0 {
+        let query = match album.as_ref().map(|album| Query::parse(&album.query)) {
+            None => Query::All,
+            Some(Ok(query)) => query,
+            Some(Err(err)) => {
+                let message = format!("{}: {}", gettext("Invalid search"), err);
+                imp.toast_overlay.add_toast(adw::Toast::builder().title(message).use_markup(false).build());
+                Query::All
+            }
+        };
+        self.set_album_query(query);
+    }
+
+    /// Shows the photos matching `query`, e.g. those of a smart album or
+    /// below a tag, before the search is applied.
+    fn set_album_query(&self, query: Query) {
+        let imp = self.imp();
+        if query == *imp.album_query.borrow() {
             return;
         }
-        
-        // Get the current photo file
-        let file = imp.gallery_model.borrow().item(current_index as u32)
-            .and_downcast::<gio::File>().unwrap();
-        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
-        
-        // Get note text from sidebar
-        if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
-            let note_text = sidebar.get_note_text();
-            
-            // Save note while preserving existing tags
-            if !note_text.trim().is_empty() {
-                let mut manager = imp.photo_manager.borrow_mut();
-                
-                // Get current tags from sidebar (including newly added ones)
-                let current_tags = sidebar.get_current_tags();
-                
-                // Create new note with current tags
-                let photo_note = crate::photo_manager::PhotoNote {
-                    path: photo_path.clone(),
-                    note: note_text.to_string(),
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    tags: current_tags,
-                };
-                manager.notes.insert(photo_path, photo_note);
-                manager.save_notes();
+        imp.album_query.replace(query);
+        self.refilter(gtk::FilterChange::Different);
+    }
+
+    /// Re-runs the filter, keeping the current photo if it is still shown.
+    fn refilter(&self, change: gtk::FilterChange) {
+        let imp = self.imp();
+        let current = self.current_photo_path();
+        imp.filter.changed(change);
+        match current.as_deref().and_then(|photo| self.view_position(photo)) {
+            Some(position) => {
+                *imp.current_photo_index.borrow_mut() = position as usize;
+                self.update_navigation();
+            }
+            None => {
+                *imp.current_photo_index.borrow_mut() = 0;
+                self.update_current_photo();
             }
         }
-        
-        // Show toast
-        let toast = adw::Toast::new(&gettext("Note saved successfully"));
-        imp.toast_overlay.add_toast(toast);
     }
-    
-    fn clear_current_note(&self) {
+
+    /// Sorts the gallery again if its order depends on notes, and updates
+    /// the search results, after notes, tags or marks changed.
+    pub fn notes_changed(&self) {
         let imp = self.imp();
-        let current_index = *imp.current_photo_index.borrow();
-        let count = imp.gallery_model.borrow().n_items();
-        
-        if count == 0 {
+        if imp.sort_order.get().key.depends_on_notes() {
+            let current = self.current_photo_path();
+            imp.sorter.changed(gtk::SorterChange::Different);
+            self.follow_photo(current.as_deref());
+        }
+        self.refresh_search();
+    }
+
+    /// Updates the smart album and search results after notes or tags
+    /// changed.
+    pub fn refresh_search(&self) {
+        let imp = self.imp();
+        if !imp.photo_manager.borrow_mut().take_search_changed() {
             return;
         }
-        
-        // Get the current photo file
-        let file = imp.gallery_model.borrow().item(current_index as u32)
-            .and_downcast::<gio::File>().unwrap();
-        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
+        if let Some(navigation) = imp.navigation.borrow().as_ref() {
+            navigation.refresh_tags();
+        }
+        let filtered = *imp.album_query.borrow() != Query::All || *imp.search_query.borrow() != Query::All;
+        if filtered {
+            self.refilter(gtk::FilterChange::Different);
+        }
+    }
+
+    fn update_save_search(&self) {
+        let enabled = *self.imp().search_query.borrow() != Query::All;
+        if let Some(action) = self.lookup_action("save-search").and_downcast::<gio::SimpleAction>() {
+            action.set_enabled(enabled);
+        }
+    }
+
+    /// Saves the search as a smart album and shows it instead of the
+    /// search results.
+    fn save_search(&self) {
+        let imp = self.imp();
+        let text = imp.search_entry.text();
+        if !Query::parse(&text).is_ok_and(|query| query != Query::All) {
+            return;
+        }
+        let Some(navigation) = imp.navigation.borrow().clone() else {
+            return;
+        };
+        navigation.save_search(&text, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move || {
+                window.imp().search_entry.set_text("");
+                window.imp().search_bar.set_search_mode(false);
+            }
+        ));
+    }
+
+    /// Updates everything that shows albums after one was changed.
+    pub fn albums_changed(&self) {
+        let imp = self.imp();
+        if let Some(navigation) = imp.navigation.borrow().as_ref() {
+            navigation.refresh();
+        }
+        if let Some(album_view) = imp.album_view.borrow().as_ref() {
+            album_view.refresh();
+        }
+        self.update_album_menu();
+    }
+
+    /// Lists the albums in the "Add to Album" menu.
+    fn update_album_menu(&self) {
+        let imp = self.imp();
+        let albums = imp.photo_manager.borrow().albums().to_vec();
+        let existing = gio::Menu::new();
+        for album in &albums {
+            let item = gio::MenuItem::new(Some(album.name.as_str()), None);
+            item.set_action_and_target_value(Some("win.add-to-album"), Some(&album.id.to_variant()));
+            existing.append_item(&item);
+        }
+        let new = gio::Menu::new();
+        new.append(Some(gettext("_New Album…").as_str()), Some("win.add-to-new-album"));
+
+        imp.album_menu.remove_all();
+        imp.album_menu.append_section(None, &existing);
+        imp.album_menu.append_section(None, &new);
+    }
+
+    /// Photos that album and bulk actions apply to: the selection when
+    /// several photos are selected, the current photo otherwise.
+    fn selected_photos(&self) -> Vec<String> {
+        let imp = self.imp();
+        let selection = imp.selection.selection();
+        if selection.size() < 2 {
+            return self.current_photo_path().into_iter().collect();
+        }
+        (0..selection.size())
+            .filter_map(|index| imp.selection.item(selection.nth(index as u32)))
+            .filter_map(|item| item.downcast::<gio::File>().ok()?.path())
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Shows the bulk action bar while several photos are selected. A
+    /// single selected photo becomes the current one.
+    fn on_selection_changed(&self) {
+        let imp = self.imp();
+        let selection = imp.selection.selection();
+        let count = selection.size();
+        if count == 1 {
+            let position = selection.nth(0) as usize;
+            if position != *imp.current_photo_index.borrow() {
+                self.on_photo_selected(position);
+            }
+        }
+        imp.selection_label.set_text(
+            &ngettext("{} photo selected", "{} photos selected", count as u32).replace("{}", &count.to_string()),
+        );
+        imp.selection_revealer.set_reveal_child(count > 1);
+        self.update_remove_tag_menu();
+    }
+
+    /// Lists the tags of the selected photos in the "Remove Tag" menu.
+    fn update_remove_tag_menu(&self) {
+        let imp = self.imp();
+        imp.remove_tag_menu.remove_all();
+        if !imp.selection_revealer.reveals_child() {
+            return;
+        }
+        let tags = imp.photo_manager.borrow().tags_of(&self.selected_photos());
+        for tag in &tags {
+            let item = gio::MenuItem::new(Some(tag.as_str()), None);
+            item.set_action_and_target_value(Some("win.remove-tag-from-selection"), Some(&tag.to_variant()));
+            imp.remove_tag_menu.append_item(&item);
+        }
+        imp.remove_tag_button.set_sensitive(!tags.is_empty());
+    }
+
+    /// Shows the outcome of a bulk operation on the selected photos.
+    fn finish_bulk_operation(&self, result: Result<(), StorageError>, message: &str) {
+        match result {
+            Ok(()) => {
+                self.notes_changed();
+                self.update_current_photo();
+                self.update_remove_tag_menu();
+                let toast = adw::Toast::builder().title(message).use_markup(false).build();
+                self.imp().toast_overlay.add_toast(toast);
+            }
+            Err(err) => self.show_storage_error(&err),
+        }
+    }
+
+    fn tag_selection(&self) {
+        let photos = self.selected_photos();
+        if photos.is_empty() {
+            return;
+        }
+        let entry = gtk::Entry::builder()
+            .placeholder_text(gettext("Tag"))
+            .activates_default(true)
+            .build();
+        let heading = ngettext("Tag {} Photo", "Tag {} Photos", photos.len() as u32)
+            .replace("{}", &photos.len().to_string());
+        let alert = adw::AlertDialog::new(Some(&heading), None);
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("add", &gettext("_Add"))]);
+        alert.set_response_appearance("add", adw::ResponseAppearance::Suggested);
+        alert.set_default_response(Some("add"));
+        alert.set_close_response("cancel");
+        alert.set_response_enabled("add", false);
+        alert.set_extra_child(Some(&entry));
+
+        entry.connect_changed(glib::clone!(
+            #[weak]
+            alert,
+            move |entry| {
+                alert.set_response_enabled("add", !tags::normalize_tag(&entry.text()).is_empty());
+            }
+        ));
+        alert.connect_response(Some("add"), glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            entry,
+            move |_, _| {
+                let tag = tags::normalize_tag(&entry.text());
+                let result = window.imp().photo_manager.borrow_mut().add_tag_to_photos(&photos, &tag);
+                let message = ngettext("Tagged {} photo", "Tagged {} photos", photos.len() as u32)
+                    .replace("{}", &photos.len().to_string());
+                window.finish_bulk_operation(result, &message);
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn remove_tag_from_selection(&self, tag: &str) {
+        let photos = self.selected_photos();
+        let result = self.imp().photo_manager.borrow_mut().remove_tag_from_photos(&photos, tag);
+        let message = gettext("Removed “{}”").replace("{}", tag);
+        self.finish_bulk_operation(result, &message);
+    }
+
+    fn rate_selection(&self, rating: u8) {
+        let photos = self.selected_photos();
+        let result = self.imp().photo_manager.borrow_mut().set_rating(&photos, rating);
+        self.finish_marking(result, &photos);
+    }
+
+    /// Marks the selected photos as favorites, or unmarks them if all of
+    /// them already are.
+    fn toggle_favorite(&self) {
+        let photos = self.selected_photos();
+        let favorite = !self.all_selected(&photos, |note| note.favorite);
+        let result = self.imp().photo_manager.borrow_mut().set_favorite(&photos, favorite);
+        self.finish_marking(result, &photos);
+    }
+
+    /// Flags the selected photos with `flag`, or unflags them if all of
+    /// them already have it.
+    fn toggle_flag(&self, flag: Flag) {
+        let photos = self.selected_photos();
+        let flag = if self.all_selected(&photos, |note| note.flag == flag) { Flag::Unflagged } else { flag };
+        let result = self.imp().photo_manager.borrow_mut().set_flag(&photos, flag);
+        self.finish_marking(result, &photos);
+    }
+
+    fn all_selected<F: Fn(&PhotoNote) -> bool>(&self, photos: &[String], test: F) -> bool {
+        let manager = self.imp().photo_manager.borrow();
+        !photos.is_empty() && photos.iter().all(|path| manager.get_note(path).is_some_and(&test))
+    }
+
+    /// Redraws the badges of `photos` after their rating or flags changed.
+    /// Searches and the sort order may depend on them too.
+    fn finish_marking(&self, result: Result<(), StorageError>, photos: &[String]) {
+        if let Err(err) = result {
+            self.show_storage_error(&err);
+            return;
+        }
+        for photo in photos {
+            self.rebind_photo(photo);
+        }
+        self.notes_changed();
+    }
+
+    fn export_selection(&self) {
+        let photos = self.selected_photos();
+        if photos.is_empty() {
+            return;
+        }
+
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("JSON"));
+        filter.add_mime_type("application/json");
+        filter.add_suffix("json");
+
+        let file_dialog = gtk::FileDialog::builder()
+            .title(gettext("Export Notes"))
+            .modal(true)
+            .initial_name("notia-notes.json")
+            .default_filter(&filter)
+            .build();
+        file_dialog.save(Some(self), gio::Cancellable::NONE, glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |result| {
+                let Some(dest) = result.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let result = window.imp().photo_manager.borrow().export_notes(&photos, &dest);
+                match result {
+                    Ok(()) => {
+                        let message = ngettext("Exported notes of {} photo", "Exported notes of {} photos", photos.len() as u32)
+                            .replace("{}", &photos.len().to_string());
+                        window.imp().toast_overlay.add_toast(adw::Toast::new(&message));
+                    }
+                    Err(err) => window.show_storage_error(&err),
+                }
+            }
+        ));
+    }
+
+    fn confirm_clear_selection_notes(&self) {
+        let photos = self.selected_photos();
+        if photos.is_empty() {
+            return;
+        }
+        let heading = ngettext("Clear Notes of {} Photo?", "Clear Notes of {} Photos?", photos.len() as u32)
+            .replace("{}", &photos.len().to_string());
+        let alert = adw::AlertDialog::new(
+            Some(&heading),
+            Some(&gettext("Their notes and tags are deleted. The photos stay.")),
+        );
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("clear", &gettext("C_lear"))]);
+        alert.set_response_appearance("clear", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+        alert.connect_response(Some("clear"), glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, _| {
+                let result = window.imp().photo_manager.borrow_mut().clear_notes_of(&photos);
+                let message = ngettext("Cleared {} note", "Cleared {} notes", photos.len() as u32)
+                    .replace("{}", &photos.len().to_string());
+                window.finish_bulk_operation(result, &message);
+            }
+        ));
+        alert.present(Some(self));
+    }
+
+    fn add_to_album(&self, id: i64) {
+        let imp = self.imp();
+        let photos = self.selected_photos();
+        if photos.is_empty() {
+            return;
+        }
+        let result = imp.photo_manager.borrow_mut().add_to_album(id, &photos);
+        match result {
+            Ok(added) => {
+                let name = imp.photo_manager.borrow().album(id).map(|album| album.name.clone()).unwrap_or_default();
+                let message = if added == 0 {
+                    gettext("Already in “{}”").replace("{}", &name)
+                } else {
+                    gettext("Added to “{}”").replace("{}", &name)
+                };
+                imp.toast_overlay.add_toast(adw::Toast::builder().title(message).use_markup(false).build());
+                self.albums_changed();
+            }
+            Err(err) => self.show_storage_error(&err),
+        }
+    }
+
+    fn add_to_new_album(&self) {
+        let photos = self.selected_photos();
+        if photos.is_empty() {
+            return;
+        }
+        if let Some(navigation) = self.imp().navigation.borrow().as_ref() {
+            navigation.create_album(photos);
+        }
+    }
+
+    fn filter_photo(&self, item: &glib::Object) -> bool {
+        let Some(path) = item.downcast_ref::<gio::File>().and_then(|file| file.path()) else {
+            return false;
+        };
+        let imp = self.imp();
+        let path = path.to_string_lossy();
+        let manager = imp.photo_manager.borrow();
+        manager.matches_search(&path, &imp.album_query.borrow())
+            && manager.matches_search(&path, &imp.search_query.borrow())
+    }
+
+    fn compare_photos(&self, a: &glib::Object, b: &glib::Object) -> gtk::Ordering {
+        let path = |item: &glib::Object| {
+            item.downcast_ref::<gio::File>()
+                .and_then(|file| file.path())
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        let imp = self.imp();
+        let manager = imp.photo_manager.borrow();
+        imp.sort_order.get().compare(&manager, &path(a), &path(b)).into()
+    }
+
+    pub fn show_preferences(&self) {
+        NotiaPreferencesDialog::new().present(Some(self));
+    }
+
+    pub fn load_photos(&self) {
+        let imp = self.imp();
+        
+        // Clear the gallery model. Folders stay watched during the scan so
+        // that changes made meanwhile are not missed.
+        imp.gallery_model.borrow_mut().remove_all();
+        *imp.current_photo_index.borrow_mut() = 0;
+        self.update_current_photo();
+        
+        // Setup the grid view
+        let factory = gtk::SignalListItemFactory::new();
+        
+        factory.connect_setup(|_, item| {
+            let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+            let picture = gtk::Picture::new();
+            picture.set_content_fit(gtk::ContentFit::Cover);
+            picture.set_size_request(200, 150);
+            let overlay = gtk::Overlay::new();
+            overlay.set_child(Some(&picture));
+            overlay.add_overlay(&PhotoBadges::new().container);
+            item.set_child(Some(&overlay));
+        });
+        
+        factory.connect_bind(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let Some(path) = item.item().and_downcast::<gio::File>().and_then(|file| file.path()) else {
+                    return;
+                };
+                let path = path.to_string_lossy().to_string();
+                window.imp().bound_cells.borrow_mut().insert(path, item.clone());
+                window.bind_cell(item);
+            }
+        ));
+
+        // Görünümden çıkan hücrelerin bekleyen çözümlemelerini iptal et
+        factory.connect_unbind(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, item| {
+                let item = item.downcast_ref::<gtk::ListItem>().unwrap();
+                let Some(path) = item.item().and_downcast::<gio::File>().and_then(|file| file.path()) else {
+                    return;
+                };
+                let path = path.to_string_lossy().to_string();
+                let imp = window.imp();
+                imp.texture_loader.cancel(&path);
+                let mut cells = imp.bound_cells.borrow_mut();
+                if cells.get(&path) == Some(item) {
+                    cells.remove(&path);
+                }
+            }
+        ));
+        
+        imp.gallery_grid.set_factory(Some(&factory));
+        imp.gallery_grid.set_model(Some(&imp.selection));
+        
+        self.start_scan();
+    }
+
+    /// Shows the thumbnail, rating and marks of the photo in a gallery cell.
+    fn bind_cell(&self, item: &gtk::ListItem) {
+        let imp = self.imp();
+        let overlay = item.child().and_downcast::<gtk::Overlay>().unwrap();
+        let picture = overlay.child().and_downcast::<gtk::Picture>().unwrap();
+        let file = item.item().and_downcast::<gio::File>().unwrap();
+        let path = file.path().unwrap_or_default().to_string_lossy().to_string();
+        let manager = imp.photo_manager.borrow();
+        let orientation = manager.orientation(&path);
+        if let Some(badges) = overlay.last_child().and_then(|child| PhotoBadges::from_widget(&child)) {
+            badges.show(manager.get_note(&path));
+        }
+        // Reddedilen fotoğraflar soluk gösterilir
+        if manager.get_note(&path).is_some_and(|note| note.flag == Flag::Reject) {
+            overlay.add_css_class("rejected");
+        } else {
+            overlay.remove_css_class("rejected");
+        }
+        drop(manager);
+
+        // The cell may show another photo by the time the thumbnail is ready
+        let item = item.downgrade();
+        imp.texture_loader.show_thumbnail(&picture, &path, orientation, move || {
+            item.upgrade()
+                .and_then(|item| item.item())
+                .and_downcast::<gio::File>()
+                .as_ref()
+                == Some(&file)
+        });
+    }
+
+    /// Binds the gallery cell of `photo` again, if it is on screen. Unlike
+    /// replacing the model item this keeps the selection.
+    fn rebind_photo(&self, photo: &str) {
+        let item = self.imp().bound_cells.borrow().get(photo).cloned();
+        if let Some(item) = item {
+            self.bind_cell(&item);
+        }
+    }
+
+    /// Binds every gallery cell on screen again, after the notes of any
+    /// number of photos changed at once.
+    fn rebind_visible_cells(&self) {
+        let items: Vec<gtk::ListItem> = self.imp().bound_cells.borrow().values().cloned().collect();
+        for item in items {
+            self.bind_cell(&item);
+        }
+    }
+
+    /// Scans the library on a worker thread. Photos are added to the gallery
+    /// folder by folder while the scan runs.
+    fn start_scan(&self) {
+        let imp = self.imp();
+        let (roots, options, notes) = {
+            let manager = imp.photo_manager.borrow();
+            (manager.library_roots.clone(), manager.scan_options.clone(), manager.note_fingerprints())
+        };
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Some(previous) = imp.scan_cancelled.replace(Some(cancelled.clone())) {
+            previous.store(true, Ordering::Relaxed);
+        }
+
+        let (sender, receiver) = async_channel::unbounded();
+        let stop = cancelled.clone();
+        std::thread::spawn(move || {
+            let scan = photo_manager::scan_library(&roots, &options, &mut |folder, photos| {
+                let photos = photos
+                    .iter()
+                    .map(|photo| (photo.clone(), metadata::read_metadata(Path::new(photo))))
+                    .collect();
+                let message = ScanMessage::Folder(folder.to_path_buf(), photos);
+                sender.send_blocking(message).is_ok() && !stop.load(Ordering::Relaxed)
+            });
+            // Taşınan fotoğrafları bulmak dosya okur, ana iş parçacığında yapılmaz
+            let moved = photo_manager::find_moved_notes(&notes, &scan.photos);
+            let _ = sender.send_blocking(ScanMessage::Finished(scan, moved));
+        });
+
+        imp.scan_label.set_text(&gettext("Scanning…"));
+        imp.cancel_scan_button.set_sensitive(true);
+        imp.scan_progress.set_visible(true);
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            async move {
+                let mut found = 0;
+                while let Ok(message) = receiver.recv().await {
+                    // Yeni bir tarama bunun yerini aldıysa sonuçları at
+                    if !window.is_current_scan(&cancelled) {
+                        break;
+                    }
+                    match message {
+                        ScanMessage::Folder(folder, photos) => {
+                            window.watch_folder(folder);
+                            if photos.is_empty() {
+                                continue;
+                            }
+                            found += photos.len();
+                            window.append_photos(&window.cache_metadata(photos));
+                            let progress = ngettext("Scanning… {} photo", "Scanning… {} photos", found as u32)
+                                .replace("{}", &found.to_string());
+                            window.imp().scan_label.set_text(&progress);
+                        }
+                        ScanMessage::Finished(scan, moved) => {
+                            window.report_unrenderable(&scan.unrenderable);
+                            window.finish_scan(scan, moved);
+                            break;
+                        }
+                    }
+                }
+            }
+        ));
+    }
+
+    /// Tells the user about photos whose format is recognized but cannot be
+    /// displayed, usually because an image loader is not installed.
+    fn report_unrenderable(&self, unrenderable: &[(&str, usize)]) {
+        if unrenderable.is_empty() {
+            return;
+        }
+        let count: usize = unrenderable.iter().map(|(_, count)| count).sum();
+        let names: Vec<&str> = unrenderable.iter().map(|(name, _)| *name).collect();
+
+        let message = ngettext(
+            "{count} photo cannot be displayed ({formats}). An image loader for this format may be missing.",
+            "{count} photos cannot be displayed ({formats}). Image loaders for these formats may be missing.",
+            count as u32,
+        )
+        .replace("{count}", &count.to_string())
+        .replace("{formats}", &names.join(", "));
+        let toast = adw::Toast::builder()
+            .title(message)
+            .use_markup(false)
+            .timeout(10)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    fn is_current_scan(&self, cancelled: &Arc<AtomicBool>) -> bool {
+        self.imp()
+            .scan_cancelled
+            .borrow()
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, cancelled))
+    }
+
+    /// Stops the running scan; the photos found so far are kept.
+    pub fn cancel_scan(&self) {
+        let imp = self.imp();
+        if let Some(cancelled) = imp.scan_cancelled.borrow().as_ref() {
+            cancelled.store(true, Ordering::Relaxed);
+            imp.scan_label.set_text(&gettext("Stopping…"));
+            imp.cancel_scan_button.set_sensitive(false);
+        }
+    }
+
+    fn finish_scan(&self, scan: LibraryScan, moved: MovedNotes) {
+        let imp = self.imp();
+        imp.scan_cancelled.replace(None);
+        imp.scan_progress.set_visible(false);
+
+        let cancelled = scan.cancelled;
+        let scan_result = imp.photo_manager.borrow_mut().finish_scan(scan, moved);
+        if !cancelled {
+            self.unwatch_unscanned();
+        }
+        self.apply_scan_changes();
+        if cancelled {
+            imp.toast_overlay.add_toast(adw::Toast::new(&gettext("Scan stopped, only some photos are shown")));
+        }
+        
+        match scan_result {
+            Ok(relinked) if !relinked.is_empty() => {
+                let message = ngettext(
+                    "Re-linked {} note to a moved photo",
+                    "Re-linked {} notes to moved photos",
+                    relinked.len() as u32,
+                )
+                .replace("{}", &relinked.len().to_string());
+                imp.toast_overlay.add_toast(adw::Toast::new(&message));
+
+                // Taşınan notların rozetleri, sıralama ve arama güncellensin
+                for note in &relinked {
+                    self.rebind_photo(&note.to);
+                }
+                self.notes_changed();
+                let current = self.current_photo_path();
+                if relinked.iter().any(|note| current.as_deref() == Some(note.to.as_str())) {
+                    self.update_current_photo();
+                }
+            }
+            Ok(_) => {}
+            Err(err) => self.show_storage_error(&err),
+        }
+    }
+    
+    fn on_photo_selected(&self, index: usize) {
+        let imp = self.imp();
+        *imp.current_photo_index.borrow_mut() = index;
+        self.update_current_photo();
+    }
+    
+    fn navigate_previous(&self) {
+        let imp = self.imp();
+        let count = imp.sort_model.n_items();
+        if count == 0 {
+            return;
+        }
+        
+        let current = *imp.current_photo_index.borrow();
+        if current > 0 {
+            self.on_photo_selected(current - 1);
+        }
+    }
+    
+    fn navigate_next(&self) {
+        let imp = self.imp();
+        let count = imp.sort_model.n_items();
+        if count == 0 {
+            return;
+        }
+        
+        let current = *imp.current_photo_index.borrow();
+        if current < count as usize - 1 {
+            self.on_photo_selected(current + 1);
+        }
+    }
+
+    pub fn update_current_photo(&self) {
+        let imp = self.imp();
+        let current_index = *imp.current_photo_index.borrow();
+        let count = imp.sort_model.n_items();
+        
+        self.update_navigation();
+        if count == 0 {
+            // Update sidebar with empty data
+            if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
+                sidebar.update_sidebar(crate::sidebar::SidebarData {
+                    photo_path: None,
+                    photo_name: Some(gettext("No photos available")),
+                    note_text: Some("".to_string()),
+                    note_status: Some(gettext("No photos to add notes to")),
+                    tags: None,
+                    orientation: Orientation::NORMAL,
+                });
+                sidebar.set_metadata(None);
+            }
+            return;
+        }
+        
+        // Get the current photo file
+        let file = imp.sort_model.item(current_index as u32)
+            .and_downcast::<gio::File>().unwrap();
+        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
+        
+        // Get photo name
+        let photo_name = std::path::Path::new(&photo_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&gettext("Unknown File"))
+            .to_string();
+        
+        // Get note info
+        let (note_text, note_status) = {
+            let manager = imp.photo_manager.borrow();
+            if let Some(note) = manager.get_note(&photo_path) {
+                (note.note.clone(), format!("{}: {}", gettext("Note"), note.timestamp))
+            } else {
+                (String::new(), gettext("No note added yet"))
+            }
+        };
+        
+        // Update sidebar with photo data
+        if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
+            let (tags, orientation) = {
+                let manager = imp.photo_manager.borrow();
+                (manager.get_tags(&photo_path), manager.orientation(&photo_path))
+            };
+            
+            // Set current photo path for tag operations
+            sidebar.set_current_photo_path(photo_path.clone());
+            
+            sidebar.update_sidebar(crate::sidebar::SidebarData {
+                photo_path: Some(photo_path.clone()),
+                photo_name: Some(photo_name.clone()),
+                note_text: Some(note_text.clone()),
+                note_status: Some(note_status.clone()),
+                tags: Some(tags),
+                orientation,
+            });
+        }
+        self.show_metadata(&photo_path);
+    }
+
+    fn current_photo_path(&self) -> Option<String> {
+        let imp = self.imp();
+        let index = *imp.current_photo_index.borrow();
+        let file = imp.sort_model.item(index as u32).and_downcast::<gio::File>()?;
+        Some(file.path()?.to_string_lossy().to_string())
+    }
+
+    /// Shows the metadata of a photo in the sidebar, reading it in the
+    /// background the first time.
+    fn show_metadata(&self, photo_path: &str) {
+        let imp = self.imp();
+        let Some(sidebar) = imp.sidebar.borrow().clone() else {
+            return;
+        };
+        if let Some(metadata) = imp.photo_manager.borrow().cached_metadata(photo_path) {
+            sidebar.set_metadata(Some(metadata));
+            return;
+        }
+        sidebar.set_metadata(None);
+
+        let path = photo_path.to_string();
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            async move {
+                let file_path = PathBuf::from(&path);
+                let Ok(metadata) = gio::spawn_blocking(move || metadata::read_metadata(&file_path)).await else {
+                    return;
+                };
+                window.imp().photo_manager.borrow_mut().cache_metadata(path.clone(), metadata.clone());
+                if window.current_photo_path().as_deref() == Some(path.as_str()) {
+                    sidebar.set_metadata(Some(&metadata));
+                }
+            }
+        ));
+    }
+
+    /// Updates the photo counter and navigation buttons.
+    fn update_navigation(&self) {
+        let imp = self.imp();
+        let current_index = *imp.current_photo_index.borrow();
+        let count = imp.sort_model.n_items() as usize;
+
+        if count == 0 {
+            imp.photo_counter.set_text("0 / 0");
+        } else {
+            imp.photo_counter.set_text(&format!("{} / {}", current_index + 1, count));
+        }
+        imp.prev_button.set_sensitive(current_index > 0);
+        imp.next_button.set_sensitive(current_index + 1 < count);
+    }
+
+    /// Stops watching folders that the last scan did not read, such as
+    /// folders left over from a library root that was removed.
+    fn unwatch_unscanned(&self) {
+        let imp = self.imp();
+        let scanned: HashSet<PathBuf> = imp.photo_manager.borrow().scanned_folders.iter().cloned().collect();
+        imp.file_monitors.borrow_mut().retain(|folder, monitor| {
+            let keep = scanned.contains(folder);
+            if !keep {
+                monitor.cancel();
+            }
+            keep
+        });
+    }
+
+    /// Looks again at the paths that changed while the scan ran, since the
+    /// scan may have read their folder before or after the change.
+    fn apply_scan_changes(&self) {
+        let imp = self.imp();
+        let changes = imp.scan_changes.take();
+        let mut seen = HashSet::new();
+        for path in changes {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            if path.exists() {
+                self.reload_thumbnail(&path);
+                let added = imp.photo_manager.borrow_mut().add_path(&path);
+                self.insert_photos(added);
+            } else {
+                let removed = imp.photo_manager.borrow_mut().remove_path(&path);
+                self.remove_photos(&path, &removed);
+            }
+        }
+    }
+
+    fn watch_folder(&self, folder: PathBuf) {
+        let imp = self.imp();
+        if imp.file_monitors.borrow().contains_key(&folder) {
+            return;
+        }
+
+        let monitor = match gio::File::for_path(&folder)
+            .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+        {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                if !imp.watch_error_shown.replace(true) {
+                    let message = gettext("Changes in {folder} are only noticed on the next scan: {error}")
+                        .replace("{error}", &err.to_string())
+                        .replace("{folder}", &folder.display().to_string());
+                    let toast = adw::Toast::builder().title(message).use_markup(false).timeout(10).build();
+                    imp.toast_overlay.add_toast(toast);
+                }
+                return;
+            }
+        };
+        monitor.connect_changed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_, file, other_file, event| {
+                window.on_library_changed(file, other_file, event);
+            }
+        ));
+        imp.file_monitors.borrow_mut().insert(folder, monitor);
+    }
+
+    fn on_library_changed(&self, file: &gio::File, other_file: Option<&gio::File>, event: gio::FileMonitorEvent) {
+        let Some(path) = file.path() else {
+            return;
+        };
+        let imp = self.imp();
+        if imp.scan_cancelled.borrow().is_some() {
+            let mut changes = imp.scan_changes.borrow_mut();
+            changes.push(path);
+            changes.extend(other_file.and_then(|file| file.path()));
+            return;
+        }
+        match event {
+            gio::FileMonitorEvent::Created
+            | gio::FileMonitorEvent::MovedIn
+            | gio::FileMonitorEvent::ChangesDoneHint => {
+                if event == gio::FileMonitorEvent::ChangesDoneHint {
+                    self.reload_thumbnail(&path);
+                }
+                let added = imp.photo_manager.borrow_mut().add_path(&path);
+                self.insert_photos(added);
+            }
+            gio::FileMonitorEvent::Deleted | gio::FileMonitorEvent::MovedOut => {
+                let removed = imp.photo_manager.borrow_mut().remove_path(&path);
+                self.remove_photos(&path, &removed);
+            }
+            gio::FileMonitorEvent::Renamed => {
+                if let Some(to) = other_file.and_then(|file| file.path()) {
+                    self.rename_photos(&path, &to);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Adds newly found photos to the gallery once their metadata is read,
+    /// and watches new folders.
+    fn insert_photos(&self, added: LibraryScan) {
+        for folder in added.folders {
+            self.watch_folder(folder);
+        }
+        if added.photos.is_empty() {
+            return;
+        }
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            async move {
+                let photos = added.photos;
+                let Ok(read) = gio::spawn_blocking(move || {
+                    photos
+                        .into_iter()
+                        .map(|photo| {
+                            let metadata = metadata::read_metadata(Path::new(&photo));
+                            (photo, metadata)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await
+                else {
+                    return;
+                };
+                window.append_photos(&window.cache_metadata(read));
+            }
+        ));
+    }
+
+    /// Stores metadata read in the background and returns the photos it
+    /// belongs to.
+    fn cache_metadata(&self, read: Vec<(String, PhotoMetadata)>) -> Vec<String> {
+        let mut manager = self.imp().photo_manager.borrow_mut();
+        read.into_iter()
+            .map(|(photo, metadata)| {
+                manager.cache_metadata(photo.clone(), metadata);
+                photo
+            })
+            .collect()
+    }
+
+    fn append_photos(&self, photos: &[String]) {
+        if photos.is_empty() {
+            return;
+        }
+
+        let model = self.imp().gallery_model.borrow().clone();
+        let was_empty = model.n_items() == 0;
+        let current = self.current_photo_path();
+        let files: Vec<gio::File> = photos.iter().map(gio::File::for_path).collect();
+        model.splice(model.n_items(), 0, &files);
+
+        if was_empty {
+            self.update_current_photo();
+        } else {
+            self.follow_photo(current.as_deref());
+        }
+    }
+
+    /// Keeps `photo` current after the gallery order changed. Only the
+    /// index moves; the sidebar still shows the same photo.
+    fn follow_photo(&self, photo: Option<&str>) {
+        if let Some(position) = photo.and_then(|photo| self.view_position(photo)) {
+            *self.imp().current_photo_index.borrow_mut() = position as usize;
+        }
+        self.update_navigation();
+    }
+
+    /// Position of `photo` in the gallery as it is sorted on screen.
+    fn view_position(&self, photo: &str) -> Option<u32> {
+        let imp = self.imp();
+        if let Some(positions) = imp.view_positions.borrow().as_ref() {
+            return positions.get(photo).copied();
+        }
+        let model = &imp.sort_model;
+        let positions: HashMap<String, u32> = (0..model.n_items())
+            .filter_map(|position| {
+                let path = model.item(position).and_downcast::<gio::File>()?.path()?;
+                Some((path.to_string_lossy().to_string(), position))
+            })
+            .collect();
+        let position = positions.get(photo).copied();
+        imp.view_positions.replace(Some(positions));
+        position
+    }
+
+    /// Removes photos that disappeared below `path` from the gallery while
+    /// keeping the current photo where possible.
+    fn remove_photos(&self, path: &Path, removed: &[String]) {
+        let imp = self.imp();
+        imp.file_monitors.borrow_mut().retain(|folder, monitor| {
+            let keep = !folder.starts_with(path);
+            if !keep {
+                monitor.cancel();
+            }
+            keep
+        });
+        if removed.is_empty() {
+            return;
+        }
+
+        let model = imp.gallery_model.borrow().clone();
+        let current = self.current_photo_path();
+        // Sondan başa silinir ki konumlar kaymasın
+        let mut positions: Vec<u32> = removed.iter().filter_map(|photo| self.model_position(photo)).collect();
+        positions.sort_unstable_by(|a, b| b.cmp(a));
+        for position in positions {
+            model.remove(position);
+        }
+        for photo in removed {
+            imp.texture_loader.forget(photo);
+        }
+
+        match current.as_deref().and_then(|photo| self.view_position(photo)) {
+            Some(position) => {
+                *imp.current_photo_index.borrow_mut() = position as usize;
+                self.update_navigation();
+            }
+            // Geçerli fotoğraf silindi, aynı konumdakini göster
+            None => {
+                let count = model.n_items() as usize;
+                {
+                    let mut index = imp.current_photo_index.borrow_mut();
+                    *index = (*index).min(count.saturating_sub(1));
+                }
+                self.update_current_photo();
+            }
+        }
+    }
+
+    /// Follows a rename inside the library, keeping renamed photos at their
+    /// place in the gallery and their notes attached.
+    fn rename_photos(&self, from: &Path, to: &Path) {
+        let imp = self.imp();
+        let (removed, mut added, result) = {
+            let mut manager = imp.photo_manager.borrow_mut();
+            manager.rename_metadata(from, to);
+            let removed = manager.remove_path(from);
+            let added = manager.add_path(to);
+            let result = manager.relink_renamed(from, to, &removed);
+            (removed, added, result)
+        };
+        if let Err(err) = &result {
+            self.show_storage_error(err);
+        }
+
+        let model = imp.gallery_model.borrow().clone();
+        let mut current = self.current_photo_path();
+        let mut gone = Vec::new();
+        let mut current_renamed = false;
+        let mut unmatched: HashSet<String> = added.photos.iter().cloned().collect();
+        for old in removed {
+            let renamed = photo_manager::renamed_path(from, to, &old).filter(|new| unmatched.remove(new));
+            match (self.model_position(&old), renamed) {
+                (Some(position), Some(new)) => {
+                    model.splice(position, 1, &[gio::File::for_path(&new)]);
+                    imp.texture_loader.forget(&old);
+                    if current.as_deref() == Some(old.as_str()) {
+                        current = Some(new);
+                        current_renamed = true;
+                    }
+                }
+                _ => gone.push(old),
+            }
+        }
+
+        // Ada göre sıralıyken yeniden adlandırılan fotoğraf yer değiştirebilir
+        self.follow_photo(current.as_deref());
+        if imp.selection.selection().size() < 2 {
+            imp.selection.select_item(*imp.current_photo_index.borrow() as u32, true);
+        }
+
+        added.photos.retain(|photo| unmatched.contains(photo));
+        self.remove_photos(from, &gone);
+        self.insert_photos(added);
+        if current_renamed {
+            self.update_current_photo();
+        }
+    }
+
+    /// Redraws the tile of a photo whose content changed on disk, and
+    /// sorts it again once its metadata is read. Editors often write a file
+    /// several times, so the photos changed within a short time are
+    /// reloaded together.
+    fn reload_thumbnail(&self, path: &Path) {
+        let imp = self.imp();
+        let photo = path.to_string_lossy().to_string();
+        if self.model_position(&photo).is_none() {
+            return;
+        }
+        imp.pending_reloads.borrow_mut().insert(photo);
+        if imp.reload_source.borrow().is_some() {
+            return;
+        }
+        let source = glib::timeout_add_local_once(
+            std::time::Duration::from_millis(300),
+            glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                move || {
+                    window.imp().reload_source.take();
+                    window.reload_pending_thumbnails();
+                }
+            ),
+        );
+        *imp.reload_source.borrow_mut() = Some(source);
+    }
+
+    fn reload_pending_thumbnails(&self) {
+        let imp = self.imp();
+        let photos: Vec<String> = imp.pending_reloads.take().into_iter().collect();
+        for photo in &photos {
+            imp.texture_loader.forget(photo);
+            imp.photo_manager.borrow_mut().forget_metadata(photo);
+        }
+
+        glib::spawn_future_local(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            async move {
+                let Ok(read) = gio::spawn_blocking(move || {
+                    photos
+                        .into_iter()
+                        .map(|photo| {
+                            let metadata = metadata::read_metadata(Path::new(&photo));
+                            (photo, metadata)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await
+                else {
+                    return;
+                };
+                let photos = window.cache_metadata(read);
+                let current = window.current_photo_path();
+                for photo in &photos {
+                    window.rebind_photo(photo);
+                }
+                // Çekim tarihi değişmiş olabilir
+                window.imp().sorter.changed(gtk::SorterChange::Different);
+                window.follow_photo(current.as_deref());
+                if let Some(current) = current.filter(|current| photos.contains(current)) {
+                    window.show_metadata(&current);
+                }
+            }
+        ));
+    }
+
+    /// Turns the current photo by `transform`. Only the stored override
+    /// changes; the file is left as it is.
+    pub fn transform_current_photo(&self, transform: Orientation) {
+        let imp = self.imp();
+        let Some(photo_path) = self.current_photo_path() else {
+            return;
+        };
+        let result = imp.photo_manager.borrow_mut().transform_photo(&photo_path, transform);
+        if let Err(err) = result {
+            self.show_storage_error(&err);
+            return;
+        }
+
+        let orientation = imp.photo_manager.borrow().orientation(&photo_path);
+        if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
+            sidebar.set_preview(&photo_path, orientation);
+        }
+        imp.texture_loader.forget(&photo_path);
+        self.rebind_photo(&photo_path);
+    }
+
+    /// Position of `photo` in the unfiltered gallery model.
+    fn model_position(&self, photo: &str) -> Option<u32> {
+        let imp = self.imp();
+        let model = imp.gallery_model.borrow().clone();
+        let path_at = |position: u32| {
+            model
+                .item(position)
+                .and_downcast::<gio::File>()
+                .and_then(|file| file.path())
+                .map(|path| path.to_string_lossy().to_string())
+        };
+
+        let cached = imp.model_positions.borrow().as_ref().map(|positions| positions.get(photo).copied());
+        match cached {
+            Some(None) => return None,
+            // Yerine başka fotoğraf konmuşsa dizin eskidir
+            Some(Some(position)) if path_at(position).as_deref() == Some(photo) => return Some(position),
+            _ => {}
+        }
+        let positions: HashMap<String, u32> = (0..model.n_items())
+            .filter_map(|position| Some((path_at(position)?, position)))
+            .collect();
+        let position = positions.get(photo).copied();
+        imp.model_positions.replace(Some(positions));
+        position
+    }
+
+    /// Keeps `model_positions` in step with the gallery model. Appended and
+    /// replaced photos are added to it; any other change drops it.
+    fn update_model_positions(&self, model: &gio::ListStore, position: u32, removed: u32, added: u32) {
+        let mut positions = self.imp().model_positions.borrow_mut();
+        let Some(index) = positions.as_mut() else {
+            return;
+        };
+        let appended = removed == 0 && position + added == model.n_items();
+        if !appended && removed != added {
+            *positions = None;
+            return;
+        }
+        for position in position..position + added {
+            if let Some(path) = model.item(position).and_downcast::<gio::File>().and_then(|file| file.path()) {
+                index.insert(path.to_string_lossy().to_string(), position);
+            }
+        }
+    }
+
+    fn save_current_note(&self) {
+        let imp = self.imp();
+        let current_index = *imp.current_photo_index.borrow();
+        let count = imp.sort_model.n_items();
+        
+        if count == 0 {
+            return;
+        }
+        
+        // Get the current photo file
+        let file = imp.sort_model.item(current_index as u32)
+            .and_downcast::<gio::File>().unwrap();
+        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
+        
+        // Get note text from sidebar
+        let mut result = Ok(());
+        if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
+            let note_text = sidebar.get_note_text();
+            
+            // Save note while preserving existing tags
+            if !note_text.trim().is_empty() {
+                let mut manager = imp.photo_manager.borrow_mut();
+                
+                // Get current tags from sidebar (including newly added ones)
+                let current_tags = sidebar.get_current_tags();
+                
+                // Save note with current tags in a single write
+                result = manager.set_note(&photo_path, note_text.to_string(), current_tags);
+            }
+        }
+        if let Err(err) = result {
+            self.show_storage_error(&err);
+            return;
+        }
+        self.notes_changed();
+        
+        // Show toast
+        let toast = adw::Toast::new(&gettext("Note saved successfully"));
+        imp.toast_overlay.add_toast(toast);
+    }
+    
+    fn clear_current_note(&self) {
+        let imp = self.imp();
+        let current_index = *imp.current_photo_index.borrow();
+        let count = imp.sort_model.n_items();
+        
+        if count == 0 {
+            return;
+        }
+        
+        // Get the current photo file
+        let file = imp.sort_model.item(current_index as u32)
+            .and_downcast::<gio::File>().unwrap();
+        let photo_path = file.path().unwrap_or_default().to_string_lossy().to_string();
         
         // Clear note
-        {
+        let result = {
             let mut manager = imp.photo_manager.borrow_mut();
-            manager.remove_note(&photo_path);
-        } // borrow burada biter
+            manager.remove_note(&photo_path)
+        }; // borrow burada biter
+        if let Err(err) = result {
+            self.show_storage_error(&err);
+            return;
+        }
+        self.notes_changed();
         
         // Clear text view in sidebar
         if let Some(sidebar) = imp.sidebar.borrow().as_ref() {
@@ -403,9 +2029,15 @@ impl NotiaWindow {
     }
 
     pub fn clear_all_notes(&self) {
+        self.backup_notes();
+
         let imp = self.imp();
-        let mut manager = imp.photo_manager.borrow_mut();
-        manager.clear_notes();
+        let result = imp.photo_manager.borrow_mut().clear_notes();
+        if let Err(err) = result {
+            self.show_storage_error(&err);
+            return;
+        }
+        self.notes_changed();
         
         // Clear text view if it contains a note for the current photo
         // let buffer = imp.note_text_view.buffer();
@@ -420,4 +2052,277 @@ impl NotiaWindow {
         let toast = adw::Toast::new(&gettext("All notes cleared"));
         imp.toast_overlay.add_toast(toast);
     }
+
+    /// Shows the errors hit while loading the notes in the storage banner.
+    fn report_load_errors(&self) {
+        let imp = self.imp();
+        let (errors, safe_mode) = {
+            let mut manager = imp.photo_manager.borrow_mut();
+            (manager.take_load_errors(), manager.safe_mode)
+        };
+        if errors.is_empty() {
+            return;
+        }
+
+        let details: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+
+        let title = if safe_mode {
+            gettext("Notes could not be loaded. Changes will only be kept until Notia is closed.")
+        } else if errors.iter().any(|err| matches!(err, StorageError::Corrupt { .. })) {
+            gettext("A damaged notes file was moved aside instead of being overwritten.")
+        } else {
+            gettext("Some notes could not be loaded.")
+        };
+        imp.storage_banner.set_title(&title);
+        imp.storage_banner.set_tooltip_text(Some(&details.join("\n")));
+        imp.storage_banner.set_revealed(true);
+    }
+
+    pub fn show_storage_error(&self, err: &StorageError) {
+        let toast = adw::Toast::builder()
+            .title(format!("{}: {}", gettext("Could not update notes"), err))
+            .use_markup(false)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Shows `err` for `widget`: as a toast of the window it is in, or in
+    /// an alert while it is not part of a Notia window.
+    pub fn report_storage_error(widget: &impl IsA<gtk::Widget>, err: &StorageError) {
+        let root = widget.root();
+        if let Some(window) = root.clone().and_downcast::<NotiaWindow>() {
+            window.show_storage_error(err);
+            return;
+        }
+        let alert = adw::AlertDialog::new(Some(&gettext("Could not update notes")), Some(&err.to_string()));
+        alert.add_response("close", &gettext("_Close"));
+        alert.present(root.as_ref());
+    }
+
+    /// Takes a rotating backup of the notes database, keeping as many
+    /// copies as the `backup-count` setting allows.
+    fn backup_notes(&self) {
+        let imp = self.imp();
+        let keep = imp.settings.int("backup-count").max(1) as usize;
+        let result = {
+            let manager = imp.photo_manager.borrow();
+            if manager.safe_mode || manager.notes.is_empty() {
+                return;
+            }
+            manager.create_backup(keep)
+        };
+        if let Err(err) = result {
+            let toast = adw::Toast::builder()
+                .title(format!("{}: {}", gettext("Could not back up notes"), err))
+                .use_markup(false)
+                .build();
+            imp.toast_overlay.add_toast(toast);
+        }
+    }
+
+    /// Backs up the notes on the first launch of the day. Other backups are
+    /// only taken before notes are cleared or replaced.
+    fn backup_notes_daily(&self) {
+        let last = self
+            .imp()
+            .photo_manager
+            .borrow()
+            .list_backups()
+            .first()
+            .and_then(|backup| storage::backup_time(backup));
+        let today = chrono::Local::now().date_naive();
+        if last.is_none_or(|time| time.date() < today) {
+            self.backup_notes();
+        }
+    }
+
+    pub fn show_orphaned_notes(&self) {
+        let dialog = OrphanedNotesDialog::new(self.imp().photo_manager.clone());
+        dialog.connect_closed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |dialog| {
+                for photo in dialog.relinked_photos() {
+                    window.rebind_photo(&photo);
+                }
+                window.notes_changed();
+                window.update_current_photo();
+            }
+        ));
+        dialog.present(Some(self));
+    }
+
+    pub fn show_tag_manager(&self) {
+        let dialog = TagManagerDialog::new(self.imp().photo_manager.clone());
+        dialog.connect_closed(glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.notes_changed();
+                window.update_current_photo();
+                window.update_remove_tag_menu();
+            }
+        ));
+        dialog.present(Some(self));
+    }
+
+    pub fn show_restore_dialog(&self) {
+        let backups = self.imp().photo_manager.borrow().list_backups();
+        if backups.is_empty() {
+            let toast = adw::Toast::new(&gettext("No backups available"));
+            self.imp().toast_overlay.add_toast(toast);
+            return;
+        }
+
+        let dialog = adw::Dialog::builder()
+            .title(gettext("Restore from Backup"))
+            .content_width(420)
+            .content_height(480)
+            .build();
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+        list.add_css_class("boxed-list");
+
+        for backup in backups {
+            let title = storage::backup_time(&backup)
+                .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| backup.file_name().unwrap_or_default().to_string_lossy().to_string());
+            let size = std::fs::metadata(&backup).map(|m| m.len()).unwrap_or(0);
+
+            let row = adw::ActionRow::builder()
+                .title(title)
+                .subtitle(glib::format_size(size))
+                .activatable(true)
+                .build();
+            row.add_suffix(&gtk::Image::from_icon_name("go-next-symbolic"));
+            row.connect_activated(glib::clone!(
+                #[weak(rename_to = window)]
+                self,
+                #[weak]
+                dialog,
+                move |_| {
+                    window.confirm_restore(&dialog, &backup);
+                }
+            ));
+            list.append(&row);
+        }
+
+        let scrolled = gtk::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk::PolicyType::Never)
+            .vexpand(true)
+            .child(&list)
+            .build();
+        list.set_margin_start(12);
+        list.set_margin_end(12);
+        list.set_margin_top(12);
+        list.set_margin_bottom(12);
+
+        let toolbar = adw::ToolbarView::new();
+        toolbar.add_top_bar(&adw::HeaderBar::new());
+        toolbar.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar));
+        dialog.present(Some(self));
+    }
+
+    fn confirm_restore(&self, dialog: &adw::Dialog, backup: &Path) {
+        let alert = adw::AlertDialog::new(
+            Some(&gettext("Restore Backup?")),
+            Some(&gettext("All current notes and tags will be replaced. A backup of them is taken first.")),
+        );
+        alert.add_responses(&[("cancel", &gettext("_Cancel")), ("restore", &gettext("_Restore"))]);
+        alert.set_response_appearance("restore", adw::ResponseAppearance::Destructive);
+        alert.set_default_response(Some("cancel"));
+        alert.set_close_response("cancel");
+
+        let backup: PathBuf = backup.to_path_buf();
+        alert.connect_response(Some("restore"), glib::clone!(
+            #[weak(rename_to = window)]
+            self,
+            #[weak]
+            dialog,
+            move |_, _| {
+                window.restore_backup(&backup);
+                dialog.close();
+            }
+        ));
+        alert.present(Some(dialog));
+    }
+
+    fn restore_backup(&self, backup: &Path) {
+        self.backup_notes();
+
+        let imp = self.imp();
+        let result = imp.photo_manager.borrow_mut().restore_backup(backup);
+        let message = match result {
+            Ok(()) => gettext("Backup restored"),
+            Err(err) => format!("{}: {}", gettext("Could not restore backup"), err),
+        };
+        self.albums_changed();
+        self.refresh_search();
+        // Restored notes change what is shown and how it is sorted, and
+        // thumbnails may have been rotated
+        imp.texture_loader.clear();
+        self.rebind_visible_cells();
+        imp.sorter.changed(gtk::SorterChange::Different);
+        self.refilter(gtk::FilterChange::Different);
+        self.update_current_photo();
+        let toast = adw::Toast::builder().title(message).use_markup(false).build();
+        imp.toast_overlay.add_toast(toast);
+    }
+}
+
+/// Rating stars and mark icons shown in the corner of a gallery cell.
+struct PhotoBadges {
+    container: gtk::Box,
+    stars: gtk::Label,
+    favorite: gtk::Image,
+    flag: gtk::Image,
+}
+
+impl PhotoBadges {
+    fn new() -> Self {
+        let container = gtk::Box::builder()
+            .spacing(4)
+            .halign(gtk::Align::End)
+            .valign(gtk::Align::End)
+            .margin_end(6)
+            .margin_bottom(6)
+            .css_classes(["photo-badges"])
+            .build();
+        let stars = gtk::Label::new(None);
+        let favorite = gtk::Image::from_icon_name("emblem-favorite-symbolic");
+        let flag = gtk::Image::new();
+        container.append(&stars);
+        container.append(&favorite);
+        container.append(&flag);
+        Self { container, stars, favorite, flag }
+    }
+
+    /// The badges built by [`PhotoBadges::new`] inside `widget`.
+    fn from_widget(widget: &gtk::Widget) -> Option<Self> {
+        let container = widget.clone().downcast::<gtk::Box>().ok()?;
+        let stars = container.first_child().and_downcast::<gtk::Label>()?;
+        let favorite = stars.next_sibling().and_downcast::<gtk::Image>()?;
+        let flag = favorite.next_sibling().and_downcast::<gtk::Image>()?;
+        Some(Self { container, stars, favorite, flag })
+    }
+
+    fn show(&self, note: Option<&PhotoNote>) {
+        let rating = note.map_or(0, |note| note.rating);
+        let favorite = note.is_some_and(|note| note.favorite);
+        let flag = note.map_or(Flag::Unflagged, |note| note.flag);
+
+        self.stars.set_text(&"★".repeat(rating as usize));
+        self.stars.set_visible(rating > 0);
+        self.favorite.set_visible(favorite);
+        match flag {
+            Flag::Pick => self.flag.set_icon_name(Some("object-select-symbolic")),
+            Flag::Reject => self.flag.set_icon_name(Some("action-unavailable-symbolic")),
+            Flag::Unflagged => self.flag.set_icon_name(None),
+        }
+        self.flag.set_visible(flag != Flag::Unflagged);
+        self.container.set_visible(rating > 0 || favorite || flag != Flag::Unflagged);
+    }
 }
+